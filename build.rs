@@ -0,0 +1,30 @@
+use std::env;
+use std::io;
+use std::path::PathBuf;
+use clap::CommandFactory;
+
+#[allow(dead_code)]
+#[path = "src/cli.rs"]
+mod cli;
+
+/// Generates man pages for obs2web and each subcommand into `$OUT_DIR/man`, and
+/// also into `$OBS2WEB_MAN_DIR` when set, for packaging.
+fn main() -> io::Result<()> {
+    println!("cargo:rerun-if-changed=src/cli.rs");
+    println!("cargo:rerun-if-env-changed=OBS2WEB_MAN_DIR");
+    // Library-only builds, e.g. for WebAssembly, have no command line to document
+    if env::var_os("CARGO_FEATURE_CLI").is_none() {
+        return Ok(());
+    }
+
+    let out_dir = env::var_os("OUT_DIR").ok_or_else(|| io::Error::other("OUT_DIR not set"))?;
+    let mut dirs = vec![PathBuf::from(out_dir).join("man")];
+    if let Some(dir) = env::var_os("OBS2WEB_MAN_DIR") {
+        dirs.push(PathBuf::from(dir));
+    }
+    for dir in dirs {
+        std::fs::create_dir_all(&dir)?;
+        clap_mangen::generate_to(cli::Args::command(), &dir)?;
+    }
+    Ok(())
+}
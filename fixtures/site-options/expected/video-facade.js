@@ -0,0 +1,15 @@
+// Swaps a video facade for the player's iframe when it is clicked
+document.addEventListener('click', function (e) {
+    const link = e.target.closest('.video-facade a');
+    if (!link) {
+        return;
+    }
+    e.preventDefault();
+    const facade = link.closest('.video-facade');
+    const iframe = document.createElement('iframe');
+    iframe.src = facade.dataset.player;
+    iframe.title = facade.dataset.title;
+    iframe.allow = 'autoplay; fullscreen; picture-in-picture; encrypted-media';
+    iframe.allowFullscreen = true;
+    facade.replaceChildren(iframe);
+});
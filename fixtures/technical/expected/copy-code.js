@@ -0,0 +1,18 @@
+// Copies the code of a block when its header's copy button is clicked
+document.addEventListener('click', async function (e) {
+    const button = e.target.closest('.copy-code');
+    if (!button) {
+        return;
+    }
+    const code = button.closest('.code-block').querySelector('code');
+    // Line numbers are generated content, so innerText leaves them out
+    try {
+        await navigator.clipboard.writeText(code.innerText);
+        button.textContent = 'Copied!';
+    } catch (err) {
+        button.textContent = 'Copy failed';
+    }
+    setTimeout(function () {
+        button.textContent = 'Copy';
+    }, 2000);
+});
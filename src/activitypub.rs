@@ -0,0 +1,137 @@
+use std::io;
+use std::path::Path;
+use chrono::SecondsFormat;
+use comrak::{Anchorizer, ComrakOptions};
+use serde_json::{json, Value};
+use tera::Tera;
+use crate::config::Config;
+use crate::content::{absolute_url, note_html, standalone_html};
+use crate::dates::{parse_date, recent_notes, site_timezone};
+use crate::diagnostics::{info, warning};
+use crate::index::{IndexedNote, SiteIndex};
+use crate::vfs::OutputSink;
+
+/// Audience of public posts.
+const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+
+/// Elements left out of posts: fediverse servers drop scripts and players anyway,
+/// and heading anchors are empty links.
+const REMOVED_ELEMENTS: &str = "script, style, iframe, form, button, input, a.anchor, .copy-code";
+
+/// Writes the site's ActivityPub actor (`actor.json`), its outbox of the newest
+/// dated notes (`outbox.json`), and `.well-known/webfinger` to find the actor by
+/// `@username@host`. The site only publishes these; accepting follows and
+/// delivering posts is left to the service at `activitypub_inbox`.
+pub fn write_activitypub(
+    notes: &[IndexedNote],
+    index: &SiteIndex,
+    tera: &Tera,
+    comrak_options: &ComrakOptions,
+    output: &dyn OutputSink,
+    config: &Config,
+) -> io::Result<()> {
+    let Some(site_url) = &config.site_url else {
+        warning!("activitypub needs site_url to be set; no actor or outbox will be written");
+        return Ok(());
+    };
+    if config.activitypub_inbox.is_none() {
+        warning!("activitypub_inbox is not set; the site can be found from the fediverse but not followed");
+    }
+    let site_url = site_url.trim_end_matches('/');
+    let actor_id = format!("{site_url}/actor.json");
+    let outbox_id = format!("{site_url}/outbox.json");
+    let username = &config.activitypub_username;
+    let host = site_url.split_once("://").map_or(site_url, |(_, rest)| rest);
+    let host = host.split('/').next().unwrap_or(host);
+
+    let mut actor = json!({
+        "@context": ["https://www.w3.org/ns/activitystreams", "https://w3id.org/security/v1"],
+        "id": actor_id,
+        "type": "Person",
+        "preferredUsername": username,
+        "name": config.site_name.as_deref().unwrap_or("Notes"),
+        "url": format!("{site_url}/"),
+        "outbox": outbox_id,
+    });
+    if let Some(inbox) = &config.activitypub_inbox {
+        actor["inbox"] = json!(inbox);
+    }
+    if let Some(key) = &config.activitypub_public_key {
+        actor["publicKey"] = json!({
+            "id": format!("{actor_id}#main-key"),
+            "owner": actor_id,
+            "publicKeyPem": key,
+        });
+    }
+    if config.icon.is_some() {
+        actor["icon"] = json!({ "type": "Image", "mediaType": "image/png", "url": format!("{site_url}/icon-512.png") });
+    }
+
+    let tz = site_timezone(config)?;
+    let mut items = Vec::new();
+    for recent in recent_notes(notes, None, tz).into_iter().take(config.activitypub_limit) {
+        let note = recent.note;
+        let url = absolute_url(&note.url, config);
+        let published = note
+            .frontmatter
+            .date
+            .as_deref()
+            .and_then(|d| parse_date(&Value::from(d), tz))
+            .unwrap_or(recent.date)
+            .to_rfc3339_opts(SecondsFormat::Secs, true);
+        let html = note_html(&note.body, note, index, tera, comrak_options, config, Anchorizer::new());
+        let tags: Vec<Value> = note
+            .frontmatter
+            .tags
+            .iter()
+            .flatten()
+            .map(|tag| json!({ "type": "Hashtag", "name": format!("#{}", tag.replace(' ', "")) }))
+            .collect();
+        let mut article = json!({
+            "id": url,
+            "type": "Article",
+            "attributedTo": actor_id,
+            "name": note.title,
+            "url": url,
+            "content": standalone_html(&html, &url, REMOVED_ELEMENTS, config)?,
+            "published": published,
+            "to": [PUBLIC],
+            "tag": tags,
+        });
+        let updated = recent.date.to_rfc3339_opts(SecondsFormat::Secs, true);
+        if updated != published {
+            article["updated"] = json!(updated);
+        }
+        items.push(json!({
+            "id": format!("{url}#create"),
+            "type": "Create",
+            "actor": actor_id,
+            "published": published,
+            "to": [PUBLIC],
+            "object": article,
+        }));
+    }
+    let outbox = json!({
+        "@context": "https://www.w3.org/ns/activitystreams",
+        "id": outbox_id,
+        "type": "OrderedCollection",
+        "totalItems": items.len(),
+        "orderedItems": items,
+    });
+
+    let webfinger = json!({
+        "subject": format!("acct:{username}@{host}"),
+        "aliases": [actor_id],
+        "links": [
+            { "rel": "self", "type": "application/activity+json", "href": actor_id },
+            { "rel": "http://webfinger.net/rel/profile-page", "type": "text/html", "href": format!("{site_url}/") },
+        ],
+    });
+
+    for (path, value) in [("actor.json", &actor), ("outbox.json", &outbox), (".well-known/webfinger", &webfinger)] {
+        let json = serde_json::to_string_pretty(value).map_err(io::Error::other)?;
+        output.write(Path::new(path), json.as_bytes())?;
+    }
+    info!("Wrote ActivityPub outbox of {} notes for @{username}@{host}", outbox["totalItems"]);
+    Ok(())
+}
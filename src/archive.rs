@@ -0,0 +1,109 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use crate::diagnostics::info;
+pub use crate::cli::OutputFormat;
+
+impl OutputFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Dir => "",
+            OutputFormat::Zip => "zip",
+            OutputFormat::TarGz => "tar.gz",
+        }
+    }
+}
+
+/// Returns the archive file path for `output`, appending the format's extension
+/// when the user didn't already name the file that way.
+pub fn archive_path(output: &Path, format: OutputFormat) -> PathBuf {
+    let ext = format.extension();
+    let name = output.to_string_lossy();
+    if ext.is_empty() || name.ends_with(&format!(".{ext}")) {
+        output.to_path_buf()
+    } else {
+        PathBuf::from(format!("{name}.{ext}"))
+    }
+}
+
+/// Packs every file below `site_dir` into a single archive at `dest`.
+pub fn write_archive(site_dir: &Path, dest: &Path, format: OutputFormat) -> io::Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    info!("Writing archive: {}", dest.display());
+    match format {
+        OutputFormat::Dir => Ok(()),
+        OutputFormat::Zip => write_zip(site_dir, dest),
+        OutputFormat::TarGz => write_tar_gz(site_dir, dest),
+    }
+}
+
+/// Reads the file at `name` (output-relative, with forward slashes) from a
+/// `.zip` or `.tar.gz` archive written by `write_archive`, if it holds one.
+pub fn read_archive_file(archive: &Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+    let mut contents = Vec::new();
+    if archive.to_string_lossy().ends_with(".zip") {
+        let mut zip = ZipArchive::new(File::open(archive)?).map_err(io::Error::other)?;
+        return match zip.by_name(name) {
+            Ok(mut file) => {
+                file.read_to_end(&mut contents)?;
+                Ok(Some(contents))
+            }
+            Err(zip::result::ZipError::FileNotFound) => Ok(None),
+            Err(e) => Err(io::Error::other(e)),
+        };
+    }
+    let mut tar = tar::Archive::new(GzDecoder::new(File::open(archive)?));
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        // Entries are stored below `.`
+        let path = entry.path()?.into_owned();
+        if path.strip_prefix(".").unwrap_or(&path) == Path::new(name) {
+            entry.read_to_end(&mut contents)?;
+            return Ok(Some(contents));
+        }
+    }
+    Ok(None)
+}
+
+fn write_zip(site_dir: &Path, dest: &Path) -> io::Result<()> {
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(dest)?));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for entry in WalkDir::new(site_dir).sort_by_file_name() {
+        let entry = entry.map_err(io::Error::other)?;
+        let relative = entry.path().strip_prefix(site_dir).map_err(io::Error::other)?;
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+        // Zip entries always use forward slashes regardless of platform
+        let name = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if entry.file_type().is_dir() {
+            zip.add_directory(name, options).map_err(io::Error::other)?;
+        } else {
+            zip.start_file(name, options).map_err(io::Error::other)?;
+            io::copy(&mut File::open(entry.path())?, &mut zip)?;
+        }
+    }
+    zip.finish().map_err(io::Error::other)?;
+    Ok(())
+}
+
+fn write_tar_gz(site_dir: &Path, dest: &Path) -> io::Result<()> {
+    let encoder = GzEncoder::new(BufWriter::new(File::create(dest)?), Compression::default());
+    let mut tar = tar::Builder::new(encoder);
+    tar.append_dir_all(".", site_dir)?;
+    tar.into_inner()?.finish()?;
+    Ok(())
+}
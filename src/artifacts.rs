@@ -0,0 +1,142 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::config::Config;
+use crate::content::{make_comrak_options, media_type};
+use crate::deploy::shell_command;
+use crate::diagnostics::{config_error, info, warning};
+use crate::fs::only_filter;
+use crate::index::{url_for, IndexedNote, SiteIndex};
+use crate::markdown::document_text;
+use crate::sources::note_markdown;
+use crate::vfs::OutputSink;
+
+/// A file generated for every page by an external command, such as an audio
+/// version read by a text-to-speech tool, configured as a `[[page_artifact]]` table.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArtifactConfig {
+    /// Name templates and note metadata list the artifact under, e.g. `audio`
+    pub name: String,
+    /// Shell command that reads the page from `$OBS2WEB_INPUT` and writes the artifact to `$OBS2WEB_ARTIFACT`
+    pub command: String,
+    /// Extension of the generated file, e.g. `mp3`
+    pub extension: String,
+    /// What the command reads: the note's text without markup, or its Markdown
+    pub input: ArtifactInput,
+    /// Only generate the artifact for notes whose vault-relative path matches one of these globs
+    pub only: Vec<String>,
+}
+
+/// The form of the note an artifact command is given.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactInput {
+    /// As `text_files` writes it
+    #[default]
+    Text,
+    /// As `markdown_files` writes it
+    Markdown,
+}
+
+/// An artifact generated for a note, published next to its page.
+#[derive(Serialize, Debug, Clone)]
+pub struct PageArtifact {
+    pub name: String,
+    /// Root-relative URL of the file
+    pub url: String,
+    pub mime_type: Option<&'static str>,
+}
+
+/// Runs every `[[page_artifact]]` command for each note `selected` accepts, writes
+/// the files next to their pages (`Note.audio.mp3`), and lists them in the notes'
+/// `artifacts` for templates and note metadata. Outputs are cached in
+/// `cache_dir/artifacts/` by command and input, so unchanged notes aren't
+/// processed again; a failing command is reported and the note goes without.
+pub fn generate_artifacts(
+    index: &mut SiteIndex,
+    selected: impl Fn(&IndexedNote) -> bool,
+    output: &dyn OutputSink,
+    config: &Config,
+) -> io::Result<()> {
+    let comrak_options = make_comrak_options(config);
+    let cache_dir = config.cache_dir.join("artifacts");
+    for artifact in &config.page_artifacts {
+        if artifact.name.is_empty() || artifact.command.is_empty() || artifact.extension.is_empty() {
+            return Err(config_error("Every [[page_artifact]] needs a name, command, and extension".to_string()));
+        }
+        let only = only_filter(&artifact.only)?;
+        let mut generated = Vec::new();
+        for (i, note) in index.notes.iter().enumerate() {
+            if !selected(note) || only.as_ref().is_some_and(|only| !only.is_match(&note.source)) {
+                continue;
+            }
+            let markdown = note_markdown(note, index, config);
+            let input = match artifact.input {
+                ArtifactInput::Text => document_text(&markdown, &comrak_options),
+                ArtifactInput::Markdown => markdown,
+            };
+            let cached = cache_dir.join(format!("{}.{}", cache_key(artifact, &input), artifact.extension));
+            if !cached.is_file()
+                && let Err(e) = run_command(artifact, note, &input, &cached)
+            {
+                warning!(file: &note.source; "{} failed for {}: {e}", artifact.name, note.source.display());
+                continue;
+            }
+            let path = artifact_path(note, artifact);
+            output.write(&path, &fs::read(&cached)?)?;
+            generated.push((i, PageArtifact {
+                name: artifact.name.clone(),
+                url: url_for(&path),
+                mime_type: media_type(&path),
+            }));
+        }
+        info!("Generated {} for {} notes", artifact.name, generated.len());
+        for (i, page_artifact) in generated {
+            index.notes[i].artifacts.push(page_artifact);
+        }
+    }
+    Ok(())
+}
+
+/// Where a note's artifact is published: next to the page, named after the artifact.
+fn artifact_path(note: &IndexedNote, artifact: &ArtifactConfig) -> PathBuf {
+    note.output.with_extension(format!("{}.{}", artifact.name, artifact.extension))
+}
+
+/// Runs the artifact's command on `input`, copying what it writes to `cached`.
+fn run_command(artifact: &ArtifactConfig, note: &IndexedNote, input: &str, cached: &Path) -> io::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let input_path = dir.path().join("input");
+    let artifact_path = dir.path().join(format!("artifact.{}", artifact.extension));
+    fs::write(&input_path, input)?;
+    let status = shell_command(&artifact.command)
+        .env("OBS2WEB_INPUT", &input_path)
+        .env("OBS2WEB_ARTIFACT", &artifact_path)
+        .env("OBS2WEB_NOTE", &note.source)
+        .env("OBS2WEB_TITLE", &note.title)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("command exited with {status}")));
+    }
+    if !artifact_path.is_file() {
+        return Err(io::Error::other("command wrote nothing to $OBS2WEB_ARTIFACT"));
+    }
+    if let Some(parent) = cached.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(&artifact_path, cached)?;
+    Ok(())
+}
+
+/// Hex SHA-256 of the artifact's command and extension and the note's input.
+fn cache_key(artifact: &ArtifactConfig, input: &str) -> String {
+    let mut hasher = Sha256::new();
+    for part in [artifact.command.as_str(), artifact.extension.as_str(), input] {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+    hasher.finalize().iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use tera::{Context, Tera};
+use crate::config::Config;
+use crate::content::page_context;
+use crate::diagnostics::{broken_link, info};
+use crate::domain::Note;
+use crate::index::{frontmatter_link, IndexedNote, SiteIndex};
+use crate::links::slugify;
+use crate::vfs::OutputSink;
+
+/// An author of the site's notes, configured as an `[authors.<id>]` table.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthorProfile {
+    /// Name shown on pages, the table's id by default
+    pub name: Option<String>,
+    /// Picture of the author, a vault attachment or an absolute URL
+    pub avatar: Option<String>,
+    /// A sentence or two about the author
+    pub bio: Option<String>,
+    /// Links to the author elsewhere, e.g. `{ title = "Mastodon", url = "https://..." }`
+    pub links: Vec<AuthorLink>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AuthorLink {
+    pub title: String,
+    pub url: String,
+}
+
+/// An author as templates get it.
+#[derive(Serialize, Debug, Clone)]
+pub struct Author {
+    /// The profile's id, or the name made URL-safe for authors without a profile
+    pub id: String,
+    pub name: String,
+    /// URL of the avatar image
+    pub avatar: Option<String>,
+    pub bio: Option<String>,
+    pub links: Vec<AuthorLink>,
+    /// URL of the author's page, with `author_pages`
+    pub url: Option<String>,
+}
+
+/// The authors of a note: its `author` frontmatter (a name or a list), or else the
+/// site's `author`. A name matching the id or name of an `[authors.<id>]` profile
+/// gets that profile.
+pub fn note_authors(note: &IndexedNote, index: &SiteIndex, config: &Config) -> Vec<Author> {
+    let names: Vec<&str> = match &note.frontmatter.author {
+        Some(authors) => authors.values().iter().map(|name| frontmatter_link(name)).collect(),
+        None => config.author.as_deref().into_iter().collect(),
+    };
+    names.into_iter().filter(|name| !name.is_empty()).map(|name| author(name, index, config)).collect()
+}
+
+fn author(name: &str, index: &SiteIndex, config: &Config) -> Author {
+    let profile = config.authors.iter().find(|(id, profile)| {
+        id.eq_ignore_ascii_case(name) || profile.name.as_deref().is_some_and(|n| n.eq_ignore_ascii_case(name))
+    });
+    let (id, name, profile) = match profile {
+        Some((id, profile)) => (id.clone(), profile.name.clone().unwrap_or_else(|| id.clone()), profile.clone()),
+        None => (slugify(name), name.to_string(), AuthorProfile::default()),
+    };
+    Author {
+        url: config.author_pages.then(|| config.url_style.url(&author_page(&id, config))),
+        id,
+        name,
+        avatar: profile.avatar.as_deref().and_then(|avatar| index.attachment_url(avatar)),
+        bio: profile.bio,
+        links: profile.links,
+    }
+}
+
+/// Reports author avatars that aren't in the vault.
+pub fn check_avatars(index: &SiteIndex, config: &Config) {
+    for (id, profile) in &config.authors {
+        if let Some(avatar) = &profile.avatar
+            && index.attachment_url(avatar).is_none()
+        {
+            broken_link!("avatar {avatar} of author {id} not found in the vault");
+        }
+    }
+}
+
+/// Output-relative path of an author's page.
+fn author_page(id: &str, config: &Config) -> PathBuf {
+    config.url_style.output(&Path::new("authors").join(format!("{id}.html")))
+}
+
+/// Writes `authors/<id>.html` for every author of the site's notes, listing
+/// their notes newest first under their profile, rendered with the theme's
+/// `author.html` inside `base.html`.
+pub fn write_author_pages(
+    notes: &[IndexedNote],
+    index: &SiteIndex,
+    tera: &Tera,
+    output: &dyn OutputSink,
+    config: &Config,
+) -> io::Result<()> {
+    let mut authors: BTreeMap<String, (Author, Vec<&IndexedNote>)> = BTreeMap::new();
+    for note in notes {
+        for author in note_authors(note, index, config) {
+            authors.entry(author.id.clone()).or_insert_with(|| (author, Vec::new())).1.push(note);
+        }
+    }
+    for (author, notes) in authors.values_mut() {
+        notes.sort_by(|a, b| b.frontmatter.date.cmp(&a.frontmatter.date).then_with(|| a.title.cmp(&b.title)));
+        let notes: Vec<Note> = notes.iter().map(|note| note.to_note()).collect();
+        let mut context = Context::new();
+        context.insert("author", author);
+        context.insert("notes", &notes);
+        let content = tera
+            .render("author.html", &context)
+            .map_err(|e| io::Error::other(format!("Template rendering failed for author.html: {e}")))?;
+
+        let page = author_page(&author.id, config);
+        let mut context = page_context(&page, index, config);
+        context.insert("title", &author.name);
+        context.insert("content", &content);
+        let rendered = tera
+            .render("base.html", &context)
+            .map_err(|e| io::Error::other(format!("Template rendering failed for {}: {e}", page.display())))?;
+        output.write(&page, rendered.as_bytes())?;
+    }
+    info!("Wrote {} author pages", authors.len());
+    Ok(())
+}
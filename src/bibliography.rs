@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::sync::LazyLock;
+use biblatex::ChunksExt;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use crate::content::escape_html;
+use crate::diagnostics::warning;
+use crate::vfs::VaultSource;
+
+static CITATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[(-?@[^\[\]]+)\]").unwrap());
+static CITE_ITEM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(-?)@([\w:.#$%&+?<>~/-]+?)\s*(?:,\s*(.*))?$").unwrap());
+
+/// A bibliography entry, reduced to what the author-date style needs.
+#[derive(Debug, Clone, Default)]
+pub struct Reference {
+    /// Family names, in order
+    authors: Vec<String>,
+    /// Authors as written in the reference list, e.g. `Smith, J.`
+    full_authors: Vec<String>,
+    year: Option<String>,
+    title: Option<String>,
+    /// Journal, book, or publisher the work appeared in
+    container: Option<String>,
+    url: Option<String>,
+}
+
+/// References from the vault's BibTeX and CSL JSON files, keyed by citekey.
+#[derive(Debug, Default)]
+pub struct Bibliography {
+    references: HashMap<String, Reference>,
+}
+
+#[derive(Deserialize)]
+struct CslItem {
+    id: String,
+    #[serde(default)]
+    author: Vec<CslName>,
+    issued: Option<CslDate>,
+    title: Option<String>,
+    #[serde(rename = "container-title")]
+    container_title: Option<String>,
+    publisher: Option<String>,
+    #[serde(rename = "URL")]
+    url: Option<String>,
+    #[serde(rename = "DOI")]
+    doi: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CslName {
+    family: Option<String>,
+    given: Option<String>,
+    literal: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CslDate {
+    #[serde(rename = "date-parts", default)]
+    date_parts: Vec<Vec<serde_json::Value>>,
+    raw: Option<String>,
+}
+
+impl Bibliography {
+    /// Reads `.bib` (BibTeX/BibLaTeX) and `.json` (CSL JSON) files; later files win on duplicate keys.
+    pub fn load(vault: &dyn VaultSource, files: &[impl AsRef<Path>]) -> io::Result<Bibliography> {
+        let mut references = HashMap::new();
+        for file in files {
+            let path = file.as_ref();
+            let text = vault.read_to_string(path)
+                .map_err(|e| io::Error::other(format!("Cannot read bibliography {}: {e}", path.display())))?;
+            let error = |e: String| io::Error::other(format!("Bibliography error in {}: {e}", path.display()));
+            if path.extension().and_then(|s| s.to_str()) == Some("json") {
+                let items: Vec<CslItem> = serde_json::from_str(&text).map_err(|e| error(e.to_string()))?;
+                references.extend(items.into_iter().map(|item| (item.id.clone(), csl_reference(item))));
+            } else {
+                let bibliography = biblatex::Bibliography::parse(&text).map_err(|e| error(e.to_string()))?;
+                references.extend(bibliography.iter().map(|entry| (entry.key.clone(), bibtex_reference(entry))));
+            }
+        }
+        Ok(Bibliography { references })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.references.is_empty()
+    }
+
+    /// Replaces Pandoc-style citations (`[@key]`, `[@key, p. 3]`, `[@a; @b]`, and
+    /// `[-@key]` to leave out the author) with author-date citations, and appends
+    /// a bibliography of the works cited.
+    pub fn cite(&self, content: &str, source: &Path) -> String {
+        let mut cited: Vec<&str> = Vec::new();
+        let content = CITATION.replace_all(content, |caps: &Captures| {
+            let mut parts = Vec::new();
+            for item in caps[1].split(';') {
+                let Some(item) = CITE_ITEM.captures(item.trim()) else {
+                    return caps[0].to_string();
+                };
+                let key = item.get(2).map_or("", |m| m.as_str());
+                let Some((key, reference)) = self.references.get_key_value(key) else {
+                    warning!(file: source, near: &format!("@{key}"); "unknown citation key @{key} in {}", source.display());
+                    parts.push(format!("<span class=\"unresolved-citation\">@{}</span>", escape_html(key)));
+                    continue;
+                };
+                if !cited.contains(&key.as_str()) {
+                    cited.push(key);
+                }
+                let mut text = if item[1].is_empty() {
+                    format!("{} {}", reference.short_authors(), reference.year())
+                } else {
+                    reference.year().to_string()
+                };
+                if let Some(locator) = item.get(3).filter(|m| !m.as_str().is_empty()) {
+                    text.push_str(&format!(", {}", locator.as_str()));
+                }
+                parts.push(format!("<a href=\"#ref-{}\">{}</a>", anchor(key), escape_html(text.trim())));
+            }
+            format!("<cite class=\"citation\">({})</cite>", parts.join("; "))
+        });
+        if cited.is_empty() {
+            return content.into_owned();
+        }
+
+        let mut entries: Vec<(&str, &Reference)> = cited.iter().map(|key| (*key, &self.references[*key])).collect();
+        entries.sort_by(|(_, a), (_, b)| (a.authors.first(), &a.year).cmp(&(b.authors.first(), &b.year)));
+        let mut html = String::from("\n\n<section class=\"bibliography\">\n<h2>Bibliography</h2>\n<ul>\n");
+        for (key, reference) in entries {
+            html.push_str(&format!("<li id=\"ref-{}\">{}</li>\n", anchor(key), reference.format()));
+        }
+        html.push_str("</ul>\n</section>\n");
+        content.into_owned() + &html
+    }
+}
+
+impl Reference {
+    /// `Smith`, `Smith & Jones`, or `Smith et al.`
+    fn short_authors(&self) -> String {
+        match self.authors.as_slice() {
+            [] => self.title.clone().unwrap_or_default(),
+            [one] => one.clone(),
+            [first, second] => format!("{first} & {second}"),
+            [first, ..] => format!("{first} et al."),
+        }
+    }
+
+    fn year(&self) -> &str {
+        self.year.as_deref().unwrap_or("n.d.")
+    }
+
+    /// The reference list entry: `Smith, J., & Jones, K. (2020). Title. <i>Journal</i>. URL`
+    fn format(&self) -> String {
+        let mut html = match self.full_authors.as_slice() {
+            [] => String::new(),
+            [one] => format!("{} ", escape_html(one)),
+            [rest @ .., last] => format!("{}, & {} ", escape_html(&rest.join(", ")), escape_html(last)),
+        };
+        html.push_str(&format!("({}). ", escape_html(self.year())));
+        if let Some(title) = &self.title {
+            html.push_str(&format!("{}. ", escape_html(title.trim_end_matches('.'))));
+        }
+        if let Some(container) = &self.container {
+            html.push_str(&format!("<i>{}</i>. ", escape_html(container)));
+        }
+        if let Some(url) = &self.url {
+            html.push_str(&format!("<a href=\"{0}\">{0}</a>", escape_html(url)));
+        }
+        html.trim_end().to_string()
+    }
+}
+
+fn bibtex_reference(entry: &biblatex::Entry) -> Reference {
+    let field = |name: &str| entry.get(name).map(|chunks| chunks.format_verbatim());
+    let people = entry.author().unwrap_or_default();
+    Reference {
+        authors: people.iter().map(|p| p.name.clone()).collect(),
+        full_authors: people.iter().map(|p| full_name(&p.name, Some(&p.given_name))).collect(),
+        year: field("year").or_else(|| field("date")).map(|date| date.chars().take(4).collect()),
+        title: field("title"),
+        container: field("journaltitle")
+            .or_else(|| field("journal"))
+            .or_else(|| field("booktitle"))
+            .or_else(|| field("publisher")),
+        url: field("url").or_else(|| field("doi").map(|doi| format!("https://doi.org/{doi}"))),
+    }
+}
+
+fn csl_reference(item: CslItem) -> Reference {
+    let names: Vec<(String, Option<String>)> = item
+        .author
+        .into_iter()
+        .filter_map(|name| match (name.family, name.literal) {
+            (Some(family), _) => Some((family, name.given)),
+            (None, Some(literal)) => Some((literal, None)),
+            (None, None) => None,
+        })
+        .collect();
+    let year = item.issued.and_then(|date| match date.date_parts.first().and_then(|parts| parts.first()) {
+        Some(year) => Some(year.to_string().trim_matches('"').to_string()),
+        None => date.raw.map(|raw| raw.chars().take(4).collect()),
+    });
+    Reference {
+        authors: names.iter().map(|(family, _)| family.clone()).collect(),
+        full_authors: names.iter().map(|(family, given)| full_name(family, given.as_deref())).collect(),
+        year,
+        title: item.title,
+        container: item.container_title.or(item.publisher),
+        url: item.url.or_else(|| item.doi.map(|doi| format!("https://doi.org/{doi}"))),
+    }
+}
+
+/// `Smith, J. K.` from a family name and given names.
+fn full_name(family: &str, given: Option<&str>) -> String {
+    let initials: Vec<String> = given
+        .unwrap_or_default()
+        .split_whitespace()
+        .filter_map(|name| name.chars().next())
+        .map(|c| format!("{c}."))
+        .collect();
+    if initials.is_empty() {
+        family.to_string()
+    } else {
+        format!("{family}, {}", initials.join(" "))
+    }
+}
+
+/// Citekeys may contain characters that don't belong in an id.
+fn anchor(key: &str) -> String {
+    key.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' }).collect()
+}
@@ -0,0 +1,95 @@
+use std::sync::LazyLock;
+use regex::Regex;
+use crate::placeholders::code_ranges;
+
+/// A `^block-id` at the end of a line, Obsidian's marker for a block that can be linked to.
+static BLOCK_MARKER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?:^|[ \t])\^([A-Za-z0-9-]+)[ \t]*$").unwrap());
+
+/// The start of a list item, which is a block of its own.
+static LIST_ITEM: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[ \t]*(?:[-*+]|\d+[.)])[ \t]+").unwrap());
+
+/// The start of a list item or blockquote line, which isn't part of the block's text.
+static LINE_MARKER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[ \t]*(?:>[ \t]*)*(?:(?:[-*+]|\d+[.)])[ \t]+(?:\[.\][ \t]+)?)?").unwrap());
+
+/// A block of a note marked with `^id`, which `[[Note#^id]]` links to.
+#[derive(Debug, Clone)]
+pub struct NoteBlock {
+    pub id: String,
+    /// The block's Markdown without its marker, list item, or quote markers
+    pub markdown: String,
+    /// Byte offset of the marker in the body
+    pub start: usize,
+}
+
+/// The blocks of a note body marked with `^id`, in order. A marker ends the
+/// paragraph or list item it is written at the end of; on a line of its own, it
+/// marks the block before it, as tables, lists, and quotes are marked. Markers
+/// in code are left alone, and only the first block with an id counts.
+pub fn note_blocks(body: &str) -> Vec<NoteBlock> {
+    let code = code_ranges(body);
+    let lines: Vec<(usize, &str)> = body
+        .split_inclusive('\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some((start, line.trim_end_matches(['\n', '\r'])))
+        })
+        .collect();
+    let mut blocks: Vec<NoteBlock> = Vec::new();
+    for (i, &(offset, line)) in lines.iter().enumerate() {
+        let Some(marker) = BLOCK_MARKER.captures(line) else {
+            continue;
+        };
+        let start = offset + marker.get(0).unwrap().start();
+        let id = marker[1].to_string();
+        if code.iter().any(|range| range.contains(&start)) || blocks.iter().any(|block| block.id == id) {
+            continue;
+        }
+        let text = line[..marker.get(0).unwrap().start()].trim_end();
+        // Lines of the block, last first
+        let mut block = Vec::new();
+        if text.trim().is_empty() {
+            let before = lines[..i].iter().rev().skip_while(|(_, line)| line.trim().is_empty());
+            block.extend(before.take_while(|(_, line)| !line.trim().is_empty()).map(|(_, line)| *line));
+        } else if LIST_ITEM.is_match(text) {
+            block.push(text);
+        } else {
+            block.push(text);
+            block.extend(lines[..i].iter().rev().take_while(|(_, line)| !line.trim().is_empty()).map(|(_, line)| *line));
+        }
+        let markdown = block
+            .iter()
+            .rev()
+            .map(|line| line[LINE_MARKER.find(line).map_or(0, |m| m.end())..].trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        blocks.push(NoteBlock { id, markdown, start });
+    }
+    blocks
+}
+
+/// `body` without its `^id` block markers, which only Obsidian reads. Lines that
+/// held nothing but a marker are dropped.
+pub fn strip_block_markers(body: &str) -> String {
+    let code = code_ranges(body);
+    let mut offset = 0;
+    let mut stripped = String::with_capacity(body.len());
+    for line in body.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        let text = line.trim_end_matches(['\n', '\r']);
+        match BLOCK_MARKER.find(text) {
+            Some(marker) if !code.iter().any(|range| range.contains(&(start + marker.start()))) => {
+                let kept = text[..marker.start()].trim_end();
+                if !kept.trim().is_empty() {
+                    stripped.push_str(kept);
+                    stripped.push_str(&line[text.len()..]);
+                }
+            }
+            _ => stripped.push_str(line),
+        }
+    }
+    stripped
+}
@@ -0,0 +1,154 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use comrak::ComrakOptions;
+use serde::Deserialize;
+use tera::Tera;
+use crate::config::Config;
+use crate::content::{absolute_url, escape_html, note_html, page_context};
+use crate::diagnostics::{broken_link, config_error, info, warning};
+use crate::index::{IndexedNote, LinkTarget, SiteIndex, UrlStyle};
+use crate::links::{find_wikilinks, slugify};
+use crate::split::headings;
+use crate::vfs::OutputSink;
+
+/// A long-form page combining notes in order, configured as a `[[book]]` table.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BookConfig {
+    pub title: String,
+    /// Output-relative path of the page, `books/<title>.html` by default
+    pub output: Option<PathBuf>,
+    /// The chapters, in order, as wikilink targets (`Intro`, `Guides/Setup`)
+    pub chapters: Vec<String>,
+    /// A note whose wikilinks list the chapters in order, used when `chapters` is empty
+    pub toc: Option<String>,
+}
+
+impl BookConfig {
+    /// The configured `output`, or `books/<title>.html` named as `style` names pages.
+    fn output(&self, style: UrlStyle) -> PathBuf {
+        self.output
+            .clone()
+            .unwrap_or_else(|| style.output(&Path::new("books").join(format!("{}.html", slugify(&self.title)))))
+    }
+}
+
+/// Renders every configured book: its chapters one after another, numbered
+/// continuously, under a combined table of contents. Links between chapters
+/// of the same book stay on the book's page. Only `notes` can be chapters, so
+/// a book can't publish the notes of an encrypted folder in the clear.
+pub fn write_books(
+    notes: &[IndexedNote],
+    index: &SiteIndex,
+    tera: &Tera,
+    comrak_options: &ComrakOptions,
+    output: &dyn OutputSink,
+    config: &Config,
+) -> io::Result<()> {
+    // Chapter titles are the book's h2s, so the chapters' own headings go one level down
+    let mut chapter_config = config.clone();
+    chapter_config.shift_headings = true;
+    for book in &config.books {
+        let chapters = book_chapters(book, notes, index)?;
+        let mut toc = String::from("<nav class=\"book-toc\" aria-label=\"Contents\">\n<ol>\n");
+        let mut body = String::new();
+        for (i, chapter) in chapters.iter().enumerate() {
+            let number = i + 1;
+            // The chapter heading stands in for an H1 that repeats the title
+            let start = headings(&chapter.body)
+                .first()
+                .filter(|first| {
+                    first.level == 1 && first.text == chapter.title && chapter.body[..first.line.start].trim().is_empty()
+                })
+                .map_or(0, |first| first.line.end);
+            // Heading ids get a per-chapter prefix, as chapters often share heading names
+            let mut options = comrak_options.clone();
+            options.extension.header_ids = Some(format!("c{number}-"));
+            let anchors = chapter.anchors_before(start);
+            let html = note_html(&chapter.body[start..], chapter, index, tera, &options, &chapter_config, anchors);
+            let html = link_within_book(html, &chapters);
+
+            toc.push_str(&format!(
+                "<li><a href=\"#chapter-{number}\">{}</a>",
+                escape_html(&chapter.title)
+            ));
+            let sections: Vec<_> = chapter.headings.iter().filter(|h| h.start >= start && h.level <= 2).collect();
+            if !sections.is_empty() {
+                toc.push_str("\n<ol>\n");
+                for section in sections {
+                    toc.push_str(&format!(
+                        "<li><a href=\"#c{number}-{}\">{}</a></li>\n",
+                        section.anchor,
+                        escape_html(&section.text)
+                    ));
+                }
+                toc.push_str("</ol>\n");
+            }
+            toc.push_str("</li>\n");
+            body.push_str(&format!(
+                "<section class=\"book-chapter\" id=\"chapter-{number}\">\n<h2>{number}. {}</h2>\n{html}</section>\n",
+                escape_html(&chapter.title)
+            ));
+        }
+        toc.push_str("</ol>\n</nav>\n");
+
+        let book_output = book.output(config.url_style);
+        let mut context = page_context(&book_output, index, config);
+        context.insert("title", &book.title);
+        context.insert("content", &format!("{toc}{body}"));
+        if config.site_url.is_some() {
+            context.insert("canonical_url", &absolute_url(&config.url_style.url(&book_output), config));
+        }
+        let html = tera
+            .render("base.html", &context)
+            .map_err(|e| io::Error::other(format!("Template rendering failed for book {}: {e}", book.title)))?;
+        output.write(&book_output, html.as_bytes())?;
+        info!("Wrote book {} with {} chapters", book_output.display(), chapters.len());
+    }
+    Ok(())
+}
+
+/// The notes a book combines, from its `chapters` list or its `toc` note.
+fn book_chapters<'a>(book: &BookConfig, notes: &[IndexedNote], index: &'a SiteIndex) -> io::Result<Vec<&'a IndexedNote>> {
+    let (targets, listed_in): (Vec<String>, Option<&IndexedNote>) = match &book.toc {
+        Some(toc) if book.chapters.is_empty() => {
+            let Some(LinkTarget::Note(toc_note)) = index.resolve(toc) else {
+                return Err(config_error(format!("Table of contents note {toc} of book {} not found", book.title)));
+            };
+            let targets = find_wikilinks(&toc_note.body).map(|(_, link)| link.target).filter(|t| !t.is_empty());
+            (targets.collect(), Some(toc_note))
+        }
+        _ => (book.chapters.clone(), None),
+    };
+    let mut chapters = Vec::new();
+    for target in targets {
+        match index.resolve(&target) {
+            Some(LinkTarget::Note(note)) if notes.iter().any(|n| n.source == note.source) => chapters.push(note),
+            Some(LinkTarget::Note(note)) => {
+                warning!(file: &note.source; "{} is encrypted, so book {} leaves it out", note.source.display(), book.title);
+            }
+            _ => match listed_in {
+                Some(toc) => broken_link!(file: &toc.source, near: &target; "chapter {target} of book {} not found", book.title),
+                None => broken_link!("chapter {target} of book {} not found", book.title),
+            },
+        }
+    }
+    if chapters.is_empty() {
+        return Err(config_error(format!("Book {} has no chapters", book.title)));
+    }
+    Ok(chapters)
+}
+
+/// Points links to the book's chapters, or to headings in them, at their place on the book's page.
+fn link_within_book(mut html: String, chapters: &[&IndexedNote]) -> String {
+    for (i, chapter) in chapters.iter().enumerate() {
+        let number = i + 1;
+        let urls = std::iter::once(&chapter.url).chain(chapter.parts.iter().map(|part| &part.url));
+        for url in urls {
+            html = html
+                .replace(&format!("href=\"{url}#"), &format!("href=\"#c{number}-"))
+                .replace(&format!("href=\"{url}\""), &format!("href=\"#chapter-{number}\""));
+        }
+    }
+    html
+}
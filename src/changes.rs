@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use similar::{ChangeTag, TextDiff};
+use tera::{Tera, Value};
+use crate::config::Config;
+use crate::content::{escape_html, page_context};
+use crate::dates::{build_time, format_date, parse_date, site_timezone};
+use crate::diagnostics::info;
+use crate::index::{IndexedNote, SiteIndex};
+use crate::vfs::OutputSink;
+
+/// File in `cache_dir` the notes of the last build and the changes seen so far are kept in.
+const STATE_FILE: &str = "changes.json";
+/// Words of unchanged text shown around each change in a diff.
+const DIFF_CONTEXT: usize = 8;
+
+/// A note as the last build published it.
+#[derive(Serialize, Deserialize)]
+struct PublishedNote {
+    title: String,
+    url: String,
+    hash: String,
+    body: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ChangeKind {
+    Added,
+    Changed,
+    Removed,
+}
+
+/// A note that was added, changed, or removed by a build.
+#[derive(Serialize, Deserialize)]
+struct Change {
+    source: PathBuf,
+    title: String,
+    url: String,
+    kind: ChangeKind,
+    /// When the build that saw the change ran
+    time: String,
+    /// Word-level diff against the previous build, with `changes_diffs`
+    diff: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct ChangesState {
+    notes: BTreeMap<PathBuf, PublishedNote>,
+    /// Newest first, one entry per note
+    changes: Vec<Change>,
+}
+
+/// Writes `changes.html`, listing the notes this build and earlier ones added,
+/// changed, or removed, newest first. Notes are compared with the previous
+/// build, which is recorded in `cache_dir`; the first build only records.
+pub fn write_changes_page(
+    notes: &[IndexedNote],
+    index: &SiteIndex,
+    tera: &Tera,
+    output: &dyn OutputSink,
+    config: &Config,
+) -> io::Result<()> {
+    // Sites built for different audiences keep apart records
+    let state_path = match config.audience {
+        Some(audience) => config.cache_dir.join(format!("changes-{}.json", audience.name())),
+        None => config.cache_dir.join(STATE_FILE),
+    };
+    let previous: Option<ChangesState> = match fs::read_to_string(&state_path) {
+        Ok(json) => Some(serde_json::from_str(&json).map_err(|e| {
+            io::Error::other(format!("Cannot read {}: {e}; delete it to start over", state_path.display()))
+        })?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let now = build_time(site_timezone(config)?);
+    let notes: BTreeMap<PathBuf, PublishedNote> = notes
+        .iter()
+        .map(|note| {
+            let hash = Sha256::digest(format!("{}\n{}", note.title, note.body).as_bytes());
+            let published = PublishedNote {
+                title: note.title.clone(),
+                url: note.url.clone(),
+                hash: hash.iter().take(16).map(|b| format!("{b:02x}")).collect(),
+                body: note.body.clone(),
+            };
+            (note.source.clone(), published)
+        })
+        .collect();
+
+    let mut changes = Vec::new();
+    let mut kept = Vec::new();
+    if let Some(previous) = previous {
+        for (source, note) in &notes {
+            let (kind, diff) = match previous.notes.get(source) {
+                None => (ChangeKind::Added, None),
+                Some(old) if old.hash != note.hash => {
+                    (ChangeKind::Changed, config.changes_diffs.then(|| word_diff(&old.body, &note.body)))
+                }
+                Some(_) => continue,
+            };
+            changes.push(Change {
+                source: source.clone(),
+                title: note.title.clone(),
+                url: note.url.clone(),
+                kind,
+                time: now.clone(),
+                diff,
+            });
+        }
+        for (source, old) in previous.notes.iter().filter(|(source, _)| !notes.contains_key(*source)) {
+            changes.push(Change {
+                source: source.clone(),
+                title: old.title.clone(),
+                url: old.url.clone(),
+                kind: ChangeKind::Removed,
+                time: now.clone(),
+                diff: None,
+            });
+        }
+        kept = previous.changes;
+    }
+    // A note changed again keeps a single entry, which stays "added" while the note is new
+    for change in &mut changes {
+        if let Some(i) = kept.iter().position(|old| old.source == change.source) {
+            let old = kept.remove(i);
+            if old.kind == ChangeKind::Added && change.kind == ChangeKind::Changed {
+                change.kind = ChangeKind::Added;
+                change.diff = None;
+            }
+        }
+    }
+    changes.extend(kept);
+    changes.truncate(config.changes_limit);
+    if !changes.is_empty() {
+        info!("Recorded {} recent changes", changes.len());
+    }
+
+    let html = changes_html(&changes, config);
+    let page = Path::new("changes.html");
+    let mut context = page_context(page, index, config);
+    context.insert("title", "Recent changes");
+    context.insert("content", &html);
+    let rendered = tera
+        .render("base.html", &context)
+        .map_err(|e| io::Error::other(format!("Template rendering failed for changes.html: {e}")))?;
+    output.write(page, rendered.as_bytes())?;
+
+    let state = ChangesState { notes, changes };
+    fs::create_dir_all(&config.cache_dir)?;
+    fs::write(&state_path, serde_json::to_string(&state).map_err(io::Error::other)?)
+}
+
+fn changes_html(changes: &[Change], config: &Config) -> String {
+    if changes.is_empty() {
+        return "<p class=\"changes-empty\">No changes recorded yet.</p>\n".to_string();
+    }
+    let tz = site_timezone(config).unwrap_or(chrono_tz::Tz::UTC);
+    let mut html = String::from("<ol class=\"changes\">\n");
+    for change in changes {
+        let (kind, label) = match change.kind {
+            ChangeKind::Added => ("added", "New"),
+            ChangeKind::Changed => ("changed", "Updated"),
+            ChangeKind::Removed => ("removed", "Removed"),
+        };
+        let title = match change.kind {
+            ChangeKind::Removed => escape_html(&change.title),
+            _ => format!("<a href=\"{}\">{}</a>", escape_html(&change.url), escape_html(&change.title)),
+        };
+        let date = parse_date(&Value::String(change.time.clone()), tz)
+            .map_or_else(|| change.time.clone(), |date| format_date(&date, config));
+        html.push_str(&format!(
+            "<li class=\"change change-{kind}\"><span class=\"change-kind\">{label}</span> {title} <time datetime=\"{}\">{}</time>",
+            escape_html(&change.time),
+            escape_html(&date)
+        ));
+        if let Some(diff) = &change.diff {
+            html.push_str(&format!("\n<div class=\"change-diff\">\n{diff}</div>\n"));
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ol>\n");
+    html
+}
+
+/// The changed passages between two versions of a note's text, word by word,
+/// with removed words in `<del>` and added ones in `<ins>`.
+fn word_diff(old: &str, new: &str) -> String {
+    let diff = TextDiff::from_words(old, new);
+    let mut html = String::new();
+    let words = diff.old_slices().len();
+    for group in diff.grouped_ops(DIFF_CONTEXT) {
+        // Passages that don't reach the start or end of the note are marked as cut
+        let starts = group.first().is_some_and(|op| op.old_range().start == 0);
+        let ends = group.last().is_some_and(|op| op.old_range().end == words);
+        html.push_str(if starts { "<p>" } else { "<p>… " });
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                let text = escape_html(change.value());
+                match change.tag() {
+                    ChangeTag::Equal => html.push_str(&text),
+                    ChangeTag::Delete => html.push_str(&format!("<del>{text}</del>")),
+                    ChangeTag::Insert => html.push_str(&format!("<ins>{text}</ins>")),
+                }
+            }
+        }
+        html.push_str(if ends { "</p>\n" } else { " …</p>\n" });
+    }
+    html
+}
@@ -0,0 +1,323 @@
+// Only depends on clap and serde, so build.rs can include this file to generate man pages
+
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use serde::Deserialize;
+
+/// Publishes an Obsidian vault as a static website. Without a subcommand the
+/// flags below build the site, as `obs2web build` does.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Config file (defaults to obs2web.toml in the vault, if present); `-` reads TOML or JSON from stdin
+    #[arg(short, long, global = true, env = "OBS2WEB_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Only print warnings and errors, not progress
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Don't color warnings and errors (also off when NO_COLOR is set or stderr isn't a terminal)
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Print warnings and errors as CI annotations on the files they concern
+    #[arg(long, global = true, value_enum)]
+    pub annotations: Option<AnnotationFormat>,
+
+    #[command(flatten)]
+    pub build: BuildArgs,
+}
+
+/// Where to read the vault from, where to write the site, and the build flags
+/// that override the config file.
+#[derive(ClapArgs, Debug, Clone)]
+pub struct BuildArgs {
+    /// Path to the Obsidian vault
+    #[arg(short, long, required = true, env = "OBS2WEB_VAULT_PATH")]
+    pub vault_path: Option<PathBuf>,
+
+    /// Path to the output directory, or an s3://bucket/prefix to publish the site into
+    #[arg(short, long, required = true, env = "OBS2WEB_OUTPUT_DIR")]
+    pub output_dir: Option<PathBuf>,
+
+    /// Write the site as a directory or pack it into a single archive
+    #[arg(long, value_enum, default_value_t = OutputFormat::Dir)]
+    pub output_format: OutputFormat,
+
+    /// How to place attachments into the output
+    #[arg(long, value_enum)]
+    pub asset_mode: Option<AssetMode>,
+
+    /// Follow symbolic links inside the vault, e.g. attachment folders stored elsewhere
+    #[arg(long)]
+    pub follow_symlinks: bool,
+
+    /// Don't exclude files matched by .gitignore (.obs2webignore is always respected)
+    #[arg(long)]
+    pub no_gitignore: bool,
+
+    /// Render a history page for every note from the vault's git log
+    #[arg(long)]
+    pub git_history: bool,
+
+    /// Repository web URL that history pages link commit diffs to
+    #[arg(long)]
+    pub repo_url: Option<String>,
+
+    /// Path the site is served under, e.g. /notes/ for a GitHub Pages project site
+    #[arg(long, value_name = "PATH")]
+    pub base_path: Option<String>,
+
+    /// Warn about content problems such as images without alt text
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Don't fetch anything over the network; embeds and link cards only use the cache
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Wait for another build writing to the same output or cache instead of failing
+    #[arg(long)]
+    pub wait: bool,
+
+    /// Only render notes matching this glob, e.g. 'Projects/**' (repeatable); links still resolve against the whole vault
+    #[arg(long, value_name = "GLOB")]
+    pub only: Vec<String>,
+}
+
+impl BuildArgs {
+    /// The vault and output paths, which clap requires whenever they are used.
+    pub fn paths(&self) -> std::io::Result<(&Path, &Path)> {
+        match (&self.vault_path, &self.output_dir) {
+            (Some(vault_path), Some(output_dir)) => Ok((vault_path, output_dir)),
+            _ => Err(std::io::Error::other("--vault-path and --output-dir are required")),
+        }
+    }
+
+}
+
+/// Where `serve` and `preview` listen, and who they let in.
+#[derive(ClapArgs, Debug, Clone)]
+pub struct ServeArgs {
+    /// Port to listen on
+    #[arg(short, long, default_value_t = 8080)]
+    pub port: u16,
+
+    /// Address to listen on; 0.0.0.0 (or ::) serves other machines too
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: IpAddr,
+
+    /// Ask for these credentials with HTTP basic authentication, e.g. for reviewers of an unpublished build
+    #[arg(long, value_name = "USER:PASS", env = "OBS2WEB_AUTH", hide_env_values = true)]
+    pub auth: Option<String>,
+
+    /// Serve HTTPS with this PEM certificate (or chain), so credentials and pages are encrypted in transit
+    #[arg(long, value_name = "PEM", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key of --tls-cert
+    #[arg(long, value_name = "PEM", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Build the site (the default when no subcommand is given)
+    Build(BuildArgs),
+    /// Build the site and serve it locally, rebuilding when the vault changes
+    Serve {
+        #[command(flatten)]
+        build: BuildArgs,
+
+        #[command(flatten)]
+        server: ServeArgs,
+    },
+    /// Build the site, then rebuild it whenever a file in the vault changes
+    Watch(BuildArgs),
+    /// Render a single note to stdout or a file, e.g. to preview one page from an editor
+    Render {
+        /// Note to render, relative to the current directory or the vault
+        note: PathBuf,
+
+        /// Path to the Obsidian vault
+        #[arg(short, long, default_value = ".", env = "OBS2WEB_VAULT_PATH")]
+        vault_path: PathBuf,
+
+        /// File to write the page to instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Render the notes published or updated in the last days as one HTML email,
+    /// e.g. to pipe into a mailing tool
+    Digest {
+        /// Path to the Obsidian vault
+        #[arg(short, long, default_value = ".", env = "OBS2WEB_VAULT_PATH")]
+        vault_path: PathBuf,
+
+        /// How many days back the digest reaches
+        #[arg(short, long, default_value_t = 7)]
+        days: u32,
+
+        /// File to write the email to instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Build the fixture vaults in a folder and compare the output with their snapshots,
+    /// e.g. to check that a theme or an upgrade doesn't change rendering unexpectedly
+    TestFixtures {
+        /// Folder holding one fixture per subfolder, each with a vault/ and an expected/ snapshot
+        #[arg(default_value = "fixtures")]
+        dir: PathBuf,
+
+        /// Rewrite the snapshots from the current output instead of comparing
+        #[arg(long)]
+        update: bool,
+    },
+    /// Serve diagnostics for notes to editor plugins as JSON-RPC over stdin and stdout
+    Daemon {
+        /// Path to the Obsidian vault
+        #[arg(short, long, default_value = ".", env = "OBS2WEB_VAULT_PATH")]
+        vault_path: PathBuf,
+    },
+    /// Check the vault for broken links and content problems without publishing anything
+    Check {
+        /// Path to the Obsidian vault
+        #[arg(short, long)]
+        vault_path: PathBuf,
+    },
+    /// Build the site and publish it with the `deploy_command` from the config
+    Deploy {
+        #[command(flatten)]
+        build: BuildArgs,
+
+        /// Print the deploy command instead of running it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check that a copy of the site, such as a mirror or what a CDN serves, matches the checksums of a build
+    Verify {
+        /// Site directory or URL (`https://...`) of the copy to check
+        target: String,
+
+        /// SHA256SUMS of the build to check against (defaults to the copy's own)
+        #[arg(short, long)]
+        manifest: Option<PathBuf>,
+    },
+    /// Serve an existing build (site directory or archive) locally without rebuilding
+    Preview {
+        /// Site directory, .zip or .tar.gz archive to serve
+        path: PathBuf,
+
+        #[command(flatten)]
+        server: ServeArgs,
+    },
+    /// Export the vault as page bundles for another static site generator, or as a corpus of its text
+    Export {
+        /// Target generator
+        #[arg(short, long, value_enum)]
+        format: ExportFormat,
+
+        /// Path to the Obsidian vault
+        #[arg(short, long)]
+        vault_path: PathBuf,
+
+        /// Directory to write the generator's content into (or `corpus.jsonl`, for `corpus`)
+        #[arg(short, long)]
+        output_dir: PathBuf,
+
+        /// With `corpus`, split notes into chunks of about this many words (0 keeps notes whole)
+        #[arg(long, default_value_t = 0)]
+        chunk_words: usize,
+
+        /// With `corpus`, repeat this many words of the previous chunk at the start of each chunk
+        #[arg(long, default_value_t = 0, requires = "chunk_words")]
+        chunk_overlap: usize,
+    },
+    /// Convert an existing Hugo, Zola, or Jekyll site into vault notes
+    Import {
+        /// Site directory (or its content/ folder) to import from
+        site_path: PathBuf,
+
+        /// Vault to write the converted notes into
+        #[arg(short, long)]
+        vault_path: PathBuf,
+    },
+    /// Set up a vault for publishing: a starter obs2web.toml and a copy of the default theme
+    Init {
+        /// Vault to set up
+        #[arg(default_value = ".")]
+        vault_path: PathBuf,
+
+        /// Also write a GitHub Actions workflow that publishes the vault to GitHub Pages
+        #[arg(long)]
+        github_workflow: bool,
+    },
+    /// Work with custom themes
+    Theme {
+        #[command(subcommand)]
+        action: ThemeAction,
+    },
+    /// Print a shell completion script, e.g. `obs2web completions bash > /etc/bash_completion.d/obs2web`
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ThemeAction {
+    /// Start a custom theme: a copy of the default theme and a README of the variables its templates get
+    New {
+        /// Folder to create the theme in; obs2web loads themes from `templates/`
+        #[arg(default_value = "templates")]
+        name: PathBuf,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Write the site into a directory
+    #[default]
+    Dir,
+    /// Write the site into a single .zip archive
+    Zip,
+    /// Write the site into a single .tar.gz archive
+    #[value(name = "tar.gz")]
+    TarGz,
+}
+
+/// How assets are placed into the output directory.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetMode {
+    /// Reflink (copy-on-write clone) when the filesystem supports it, otherwise copy
+    #[default]
+    Auto,
+    /// Hard link when vault and output share a filesystem, otherwise copy
+    Hardlink,
+    /// Always copy the bytes
+    Copy,
+}
+
+/// Static site generators the vault can be exported to.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Hugo,
+    Zola,
+    /// One JSON line per published note, with its URL, title, tags, text, and links
+    Corpus,
+}
+
+/// CI systems whose annotation format warnings can be printed in.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnnotationFormat {
+    /// GitHub Actions workflow commands (`::warning file=...,line=...::message`)
+    Github,
+}
@@ -0,0 +1,195 @@
+use std::io::Write;
+use std::path::Path;
+use std::thread;
+use clap::CommandFactory;
+use crate::archive::{archive_path, write_archive, OutputFormat};
+use crate::build_site;
+#[cfg(feature = "network")]
+use crate::build_site_into;
+use crate::cli::{Args, BuildArgs, Command, ExportFormat, ThemeAction};
+use crate::config::{load_config, Config};
+use crate::content::{make_comrak_options, render_note};
+use crate::corpus::{export_corpus, Chunking};
+use crate::daemon::run_daemon;
+use crate::deploy::deploy;
+use crate::digest::render_digest;
+use crate::diagnostics::{config_error, info, set_quiet, set_vault, warning};
+use crate::export::export_site;
+use crate::fixtures::run_fixtures;
+use crate::import::import_site;
+use crate::index::SiteIndex;
+use crate::manifest::verify;
+use crate::postprocess::{rewrite_page, HtmlRules};
+#[cfg(feature = "network")]
+use crate::s3::S3Output;
+use crate::scaffold::{init, new_theme};
+use crate::serve;
+use crate::template::init_tera;
+use crate::vfs::{LocalVault, VaultSource};
+use crate::watch::watch;
+
+/// Runs the requested subcommand, or builds the site when there is none.
+pub fn run(args: &Args) -> std::io::Result<()> {
+    let config_path = args.config.as_deref();
+    match &args.command {
+        None => build(&args.build, &args.build.config(config_path, args.build.paths()?.0)?),
+        Some(Command::Build(build_args)) => build(build_args, &build_args.config(config_path, build_args.paths()?.0)?),
+        Some(Command::Serve { build: build_args, server }) => {
+            let (vault_path, output_dir) = build_args.paths()?;
+            let config = build_args.config(config_path, vault_path)?;
+            build_site(vault_path, output_dir, &config)?;
+            let base_path = config.base_path.clone();
+            let (vault, output) = (vault_path.to_path_buf(), output_dir.to_path_buf());
+            thread::spawn(move || {
+                if let Err(e) = watch(&vault, &output, || build_site(&vault, &output, &config)) {
+                    warning!("stopped watching the vault: {e}");
+                }
+            });
+            serve::preview(output_dir, server, base_path.as_deref())
+        }
+        Some(Command::Watch(build_args)) => {
+            let (vault_path, output_dir) = build_args.paths()?;
+            // Read once, as `--config -` can only be read from stdin once
+            let config = build_args.config(config_path, vault_path)?;
+            build(build_args, &config)?;
+            watch(vault_path, output_dir, || build(build_args, &config))
+        }
+        Some(Command::Render { note, vault_path, output }) => {
+            render(vault_path, note, output.as_deref(), &load_config(config_path, vault_path)?)
+        }
+        Some(Command::Digest { vault_path, days, output }) => {
+            digest(vault_path, *days, output.as_deref(), &load_config(config_path, vault_path)?)
+        }
+        Some(Command::TestFixtures { dir, update }) => run_fixtures(dir, *update),
+        Some(Command::Daemon { vault_path }) => run_daemon(vault_path, &load_config(config_path, vault_path)?),
+        Some(Command::Check { vault_path }) => {
+            let mut config = load_config(config_path, vault_path)?;
+            config.strict = true;
+            check(vault_path, &config)
+        }
+        Some(Command::Deploy { build: build_args, dry_run }) => {
+            let (vault_path, output_dir) = build_args.paths()?;
+            let config = build_args.config(config_path, vault_path)?;
+            build(build_args, &config)?;
+            let output = match build_args.output_format {
+                OutputFormat::Dir => output_dir.to_path_buf(),
+                format => archive_path(output_dir, format),
+            };
+            deploy(&output, &config, *dry_run)
+        }
+        Some(Command::Verify { target, manifest }) => {
+            verify(target, manifest.as_deref(), &load_config(config_path, Path::new("."))?)
+        }
+        Some(Command::Preview { path, server }) => serve::preview(path, server, None),
+        Some(Command::Export { format: ExportFormat::Corpus, vault_path, output_dir, chunk_words, chunk_overlap }) => {
+            let chunking = Chunking { words: *chunk_words, overlap: *chunk_overlap };
+            export_corpus(vault_path, output_dir, chunking, &load_config(config_path, vault_path)?)
+        }
+        Some(Command::Export { format, vault_path, output_dir, .. }) => {
+            export_site(vault_path, output_dir, *format, &load_config(config_path, vault_path)?)
+        }
+        Some(Command::Import { site_path, vault_path }) => import_site(site_path, vault_path),
+        Some(Command::Init { vault_path, github_workflow }) => init(vault_path, *github_workflow),
+        Some(Command::Theme { action: ThemeAction::New { name } }) => new_theme(name),
+        Some(Command::Completions { shell }) => {
+            // Generated into a buffer, as clap_complete panics on write errors such as a closed pipe
+            let mut script = Vec::new();
+            clap_complete::generate(*shell, &mut Args::command(), "obs2web", &mut script);
+            std::io::stdout().write_all(&script)
+        }
+    }
+}
+
+/// Builds the site described by `build_args` with `config`, packing it into an archive when requested.
+fn build(build_args: &BuildArgs, config: &Config) -> std::io::Result<()> {
+    let (vault_path, output_dir) = build_args.paths()?;
+    if let Some(url) = output_dir.to_str().filter(|dir| dir.starts_with("s3://")) {
+        if build_args.output_format != OutputFormat::Dir {
+            return Err(config_error(format!("Archives are written to disk; {url} can only be published to as a directory")));
+        }
+        return publish_to_bucket(vault_path, url, config);
+    }
+    match build_args.output_format {
+        OutputFormat::Dir => build_site(vault_path, output_dir, config),
+        format => {
+            // In a folder of its own, so the build's lock file is removed with the staging folder
+            let staging = tempfile::tempdir()?;
+            let site = staging.path().join("site");
+            build_site(vault_path, &site, config)?;
+            write_archive(&site, &archive_path(output_dir, format), format)
+        }
+    }
+}
+
+/// Builds the site straight into the bucket an `s3://bucket/prefix` output names.
+#[cfg(feature = "network")]
+fn publish_to_bucket(vault_path: &Path, url: &str, config: &Config) -> std::io::Result<()> {
+    build_site_into(vault_path, &S3Output::new(url, config)?, config)
+}
+
+#[cfg(not(feature = "network"))]
+fn publish_to_bucket(_vault_path: &Path, url: &str, _config: &Config) -> std::io::Result<()> {
+    Err(config_error(format!("Cannot publish to {url}: obs2web was built without the network feature")))
+}
+
+/// Renders the whole site into a throwaway directory so every link and content
+/// problem is reported, without publishing anything.
+fn check(vault_path: &Path, config: &Config) -> std::io::Result<()> {
+    let staging = tempfile::tempdir()?;
+    build_site(vault_path, &staging.path().join("site"), config)?;
+    info!("Check finished.");
+    Ok(())
+}
+
+/// Writes the digest of recent notes to `output`, or to stdout.
+fn digest(vault_path: &Path, days: u32, output: Option<&Path>, config: &Config) -> std::io::Result<()> {
+    if output.is_none() {
+        set_quiet(true);
+    }
+    set_vault(vault_path);
+    let local = LocalVault::new(vault_path);
+    let index = SiteIndex::build(&local, &local.files(config)?, config)?;
+    let html = render_digest(&index, days, config)?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, html)?;
+            info!("Wrote digest: {}", path.display());
+            Ok(())
+        }
+        None => std::io::stdout().write_all(html.as_bytes()),
+    }
+}
+
+/// Renders one note with the site's templates to `output`, or to stdout. Links
+/// are resolved against an index of the whole vault, as in a full build.
+fn render(vault_path: &Path, note_path: &Path, output: Option<&Path>, config: &Config) -> std::io::Result<()> {
+    if output.is_none() {
+        // Progress messages would end up in the page
+        set_quiet(true);
+    }
+    set_vault(vault_path);
+    let vault = vault_path.canonicalize()?;
+    let source = note_path
+        .canonicalize()
+        .or_else(|_| vault_path.join(note_path).canonicalize())
+        .map_err(|e| std::io::Error::other(format!("Cannot find note {}: {e}", note_path.display())))?;
+    let source = source
+        .strip_prefix(&vault)
+        .map_err(|_| std::io::Error::other(format!("{} is not in the vault {}", note_path.display(), vault_path.display())))?;
+
+    let local = LocalVault::new(vault_path);
+    let index = SiteIndex::build(&local, &local.files(config)?, config)?;
+    let note = index
+        .note(source)
+        .ok_or_else(|| std::io::Error::other(format!("{} is not a published note", source.display())))?;
+    let html = render_note(note, &index, &init_tera(config)?, &make_comrak_options(config), config, None, None)?;
+    let html = rewrite_page(&html, &HtmlRules::new(&config.html_rewrite)?, config.base_path.as_deref())?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, html)?;
+            info!("Wrote HTML: {}", path.display());
+            Ok(())
+        }
+        None => std::io::stdout().write_all(html.as_bytes()),
+    }
+}
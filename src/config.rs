@@ -0,0 +1,513 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use crate::artifacts::ArtifactConfig;
+use crate::authors::AuthorProfile;
+use crate::book::BookConfig;
+use crate::cli::BuildArgs;
+use crate::content::MarkdownOptions;
+use crate::diagnostics::config_error;
+use crate::domain::Audience;
+use crate::encrypt::EncryptedFolder;
+use crate::fields::InlineFields;
+use crate::fs::{AssetMode, OutputSwap};
+use crate::index::UrlStyle;
+use crate::limits::SizePolicy;
+use crate::placeholders::TemplatePlaceholders;
+use crate::postprocess::HtmlRule;
+use crate::scan::ScanPolicy;
+use crate::search::SearchBackend;
+use crate::structured_data::NoteType;
+
+/// File name of the site configuration, looked up in the vault root by default.
+pub const CONFIG_FILE: &str = "obs2web.toml";
+
+/// The obs2web.toml `obs2web init` writes: a few common settings to start from.
+pub const STARTER_CONFIG: &str = r#"# obs2web configuration; command-line flags override these values.
+
+# Public URL of the site, used for canonical links and social previews
+# site_url = "https://notes.example.com"
+# site_name = "My Notes"
+
+# Where page titles come from, tried in order
+title_sources = ["frontmatter", "heading", "filename"]
+
+# Generate a client-side search index and search page
+search = true
+
+# Command `obs2web deploy` runs after building; the build is in $OBS2WEB_OUTPUT
+# deploy_command = "rsync -a --delete \"$OBS2WEB_OUTPUT/\" host:/var/www/notes/"
+"#;
+
+/// Where a note's title comes from; sources are tried in the configured order.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleSource {
+    /// The `title` frontmatter field
+    Frontmatter,
+    /// The first `# Heading` in the body
+    Heading,
+    /// The note's file name
+    Filename,
+}
+/// Settings that control how a site is built.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    pub asset_mode: AssetMode,
+    /// Write into the output folder (`none`), or build next to it and swap the build in only once
+    /// it succeeds, by renaming it into place (`rename`) or flipping the output, a symlink, to it (`symlink`)
+    pub output_swap: OutputSwap,
+    /// When another build is writing to the same output or cache, wait for it instead of failing
+    pub wait_for_lock: bool,
+    /// Size in bytes above which an attachment is reported, left out, or fails the build, per `size_policy`
+    pub max_asset_size: Option<u64>,
+    /// Size in bytes of the whole site above which it is reported, or fails the build with `size_policy = "fail"`
+    pub max_output_size: Option<u64>,
+    /// What happens beyond `max_asset_size` and `max_output_size`: `warn`, `skip` (leave the attachment out), or `fail`
+    pub size_policy: SizePolicy,
+    pub follow_symlinks: bool,
+    pub respect_gitignore: bool,
+    /// Render a history page per note from the vault's git log
+    pub git_history: bool,
+    /// Public URL the site is served from, e.g. `https://notes.example.com`, used where
+    /// absolute URLs are required (such as `og:image`)
+    pub site_url: Option<String>,
+    /// Path the site is served under when it isn't at the root of its domain, e.g. `/notes/`
+    /// for a GitHub Pages project site; root-relative links are moved below it
+    pub base_path: Option<String>,
+    /// How pages are named: `html` (`Note.html`), `directory` (`Note/index.html`, linked as
+    /// `/Note/`), or `clean` (`Note.html` linked as `/Note`, which the server must map)
+    pub url_style: UrlStyle,
+    /// Who wrote the site's notes, a name or the id of one of `authors`; notes can name their own `author`
+    pub author: Option<String>,
+    /// Build the site for one audience: `public` leaves out notes with `audience: internal`
+    pub audience: Option<Audience>,
+    /// Build a public and an internal site in one run, into the `public/` and `internal/` folders of the output
+    pub audience_variants: bool,
+    /// Profiles of the site's authors, each an `[authors.<id>]` table that notes name in their `author`
+    pub authors: BTreeMap<String, AuthorProfile>,
+    /// Write `authors/<id>.html` for every author, listing their notes
+    pub author_pages: bool,
+    /// Describe every note page to search engines as a schema.org article in JSON-LD, with an `og:type`
+    pub structured_data: bool,
+    /// Site name shown on social cards
+    pub site_name: Option<String>,
+    /// Source image (PNG or SVG, relative to the vault) the favicon, touch icon, and web manifest icons are generated from
+    pub icon: Option<PathBuf>,
+    /// Make the site installable and usable offline with a web manifest and a pre-caching service worker
+    pub pwa: bool,
+    /// Prefetch internal links on hover and follow them by swapping the page container instead of reloading
+    pub instant_navigation: bool,
+    /// Class of the element base.html wraps each page's content in, which instant navigation swaps
+    pub page_container_class: String,
+    /// Generate a search index and a search page
+    pub search: bool,
+    /// Write an OpenSearch description and link it from every page, so browsers can add the
+    /// site as a search engine (requires `search` and `site_url`)
+    pub opensearch: bool,
+    /// Index with obs2web's JSON index (`json`) or with Pagefind (`pagefind`)
+    pub search_backend: SearchBackend,
+    /// Command that runs Pagefind, e.g. `npx pagefind`
+    pub pagefind_command: String,
+    /// Size in bytes above which the search index is split into shards loaded on demand
+    pub search_shard_threshold: usize,
+    /// Render a PNG social preview card per page and use it as the `og:image` of pages without a cover
+    pub social_cards: bool,
+    /// SVG template for social cards (defaults to templates/social-card.svg)
+    pub social_card_template: Option<PathBuf>,
+    /// Shell command `obs2web deploy` runs to publish the build, found in `$OBS2WEB_OUTPUT`
+    pub deploy_command: Option<String>,
+    /// Write `SHA256SUMS` with the checksum of every file of the build, which `obs2web verify` checks copies against
+    pub checksum_manifest: bool,
+    /// URLs `obs2web deploy` requests once the site is published, such as a search engine's
+    /// sitemap ping; `{sitemap}` is replaced with the encoded URL of the sitemap
+    pub deploy_pings: Vec<String>,
+    /// After deploying, request every page listed in the sitemap so caches in front of the site hold them
+    pub warm_cache: bool,
+    /// Write `sitemap.xml`, listing every page with the date it was last updated (requires `site_url`)
+    pub sitemap: bool,
+    /// Repository web URL used to link commits, e.g. `https://github.com/user/vault`
+    pub repo_url: Option<String>,
+    /// Order in which title sources are tried
+    pub title_sources: Vec<TitleSource>,
+    /// Remove a leading H1 that duplicates the page title from the rendered body
+    pub strip_title_heading: bool,
+    /// Demote every heading in note bodies by one level, since templates render the title as h1
+    pub shift_headings: bool,
+    /// Report content problems (such as images without alt text) as warnings
+    pub strict: bool,
+    /// Only render notes whose vault-relative path matches one of these globs
+    pub only: Vec<String>,
+    /// Offer a "print / save as PDF" button on every page (notes can opt out with `print: false`)
+    pub print_button: bool,
+    /// Put a QR code of the page's URL in the footer of every page (requires `site_url`;
+    /// notes can opt out with `qr: false`)
+    pub qr_codes: bool,
+    /// Turn external links into numbered references listed at the end of the page
+    pub external_link_citations: bool,
+    /// BibTeX (`.bib`) or CSL JSON (`.json`) files, relative to the vault, that `[@citekey]` citations are resolved against
+    pub bibliography: Vec<PathBuf>,
+    /// Give fenced code blocks a header with their `title="..."` and a copy button
+    pub code_block_headers: bool,
+    /// Render `> [!note]` blockquotes (Obsidian callouts and GitHub alerts) as styled callouts, and `>! text` ones as spoilers
+    pub callouts: bool,
+    /// Number headings 1, 1.1, 1.2, ... within each page
+    pub number_headings: bool,
+    /// Render H2 and H3 sections as `<details>` blocks readers can fold, headed by the section's heading
+    pub fold_sections: bool,
+    /// Number `![[image.png|fig: Caption]]` figures and `Table: Caption` tables, and resolve `@fig:`/`@tbl:` references
+    pub number_figures: bool,
+    /// Render `![[image.png|caption: Caption]]`, and image embeds directly followed by an italic line, as captioned figures
+    pub image_captions: bool,
+    /// Replace YouTube and Vimeo links alone on a line, and `![](video-url)` images, with players that load when clicked
+    pub video_facades: bool,
+    /// Replace links to tweets and Mastodon posts alone on a line with static copies of the posts, fetched at build time
+    pub post_embeds: bool,
+    /// Render external links alone in a paragraph or list item as bookmark cards, from the pages' Open Graph metadata fetched at build time
+    pub link_cards: bool,
+    /// Locale the `date` template filter names months and weekdays in, e.g. `de_DE` or `vi_VN`
+    pub locale: Option<String>,
+    /// chrono format the `date` template filter uses when a template doesn't give one
+    pub date_format: String,
+    /// IANA timezone, e.g. `Asia/Ho_Chi_Minh`, dates are shown in; dates written without an offset are taken to be in it (UTC by default)
+    pub timezone: Option<String>,
+    /// Write `changes.html`, listing the notes recent builds added, changed, or removed
+    pub changes_page: bool,
+    /// Show what changed in each updated note on the changes page, word by word
+    pub changes_diffs: bool,
+    /// Most changes the changes page lists
+    pub changes_limit: usize,
+    /// Write `notes.html`, a table of every note that can be sorted by column and filtered
+    pub notes_table: bool,
+    /// Write `random.html`, which opens a note picked at random, and link it from every page
+    pub random_note: bool,
+    /// Open a palette with Ctrl+K on every page that finds notes by title or `#tag` and lists the current note's links and backlinks
+    pub command_palette: bool,
+    /// Write the Q/A cards of notes tagged `flashcard_tag` to `flashcards.tsv`, an Anki deck, and `flashcards.html`, a page to review them
+    pub flashcards: bool,
+    /// Tag marking the notes `flashcards` reads cards from; nested tags such as `flashcards/spanish` count too
+    pub flashcard_tag: String,
+    /// Write `timeline.html`, the notes with an `event_date` or `date` in chronological order, grouped by year
+    pub timeline: bool,
+    /// Write `map.html`, a map of the notes with a `location`, its markers linking to the notes
+    pub map: bool,
+    /// URL template of the map's tiles, with `{z}`, `{x}`, and `{y}`
+    pub map_tiles: String,
+    /// Credit for the tiles, shown on the map as their provider requires
+    pub map_attribution: String,
+    /// Vault folders, besides those holding a `_gallery.md`, published with a `gallery.html` of their images
+    pub galleries: Vec<PathBuf>,
+    /// Largest width or height, in pixels, of the thumbnails on gallery pages
+    pub gallery_thumbnail_size: u32,
+    /// How many of the most recently updated notes templates get as `recent_notes`
+    pub recent_notes: usize,
+    /// List the notes that write a note's title without linking to it on the note's page
+    pub unlinked_mentions: bool,
+    /// Link the first unlinked mention of each note's title within every other note
+    pub link_mentions: bool,
+    /// Show the notes each page links to in a panel below it
+    pub outgoing_links_panel: bool,
+    /// Warn about (`warn`), leave out (`strip`), or publish (`keep`) Templater `<% ... %>` and
+    /// `{{date}}`/`{{time}}`/`{{title}}` placeholders left unexpanded in notes
+    pub template_placeholders: TemplatePlaceholders,
+    /// Show Dataview inline fields (`Key:: Value`) as written (`plain`), leave them out (`hide`), or style them (`style`)
+    pub inline_fields: InlineFields,
+    /// Show the frontmatter as a panel of properties above each note, as Obsidian does
+    pub properties_panel: bool,
+    /// Fields the properties panel shows, in any order; every field when empty
+    pub properties_show: Vec<String>,
+    /// Fields the properties panel never shows
+    pub properties_hide: Vec<String>,
+    /// Frontmatter and inline fields dropped from every note before it is read, so they never reach the site
+    pub private_fields: Vec<String>,
+    /// Look through every note for email addresses, API keys, tokens, and `secret_patterns` before publishing
+    pub secret_scan: bool,
+    /// What a note with a hit does to the build: `fail` it (the default), `exclude` the note, or only `warn`
+    pub secret_scan_policy: ScanPolicy,
+    /// Regular expressions of further text the scan reports, such as client names or internal hostnames
+    pub secret_patterns: Vec<String>,
+    /// Regular expressions of hits that may be published, such as your public email address
+    pub secret_allow: Vec<String>,
+    /// Write a `.json` file next to every note's page with its title, dates, tags, summary, links, and backlinks
+    pub note_metadata: bool,
+    /// Write a `.md` copy of every note next to its page, with its links pointing at the site
+    pub markdown_files: bool,
+    /// Write a `.txt` file of every note's text, without markup, next to its page
+    pub text_files: bool,
+    /// Write an ActivityPub actor, outbox, and WebFinger file so the site's dated notes can be
+    /// followed from the fediverse (requires `site_url`; delivery is left to an external service)
+    pub activitypub: bool,
+    /// Name the site's actor is found under, as in `@notes@example.com`
+    pub activitypub_username: String,
+    /// Inbox of the service that accepts follows and delivers new posts for the site
+    pub activitypub_inbox: Option<String>,
+    /// PEM public key of the delivery service, which signs its deliveries as the site's actor
+    pub activitypub_public_key: Option<String>,
+    /// Most notes the outbox lists
+    pub activitypub_limit: usize,
+    /// Folder, relative to the working directory, that responses fetched at build time are cached in,
+    /// along with the changes page's record of earlier builds
+    pub cache_dir: PathBuf,
+    /// Only use responses already in the cache instead of fetching anything
+    pub offline: bool,
+    /// Seconds to wait for a response when fetching
+    pub http_timeout: u64,
+    /// Milliseconds to leave between two requests to the same host
+    pub http_request_interval_ms: u64,
+    /// Split notes of at least `split_min_words` words into pages at their headings
+    pub split_notes: bool,
+    /// Deepest heading level notes are split at: 1 splits at H1s, 2 at H1s and H2s
+    pub split_heading_level: usize,
+    pub split_min_words: usize,
+    /// Long-form pages combining notes in order, each a `[[book]]` table
+    #[serde(rename = "book")]
+    pub books: Vec<BookConfig>,
+    /// Files generated for every page by external commands, each a `[[page_artifact]]` table
+    #[serde(rename = "page_artifact")]
+    pub page_artifacts: Vec<ArtifactConfig>,
+    /// Templates and schema.org types for notes by their frontmatter `type`, each a `[[note_type]]` table
+    #[serde(rename = "note_type")]
+    pub note_types: Vec<NoteType>,
+    /// Folders whose pages are published encrypted behind a password, each an `[[encrypted_folder]]` table
+    #[serde(rename = "encrypted_folder")]
+    pub encrypted_folders: Vec<EncryptedFolder>,
+    /// Rules applied to the rendered pages, such as wrapping tables or marking external links
+    pub html_rewrite: Vec<HtmlRule>,
+    /// Markdown extensions and rendering options
+    pub markdown: MarkdownOptions,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            asset_mode: AssetMode::default(),
+            output_swap: OutputSwap::default(),
+            wait_for_lock: false,
+            max_asset_size: None,
+            max_output_size: None,
+            size_policy: SizePolicy::default(),
+            follow_symlinks: false,
+            respect_gitignore: true,
+            git_history: false,
+            site_name: None,
+            icon: None,
+            pwa: false,
+            instant_navigation: false,
+            page_container_class: "page".to_string(),
+            search: false,
+            opensearch: false,
+            search_backend: SearchBackend::default(),
+            pagefind_command: "pagefind".to_string(),
+            search_shard_threshold: 512 * 1024,
+            social_cards: false,
+            social_card_template: None,
+            deploy_command: None,
+            checksum_manifest: false,
+            deploy_pings: Vec::new(),
+            warm_cache: false,
+            sitemap: false,
+            repo_url: None,
+            site_url: None,
+            base_path: None,
+            url_style: UrlStyle::default(),
+            author: None,
+            structured_data: false,
+            audience: None,
+            audience_variants: false,
+            authors: BTreeMap::new(),
+            author_pages: false,
+            title_sources: vec![TitleSource::Frontmatter, TitleSource::Heading, TitleSource::Filename],
+            strip_title_heading: false,
+            shift_headings: false,
+            strict: false,
+            only: Vec::new(),
+            print_button: false,
+            qr_codes: false,
+            external_link_citations: false,
+            bibliography: Vec::new(),
+            code_block_headers: false,
+            callouts: true,
+            number_headings: false,
+            fold_sections: false,
+            number_figures: false,
+            image_captions: false,
+            video_facades: false,
+            post_embeds: false,
+            link_cards: false,
+            locale: None,
+            date_format: "%Y-%m-%d".to_string(),
+            timezone: None,
+            changes_page: false,
+            changes_diffs: false,
+            changes_limit: 30,
+            notes_table: false,
+            random_note: false,
+            command_palette: false,
+            flashcards: false,
+            flashcard_tag: "flashcards".to_string(),
+            timeline: false,
+            map: false,
+            map_tiles: "https://tile.openstreetmap.org/{z}/{x}/{y}.png".to_string(),
+            map_attribution: "&copy; OpenStreetMap contributors".to_string(),
+            galleries: Vec::new(),
+            gallery_thumbnail_size: 400,
+            recent_notes: 0,
+            unlinked_mentions: false,
+            link_mentions: false,
+            outgoing_links_panel: false,
+            template_placeholders: TemplatePlaceholders::default(),
+            inline_fields: InlineFields::default(),
+            properties_panel: false,
+            properties_show: Vec::new(),
+            // Settings for obs2web and Obsidian rather than facts about the note
+            properties_hide: ["title", "publish", "template", "cssclasses", "aliases", "id", "uid", "partial", "templating"]
+                .map(String::from)
+                .to_vec(),
+            private_fields: Vec::new(),
+            secret_scan: false,
+            secret_scan_policy: ScanPolicy::default(),
+            secret_patterns: Vec::new(),
+            secret_allow: Vec::new(),
+            note_metadata: false,
+            markdown_files: false,
+            text_files: false,
+            activitypub: false,
+            activitypub_username: "notes".to_string(),
+            activitypub_inbox: None,
+            activitypub_public_key: None,
+            activitypub_limit: 20,
+            cache_dir: PathBuf::from(".obs2web-cache"),
+            offline: false,
+            http_timeout: 10,
+            http_request_interval_ms: 500,
+            split_notes: false,
+            split_heading_level: 2,
+            split_min_words: 5000,
+            books: Vec::new(),
+            page_artifacts: Vec::new(),
+            note_types: Vec::new(),
+            encrypted_folders: Vec::new(),
+            html_rewrite: Vec::new(),
+            markdown: MarkdownOptions::default(),
+        }
+    }
+}
+
+/// Prefix of environment variables that set config values, e.g. `OBS2WEB_SITE_URL`.
+const ENV_PREFIX: &str = "OBS2WEB_";
+/// Variables with the prefix that aren't config keys: command-line flags, the
+/// deploy command's input, and the man page directory of the build script.
+const NON_CONFIG_ENV: [&str; 5] =
+    ["OBS2WEB_CONFIG", "OBS2WEB_VAULT_PATH", "OBS2WEB_OUTPUT_DIR", "OBS2WEB_OUTPUT", "OBS2WEB_MAN_DIR"];
+
+/// Reads the config file given with `--config` (`-` for stdin), or the vault's
+/// obs2web.toml if it has one, then applies `OBS2WEB_*` environment variables.
+/// Unset fields keep their defaults.
+pub fn load_config(config_path: Option<&Path>, vault_path: &Path) -> io::Result<Config> {
+    let default_path = vault_path.join(CONFIG_FILE);
+    let mut sources = Vec::new();
+    let mut table = match config_path {
+        Some(path) if path == Path::new("-") => {
+            sources.push("stdin".to_string());
+            read_config(path)?
+        }
+        Some(path) => {
+            sources.push(path.display().to_string());
+            read_config(path)?
+        }
+        None if default_path.is_file() => {
+            sources.push(default_path.display().to_string());
+            read_config(&default_path)?
+        }
+        None => toml::Table::new(),
+    };
+    if apply_env(&mut table) {
+        sources.push("OBS2WEB_* environment variables".to_string());
+    }
+    toml::Value::Table(table)
+        .try_into()
+        .map_err(|e| config_error(format!("Config error in {}: {e}", sources.join(" or "))))
+}
+
+/// Parses a config as TOML, or as JSON when it comes from a `.json` file or is a JSON object on stdin.
+fn read_config(path: &Path) -> io::Result<toml::Table> {
+    let from_stdin = path == Path::new("-");
+    let (text, source, json) = if from_stdin {
+        let text = io::read_to_string(io::stdin())?;
+        let json = text.trim_start().starts_with('{');
+        (text, "stdin".to_string(), json)
+    } else {
+        let text = fs::read_to_string(path)
+            .map_err(|e| config_error(format!("Cannot read config {}: {e}", path.display())))?;
+        (text, path.display().to_string(), path.extension().is_some_and(|ext| ext == "json"))
+    };
+    let error = |e: String| config_error(format!("Config error in {source}: {e}"));
+    if json {
+        serde_json::from_str(&text).map_err(|e| error(e.to_string()))
+    } else {
+        toml::from_str(&text).map_err(|e| error(e.to_string()))
+    }
+}
+
+/// Sets `key` from every `OBS2WEB_<KEY>` variable. Values are read as TOML values
+/// where they parse as one (`true`, `8080`, `["a", "b"]`) and as strings otherwise.
+/// Returns whether any variable applied.
+fn apply_env(table: &mut toml::Table) -> bool {
+    let mut applied = false;
+    for (name, value) in std::env::vars() {
+        let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        if key.is_empty() || NON_CONFIG_ENV.contains(&name.as_str()) {
+            continue;
+        }
+        let value = toml::from_str::<toml::Table>(&format!("value = {value}"))
+            .ok()
+            .and_then(|mut parsed| parsed.remove("value"))
+            .unwrap_or(toml::Value::String(value));
+        table.insert(key.to_lowercase(), value);
+        applied = true;
+    }
+    applied
+}
+
+impl BuildArgs {
+    /// Build settings from the config file with command-line overrides applied.
+    pub fn config(&self, config_path: Option<&Path>, vault_path: &Path) -> io::Result<Config> {
+        let mut config = load_config(config_path, vault_path)?;
+        if let Some(mode) = self.asset_mode {
+            config.asset_mode = mode;
+        }
+        if self.follow_symlinks {
+            config.follow_symlinks = true;
+        }
+        if self.no_gitignore {
+            config.respect_gitignore = false;
+        }
+        if self.git_history {
+            config.git_history = true;
+        }
+        if let Some(url) = &self.repo_url {
+            config.repo_url = Some(url.clone());
+        }
+        if let Some(base_path) = &self.base_path {
+            config.base_path = Some(base_path.clone());
+        }
+        if self.strict {
+            config.strict = true;
+        }
+        if self.offline {
+            config.offline = true;
+        }
+        if self.wait {
+            config.wait_for_lock = true;
+        }
+        if !self.only.is_empty() {
+            config.only = self.only.clone();
+        }
+        Ok(config)
+    }
+}
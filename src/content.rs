@@ -1,177 +1,600 @@
-use comrak::{ComrakOptions, ComrakRenderOptions, ListStyleType};
-use gray_matter::engine::YAML;
-use gray_matter::Matter;
+use chrono_tz::Tz;
+use comrak::{Anchorizer, ComrakOptions, ComrakRenderOptions, ListStyleType};
+use gray_matter::engine::{Engine, JSON, TOML, YAML};
+use gray_matter::{Matter, Pod};
+use lol_html::{element, rewrite_str, RewriteStrSettings};
+use serde::Deserialize;
 use std::collections::HashMap;
-use std::fs;
 use std::path::Path;
 use tera::{Context, Tera};
-use crate::domain::{Frontmatter, Note};
-
-fn rewrite_links(content: &str) -> String {
-    let mut new_content = String::new();
-    let mut last_index = 0;
-    let mut in_link = false;
-    let mut in_asset = false;
-    let mut link_text = String::new();
-
-    for (i, c) in content.char_indices() {
-        if c == '[' && content.chars().nth(i + 1) == Some('[') {
-            if !in_link && !in_asset {
-                in_link = true;
-                new_content.push_str(&content[last_index..i]);
-                last_index = i;
+use crate::authors::note_authors;
+use crate::config::Config;
+use crate::dates::{build_time, site_timezone, template_date};
+use crate::diagnostics::{broken_link, info, warning};
+use crate::domain::Note;
+use crate::fields::render_inline_fields;
+use crate::figures::{caption_alias, caption_images, figure_alias, figure_html, number_figures};
+use crate::history::{note_history, Revision};
+use crate::index::{frontmatter_link, url_for, IndexedNote, LinkTarget, SiteIndex};
+use crate::links::{heading_slug, replace_wikilinks, WikiLink};
+use crate::markdown::{admonitions_to_callouts, markdown_to_html};
+use crate::postprocess::with_base_path;
+use crate::qr::qr_code_svg;
+use crate::search::opensearch_name;
+use crate::social::card_path;
+use crate::split::part_nav;
+use crate::properties::properties_html;
+use crate::structured_data::{json_ld, note_type};
+use crate::vfs::OutputSink;
+
+/// Turns wikilinks and embeds into HTML pointing at the pages and assets they resolve to.
+fn rewrite_links(content: &str, note: &IndexedNote, index: &SiteIndex, tera: &Tera, config: &Config) -> String {
+    let mut figures = 0;
+    replace_wikilinks(content, |link| {
+        // Numbered as number_figures counts them: every `fig:` embed, whether it resolves or not
+        let figure = link.alias.as_deref().filter(|_| link.embed && config.number_figures).and_then(figure_alias);
+        let caption = link.alias.as_deref().filter(|_| link.embed && config.image_captions).and_then(caption_alias);
+        if figure.is_some() {
+            figures += 1;
+        }
+        let label = escape_html(&link.label());
+        let slug = |target: &IndexedNote| link_anchor(link, target);
+        if link.target.is_empty() {
+            // [[#Heading]] points into the current note, which may be on another of its pages
+            let slug = slug(note);
+            let anchor = slug.as_deref().map(|s| format!("#{s}")).unwrap_or_default();
+            return match (&slug, note.parts.is_empty()) {
+                (Some(slug), false) => format!("<a href=\"{}{anchor}\">{label}</a>", note.heading_url(slug)),
+                _ => format!("<a href=\"{anchor}\">{label}</a>"),
+            };
+        }
+        match index.resolve(&link.target) {
+            Some(LinkTarget::Note(target)) => {
+                let slug = slug(target);
+                let anchor = slug.as_deref().map(|s| format!("#{s}")).unwrap_or_default();
+                let url = slug.as_deref().map_or(target.url.as_str(), |slug| target.heading_url(slug));
+                format!("<a href=\"{url}{anchor}\">{label}</a>")
             }
-        } else if c == '!' && content.chars().nth(i + 1) == Some('[') && content.chars().nth(i + 2) == Some('[') {
-            if !in_link && !in_asset {
-                in_asset = true;
-                new_content.push_str(&content[last_index..i]);
-                last_index = i;
+            Some(LinkTarget::Asset(asset)) => {
+                let url = url_for(asset);
+                if is_image(asset)
+                    && let Some((label, caption)) = figure
+                {
+                    let image = format!("<img src=\"{url}\" alt=\"{}\">", escape_html(caption));
+                    let id = label.map(|label| format!("fig-{label}"));
+                    figure_html(&image, id.as_deref(), Some(figures), caption)
+                } else if is_image(asset)
+                    && let Some(caption) = caption
+                {
+                    let image = format!("<img src=\"{url}\" alt=\"{}\">", escape_html(caption));
+                    figure_html(&image, None, None, caption)
+                } else if link.embed && is_image(asset) {
+                    image_embed(link, asset, &url, note, config)
+                } else if link.embed
+                    && let Some(kind) = media_kind(asset)
+                {
+                    media_embed(link, asset, kind, &url, note, index, tera)
+                } else {
+                    format!("<a href=\"{url}\">{label}</a>")
+                }
             }
-        } else if c == ']' && content.chars().nth(i + 1) == Some(']') {
-            if in_link {
-                in_link = false;
-                let link_slug = link_text.to_lowercase().replace(" ", "-");
-                let html_link = format!("<a href=\"{}.html\">{}</a>", link_slug, link_text);
-                new_content.push_str(&html_link);
-                link_text.clear();
-                last_index = i + 2;
-            } else if in_asset {
-                in_asset = false;
-                let html_link = format!("<img src=\"{}\">", link_text);
-                new_content.push_str(&html_link);
-                link_text.clear();
-                last_index = i + 2;
+            // Left out on purpose, so shown as text without a warning
+            Some(LinkTarget::Unpublished(_)) => format!("<span class=\"unpublished-link\">{label}</span>"),
+            None => {
+                broken_link!(file: &note.source, near: &format!("[[{}", link.target); "unresolved link [[{}]] in {}", link.target, note.source.display());
+                format!("<span class=\"unresolved-link\">{label}</span>")
             }
-        } else if in_link || in_asset {
-            if c != '[' && c != '!' {
-                link_text.push(c);
+        }
+    })
+}
+
+/// The anchor `link`'s heading has on the linked note's page, repeated headings
+/// included, or the block's `^id`.
+pub(crate) fn link_anchor(link: &WikiLink, target: &IndexedNote) -> Option<String> {
+    let heading = link.heading.as_deref()?;
+    if heading.starts_with('^') {
+        return Some(heading.to_string());
+    }
+    Some(target.heading_anchor(heading).map_or_else(
+        || heading_slug(heading.rsplit('#').next().unwrap_or(heading)),
+        str::to_string,
+    ))
+}
+
+/// Renders `![[image.png|alt text]]`. Obsidian's size syntax (`|100` or `|100x200`)
+/// sets the dimensions instead, and the alt text can also come from the
+/// frontmatter's `alt` map keyed by file name.
+fn image_embed(link: &WikiLink, asset: &Path, url: &str, note: &IndexedNote, config: &Config) -> String {
+    let mut attrs = String::new();
+    let mut alt = None;
+    if let Some(alias) = &link.alias {
+        match image_size(alias) {
+            Some((width, height)) => {
+                attrs.push_str(&format!(" width=\"{width}\""));
+                if !height.is_empty() {
+                    attrs.push_str(&format!(" height=\"{height}\""));
+                }
             }
-        } else {
-            // new_content.push(c);
+            None => alt = Some(alias.clone()),
+        }
+    }
+    let alt = alt.or_else(|| frontmatter_alt(link, asset, note));
+    match alt {
+        Some(alt) => format!("<img src=\"{url}\" alt=\"{}\"{attrs}>", escape_html(&alt)),
+        None => {
+            if config.strict {
+                warning!(file: &note.source, near: &link.target; "image {} has no alt text in {}", link.target, note.source.display());
+            }
+            format!("<img src=\"{url}\" alt=\"\"{attrs}>")
         }
     }
-    new_content.push_str(&content[last_index..]);
-    new_content
 }
 
-pub fn make_comrak_options() -> ComrakOptions {
+/// The partial an audio or video file is embedded with.
+fn media_kind(path: &Path) -> Option<&'static str> {
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or_default().to_lowercase();
+    match ext.as_str() {
+        "mp3" | "wav" | "m4a" | "ogg" | "oga" | "flac" | "aac" | "opus" => Some("audio"),
+        "mp4" | "webm" | "ogv" | "mov" | "m4v" => Some("video"),
+        _ => None,
+    }
+}
+
+pub(crate) fn media_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or_default().to_lowercase();
+    Some(match ext.as_str() {
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "m4a" | "aac" => "audio/mp4",
+        "ogg" | "oga" | "opus" => "audio/ogg",
+        "flac" => "audio/flac",
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "ogv" => "video/ogg",
+        "mov" => "video/quicktime",
+        _ => return None,
+    })
+}
+
+/// Renders `![[talk.mp3]]` or `![[demo.mp4|Title]]` with the theme's `audio.html` or
+/// `video.html` partial. Sidecar files next to the media, `talk.vtt` and `talk.txt`,
+/// are passed on as its captions and transcript.
+fn media_embed(
+    link: &WikiLink,
+    asset: &Path,
+    kind: &str,
+    url: &str,
+    note: &IndexedNote,
+    index: &SiteIndex,
+    tera: &Tera,
+) -> String {
+    let sidecar = |ext: &str| match index.resolve(&asset.with_extension(ext).to_string_lossy()) {
+        Some(LinkTarget::Asset(path)) => Some(url_for(path)),
+        _ => None,
+    };
+    let title = link.alias.clone().unwrap_or_else(|| asset.file_name().unwrap_or_default().to_string_lossy().into_owned());
+    let mut context = Context::new();
+    context.insert("url", url);
+    context.insert("title", &title);
+    context.insert("mime_type", &media_type(asset));
+    context.insert("captions", &sidecar("vtt"));
+    context.insert("transcript", &sidecar("txt"));
+    match tera.render(&format!("{kind}.html"), &context) {
+        // Blank lines would end the HTML block the player is written as
+        Ok(html) => html.lines().map(str::trim_end).filter(|line| !line.is_empty()).collect::<Vec<_>>().join("\n"),
+        Err(e) => {
+            warning!(file: &note.source, near: &link.target; "failed to render {kind}.html for {} in {}: {e}", link.target, note.source.display());
+            format!("<a href=\"{url}\">{}</a>", escape_html(&title))
+        }
+    }
+}
+
+/// The width and height (empty when not given) an image embed's alias of `100`
+/// or `100x200` sets, or nothing when the alias is alt text.
+pub(crate) fn image_size(alias: &str) -> Option<(&str, &str)> {
+    let (width, height) = alias.split_once('x').unwrap_or((alias, ""));
+    let numeric = |text: &str| text.chars().all(|c| c.is_ascii_digit());
+    (!width.is_empty() && numeric(width) && numeric(height)).then_some((width, height))
+}
+
+/// The alt text the note's frontmatter `alt` map gives an embedded image, by
+/// link target or file name.
+pub(crate) fn frontmatter_alt(link: &WikiLink, asset: &Path, note: &IndexedNote) -> Option<String> {
+    let alts = note.frontmatter.alt.as_ref()?;
+    let file_name = asset.file_name().unwrap_or_default().to_string_lossy();
+    alts.get(&link.target).or_else(|| alts.get(file_name.as_ref())).cloned()
+}
+
+pub(crate) fn is_image(path: &Path) -> bool {
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or_default().to_lowercase();
+    matches!(ext.as_str(), "png" | "jpg" | "jpeg" | "gif" | "bmp" | "svg" | "webp" | "avif")
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Splits a note into its frontmatter and body. The format is detected per file:
+/// `---` fences YAML, `+++` fences TOML (as in Hugo), and a JSON object opening the
+/// file with its closing brace alone on a line is read as JSON.
+pub(crate) fn split_frontmatter(markdown: &str) -> (Option<Pod>, String) {
+    if markdown.starts_with("+++") {
+        let mut matter = Matter::<TOML>::new();
+        matter.delimiter = "+++".to_string();
+        let result = matter.parse(markdown);
+        return (result.data, result.content);
+    }
+    if markdown.starts_with('{')
+        && let Some(end) = markdown.find("\n}")
+    {
+        let (json, body) = markdown.split_at(end + 2);
+        // A note that merely starts with a brace is left untouched
+        if let data @ Pod::Hash(_) = JSON::parse(json) {
+            return (Some(data), body.trim_start_matches(['\r', '\n']).to_string());
+        }
+    }
+    let result = Matter::<YAML>::new().parse(markdown);
+    (result.data, result.content)
+}
+
+/// Bullet used when comrak writes out a list; the `list_style` of `[markdown]`.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ListStyle {
+    Dash,
+    #[default]
+    Plus,
+    Star,
+}
+
+/// Markdown extensions and rendering options, the `[markdown]` table. The
+/// defaults are the GitHub-flavoured set obs2web has always rendered with.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkdownOptions {
+    pub tables: bool,
+    /// Bare URLs and `www.` addresses become links
+    pub autolink: bool,
+    /// Escape the HTML tags GitHub filters, such as `<script>` and `<iframe>`
+    pub tagfilter: bool,
+    pub strikethrough: bool,
+    pub tasklists: bool,
+    /// `^superscript^`
+    pub superscript: bool,
+    /// `~subscript~`, leaving `~~strikethrough~~` to double tildes
+    pub subscript: bool,
+    /// Terms followed by `: definition` lines
+    pub description_lists: bool,
+    /// `[^1]` footnote references and definitions
+    pub footnotes: bool,
+    /// Curly quotes, dashes from `--` and `---`, and ellipses
+    pub smart_punctuation: bool,
+    /// Keep raw HTML in notes; when off it is replaced with a comment
+    pub unsafe_html: bool,
+    /// Every line break within a paragraph becomes `<br>`
+    pub hard_breaks: bool,
+    pub list_style: ListStyle,
+    /// Delimiter of a further front matter block to leave out of the page, e.g. `+++`
+    pub front_matter_delimiter: Option<String>,
+}
+
+impl Default for MarkdownOptions {
+    fn default() -> Self {
+        MarkdownOptions {
+            tables: true,
+            autolink: true,
+            tagfilter: true,
+            strikethrough: true,
+            tasklists: true,
+            superscript: false,
+            subscript: false,
+            description_lists: true,
+            footnotes: false,
+            smart_punctuation: true,
+            unsafe_html: true,
+            hard_breaks: false,
+            list_style: ListStyle::default(),
+            front_matter_delimiter: None,
+        }
+    }
+}
+
+pub fn make_comrak_options(config: &Config) -> ComrakOptions {
+    let markdown = &config.markdown;
     let mut comrak_options = ComrakOptions::default();
-    comrak_options.extension.table = true;
-    comrak_options.extension.autolink = true;
-    comrak_options.extension.tagfilter = true;
-    comrak_options.extension.strikethrough = true;
-    comrak_options.extension.tasklist = true;
-    comrak_options.parse.smart = true;
+    comrak_options.extension.table = markdown.tables;
+    comrak_options.extension.autolink = markdown.autolink;
+    comrak_options.extension.tagfilter = markdown.tagfilter;
+    comrak_options.extension.strikethrough = markdown.strikethrough || markdown.subscript;
+    comrak_options.extension.tasklist = markdown.tasklists;
+    comrak_options.extension.superscript = markdown.superscript;
+    comrak_options.extension.description_lists = markdown.description_lists;
+    comrak_options.extension.footnotes = markdown.footnotes;
+    comrak_options.extension.front_matter_delimiter = markdown.front_matter_delimiter.clone();
+    comrak_options.extension.header_ids = Some(String::new());
+    comrak_options.parse.smart = markdown.smart_punctuation;
     let mut render_options = ComrakRenderOptions::default();
-    render_options.unsafe_ = true;
-    render_options.list_style=ListStyleType::Plus;
+    render_options.unsafe_ = markdown.unsafe_html;
+    render_options.hardbreaks = markdown.hard_breaks;
+    render_options.list_style = match markdown.list_style {
+        ListStyle::Dash => ListStyleType::Dash,
+        ListStyle::Plus => ListStyleType::Plus,
+        ListStyle::Star => ListStyleType::Star,
+    };
     comrak_options.render = render_options;
     comrak_options
 }
 
 pub fn process_markdown_file(
-    path: &Path,
-    output_dir: &Path,
+    note: &IndexedNote,
+    index: &SiteIndex,
+    output: &dyn OutputSink,
     tera: &Tera,
     comrak_options: &ComrakOptions,
-    notes: &mut Vec<Note>,
+    config: &Config,
     tags: &mut HashMap<String, Vec<Note>>,
 ) -> std::io::Result<()> {
-    // Compute output path next to output_dir using the vault-relative location
-    // The caller guarantees parent dirs exist.
-    println!("Converting markdown: {}", path.display());
-
-    let markdown_content = fs::read_to_string(path)?;
-    let matter = Matter::<YAML>::new();
-    let result = matter.parse(&markdown_content);
-
-    let (frontmatter, content) = match result.data {
-        Some(data) => {
-            let fm = data.deserialize::<Frontmatter>().map_err(|e| {
-                std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("Frontmatter deserialize error in {}: {e}", path.display()),
-                )
-            })?;
-            (Some(fm), result.content)
+    info!("Converting markdown: {}", note.source.display());
+
+    let fm = &note.frontmatter;
+    if let Some(tag_list) = &fm.tags {
+        for tag in tag_list {
+            tags.entry(tag.clone()).or_default().push(note.to_note());
         }
-        None => (None, result.content),
-    };
+    }
+    let mut history_url = None;
+    // History comes from git, so only vaults read from disk have one
+    if config.git_history
+        && let Some(vault_path) = &index.vault_path
+    {
+        let revisions = note_history(vault_path, &note.source, config.repo_url.as_deref());
+        if !revisions.is_empty() {
+            let history_path = note.output.with_extension("history.html");
+            render_history_page(tera, output, &history_path, note, &revisions)?;
+            history_url = Some(config.url_style.url(&history_path));
+        }
+    }
+    if note.parts.is_empty() {
+        let rendered_html = render_note(note, index, tera, comrak_options, config, history_url.as_deref(), None)?;
+        output.write(&note.output, rendered_html.as_bytes())?;
+        info!("Wrote HTML: {}", note.output.display());
+        return Ok(());
+    }
+    for (i, part) in note.parts.iter().enumerate() {
+        // The history belongs to the whole note, so it is linked from the first page
+        let history_url = history_url.as_deref().filter(|_| i == 0);
+        let rendered_html = render_note(note, index, tera, comrak_options, config, history_url, Some(i))?;
+        output.write(&part.output, rendered_html.as_bytes())?;
+    }
+    info!("Wrote HTML: {} in {} parts", note.output.display(), note.parts.len());
+    Ok(())
+}
 
-    let content_with_links = rewrite_links(&content);
-    let html_content = comrak::markdown_to_html(&content_with_links, comrak_options);
+/// Renders a note's page with its template, linking to its history page when there is one.
+/// For a split note, `part` picks the page (an index into `note.parts`) to render.
+pub fn render_note(
+    note: &IndexedNote,
+    index: &SiteIndex,
+    tera: &Tera,
+    comrak_options: &ComrakOptions,
+    config: &Config,
+    history_url: Option<&str>,
+    part: Option<usize>,
+) -> std::io::Result<String> {
+    let page = part.map(|i| &note.parts[i]);
+    let body = page.map_or(note.body.as_str(), |page| &note.body[page.body.clone()]);
+    let anchors = page.map_or_else(Anchorizer::new, |page| note.anchors_before(page.body.start));
+    let html_content = note_html(body, note, index, tera, comrak_options, config, anchors);
 
-    let mut context = Context::new();
-    let fallback_title = path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("untitled")
-        .to_string();
-    let title = if let Some(fm) = &frontmatter {
-        fm.title.clone().unwrap_or_else(|| fallback_title.clone())
-    } else {
-        fallback_title.clone()
+    let (output, url) = page.map_or((&note.output, &note.url), |page| (&page.output, &page.url));
+    let mut context = page_context(output, index, config);
+    let fm = &note.frontmatter;
+    context.insert("title", page.map_or(&note.title, |page| &page.title));
+    if let Some(i) = part {
+        context.insert("part_nav", &part_nav(note, i));
+    }
+    context.insert("date", &fm.date);
+    if let Some(updated) = &note.updated {
+        context.insert("updated", &template_date(updated, site_timezone(config)?));
+    }
+    context.insert("tags", &fm.tags);
+    context.insert("fields", &note.fields);
+    context.insert("properties", &note.properties);
+    if config.properties_panel
+        && let Some(panel) = properties_html(note, index, site_timezone(config)?, config)
+    {
+        context.insert("properties_panel", &panel);
+    }
+    let outgoing_links: Vec<Note> = index.outgoing_links(note, body).into_iter().map(IndexedNote::to_note).collect();
+    context.insert("outgoing_links", &outgoing_links);
+    if !note.mentioned_in.is_empty() {
+        let mentions: Vec<Note> =
+            note.mentioned_in.iter().filter_map(|source| index.note(source)).map(IndexedNote::to_note).collect();
+        context.insert("unlinked_mentions", &mentions);
+    }
+    let authors = note_authors(note, index, config);
+    context.insert("authors", &authors);
+    context.insert("artifacts", &note.artifacts);
+    if let Some(history_url) = history_url {
+        context.insert("history_url", history_url);
+    }
+    if let Some(cover) = &note.cover {
+        context.insert("cover", cover);
+    }
+    let og_image = match &note.cover {
+        Some(cover) => Some(cover.clone()),
+        None if config.social_cards => Some(url_for(&card_path(&note.output))),
+        None => None,
     };
-
-    // Compute output html path
-    // We need to mirror the directory structure from the vault into output_dir.
-    // So we take the file path relative to the vault root; the caller provides output path base.
-    // For this helper, we rebuild relative to the vault by scanning for the first component after the vault path is handled by caller.
-    let mut output_path = output_dir.join(path.file_name().unwrap_or_default());
-    // Try to reconstruct relative path using canonicalization when possible
-    // If the parent folder exists under output_dir, keep same structure:
-    if let Some(parent) = path.parent() {
-        let rel = parent; // caller ensures directories
-        let parent_rel_name = rel.file_name();
-        if let Some(_name) = parent_rel_name {
-            let file_name = path.file_name().unwrap_or_default().to_str().unwrap()
-                .replace("?", "");
-            output_path = output_dir.join(file_name);
-            // Ensure parent exists
-            if let Some(parent_out) = output_path.parent() {
-                fs::create_dir_all(parent_out)?;
+    let og_image = og_image.map(|og_image| absolute_url(&og_image, config));
+    if let Some(og_image) = &og_image {
+        context.insert("og_image", og_image);
+    }
+    let canonical_url = config.site_url.as_ref().map(|_| absolute_url(url, config));
+    if config.structured_data {
+        let title = page.map_or(&note.title, |page| &page.title);
+        let description = note.summary(comrak_options);
+        context.insert("og_type", "article");
+        let json_ld = json_ld(note, title, &description, canonical_url.as_deref(), og_image.as_deref(), &authors, config);
+        context.insert("json_ld", &json_ld);
+    }
+    if let Some(canonical_url) = canonical_url {
+        if fm.qr.unwrap_or(config.qr_codes) {
+            match qr_code_svg(&canonical_url) {
+                Some(svg) => context.insert("qr_code", &svg),
+                None => warning!("URL too long for a QR code: {canonical_url}"),
             }
         }
+        context.insert("canonical_url", &canonical_url);
     }
-
-    let mut html_path = output_path.clone();
-    html_path.set_extension("html");
-
-    let note = Note {
-        title: title.clone(),
-        path: html_path.to_path_buf(),
-    };
-
-    if let Some(fm) = frontmatter {
-        context.insert("title", &title);
-        context.insert("date", &fm.date);
-        context.insert("tags", &fm.tags);
-        if let Some(tag_list) = fm.tags {
-            for tag in tag_list {
-                tags.entry(tag).or_default().push(note.clone());
+    if note.is_archived() {
+        context.insert("archived", &true);
+        if let Some(successor) = &fm.successor {
+            match index.resolve(frontmatter_link(successor)) {
+                Some(LinkTarget::Note(successor)) => {
+                    context.insert("successor_url", &successor.url);
+                    context.insert("successor_title", &successor.title);
+                }
+                _ => broken_link!(file: &note.source, near: successor; "successor note {successor} not found for {}", note.source.display()),
             }
         }
-    } else {
-        context.insert("title", &title);
     }
-    context.insert("relative_path", &href_to_root_style_css(&output_dir));
+    context.insert("print_button", &fm.print.unwrap_or(config.print_button));
     context.insert("content", &html_content);
 
-    let rendered_html = tera.render("base.html", &context).map_err(|e| {
-        std::io::Error::new(
-            std::io::ErrorKind::Other,
-            format!("Template rendering failed for base.html: {e}"),
+    let template = fm
+        .template
+        .as_deref()
+        .or_else(|| note_type(note, config).and_then(|note_type| note_type.template.as_deref()))
+        .unwrap_or("base.html");
+    tera.render(template, &context).map_err(|e| {
+        std::io::Error::other(
+            format!("Template rendering failed for {template}: {e}"),
+        )
+    })
+}
+
+/// Renders (part of) a note's Markdown body to HTML, with its links resolved and
+/// citations formatted. `anchors` numbers the heading anchors, as `markdown_to_html` does.
+pub(crate) fn note_html(
+    body: &str,
+    note: &IndexedNote,
+    index: &SiteIndex,
+    tera: &Tera,
+    comrak_options: &ComrakOptions,
+    config: &Config,
+    anchors: Anchorizer,
+) -> String {
+    let mut body = if config.number_figures { number_figures(body, &note.source) } else { body.to_string() };
+    if config.callouts {
+        body = admonitions_to_callouts(&body);
+    }
+    if config.image_captions {
+        body = caption_images(&body, is_image);
+    }
+    body = render_inline_fields(&body, config.inline_fields);
+    let mut content_with_links = rewrite_links(&body, note, index, tera, config);
+    if !index.bibliography.is_empty() {
+        content_with_links = index.bibliography.cite(&content_with_links, &note.source);
+    }
+    let link_cards = note.frontmatter.link_cards.unwrap_or(config.link_cards);
+    let fold_sections = note.frontmatter.fold.unwrap_or(config.fold_sections);
+    if link_cards != config.link_cards || fold_sections != config.fold_sections {
+        let config = Config { link_cards, fold_sections, ..config.clone() };
+        markdown_to_html(&content_with_links, comrak_options, &config, &note.source, anchors)
+    } else {
+        markdown_to_html(&content_with_links, comrak_options, config, &note.source, anchors)
+    }
+}
+
+/// The site-wide settings base.html needs, for a page published at `output`.
+pub(crate) fn page_context(output: &Path, index: &SiteIndex, config: &Config) -> Context {
+    let mut context = Context::new();
+    context.insert("print_button", &config.print_button);
+    context.insert("site_icons", &config.icon.is_some());
+    context.insert("web_manifest", &(config.icon.is_some() || config.pwa));
+    context.insert("pwa", &config.pwa);
+    context.insert("opensearch", &opensearch_name(config));
+    context.insert("instant_navigation", &config.instant_navigation);
+    context.insert("page_class", &config.page_container_class);
+    context.insert("copy_code", &config.code_block_headers);
+    context.insert("video_facades", &config.video_facades);
+    context.insert("notes_table", &config.notes_table);
+    context.insert("flashcards", &config.flashcards);
+    context.insert("map", &config.map);
+    context.insert("random_note", &config.random_note);
+    context.insert("command_palette", &config.command_palette);
+    context.insert("recent_notes", &index.recent);
+    context.insert("outgoing_links_panel", &config.outgoing_links_panel);
+    context.insert("build_time", &build_time(site_timezone(config).unwrap_or(Tz::UTC)));
+    context.insert("relative_path", &href_to_root_style_css(output));
+    context
+}
+
+fn render_history_page(
+    tera: &Tera,
+    output: &dyn OutputSink,
+    history_path: &Path,
+    note: &IndexedNote,
+    revisions: &[Revision],
+) -> std::io::Result<()> {
+    let mut context = Context::new();
+    context.insert("title", &note.title);
+    context.insert("url", &note.url);
+    context.insert("revisions", revisions);
+    let history_html = tera.render("history.html", &context).map_err(|e| {
+        std::io::Error::other(
+            format!("Template rendering failed for history.html: {e}"),
         )
     })?;
+    output.write(history_path, history_html.as_bytes())
+}
 
-    fs::write(&html_path, rendered_html)?;
-    println!("Wrote HTML: {}", html_path.display());
+/// Prefixes a root-relative URL with the configured `site_url` and `base_path`.
+pub(crate) fn absolute_url(url: &str, config: &Config) -> String {
+    match &config.site_url {
+        Some(site_url) if url.starts_with('/') => {
+            format!("{}{}", site_url.trim_end_matches('/'), with_base_path(url, config.base_path.as_deref()))
+        }
+        _ => url.to_string(),
+    }
+}
 
-    notes.push(note);
-    Ok(())
+/// Makes a page's HTML readable away from the site, as in an email or a fediverse
+/// post: root-relative and fragment URLs are made absolute against `site_url` and
+/// `page_url`, and the elements matching `removed` are dropped.
+pub(crate) fn standalone_html(html: &str, page_url: &str, removed: &str, config: &Config) -> std::io::Result<String> {
+    let absolute = |url: &str| -> Option<String> {
+        if url.starts_with('#') {
+            Some(format!("{page_url}{url}"))
+        } else if url.starts_with('/') {
+            Some(absolute_url(url, config))
+        } else {
+            None
+        }
+    };
+    let handlers = vec![
+        element!(removed, |el| {
+            el.remove();
+            Ok(())
+        }),
+        element!("[href], [src], [poster]", move |el| {
+            for name in ["href", "src", "poster"] {
+                if let Some(url) = el.get_attribute(name).and_then(|url| absolute(&url)) {
+                    el.set_attribute(name, &url)?;
+                }
+            }
+            Ok(())
+        }),
+    ];
+    let settings = RewriteStrSettings { element_content_handlers: handlers, ..RewriteStrSettings::new() };
+    rewrite_str(html, settings).map_err(|e| std::io::Error::other(format!("Rewriting links failed: {e}")))
 }
 
-fn href_to_root_style_css<P: AsRef<Path>>(file_path: P) -> String {
+pub(crate) fn href_to_root_style_css<P: AsRef<Path>>(file_path: P) -> String {
     let path = file_path.as_ref();
     let depth = path.parent().map(|p| p.components().count()).unwrap_or(0);
 
@@ -185,7 +608,7 @@ fn href_to_root_style_css<P: AsRef<Path>>(file_path: P) -> String {
         for i in 0..depth {
             s.push_str("..");
             if i + 1 != depth {
-                s.push_str("/");
+                s.push('/');
             }
         }
         s
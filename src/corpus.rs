@@ -0,0 +1,122 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use serde::Serialize;
+use crate::config::Config;
+use crate::content::make_comrak_options;
+use crate::diagnostics::{info, set_vault};
+use crate::index::SiteIndex;
+use crate::markdown::document_text;
+use crate::sources::{note_markdown, public_url};
+use crate::vfs::{LocalVault, VaultSource};
+
+/// How a corpus export splits notes into records.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Chunking {
+    /// Words a chunk holds at most, unless a paragraph is longer; 0 keeps notes whole
+    pub words: usize,
+    /// Words of the previous chunk repeated at the start of the next, for context
+    pub overlap: usize,
+}
+
+/// A line of the corpus: a published note, or one chunk of it.
+#[derive(Serialize)]
+struct CorpusRecord<'a> {
+    url: &'a str,
+    title: &'a str,
+    tags: &'a [String],
+    plain_text: String,
+    /// URLs of the notes the note links to
+    links: &'a [String],
+    /// Position of the chunk within the note, when notes are chunked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chunk: Option<usize>,
+}
+
+/// Writes `corpus.jsonl` into `output_dir`: a JSON line for every published
+/// note, or every chunk of one, with its URL, title, tags, text without markup,
+/// and the URLs of the notes it links to, ready to feed into retrieval pipelines.
+pub fn export_corpus(vault_path: &Path, output_dir: &Path, chunking: Chunking, config: &Config) -> io::Result<()> {
+    if chunking.words > 0 && chunking.overlap >= chunking.words {
+        return Err(io::Error::other("--chunk-overlap must be smaller than --chunk-words"));
+    }
+    info!("Exporting corpus...");
+    set_vault(vault_path);
+    let local = LocalVault::new(vault_path);
+    let index = SiteIndex::build(&local, &local.files(config)?, config)?;
+    fs::create_dir_all(output_dir)?;
+    let path = output_dir.join("corpus.jsonl");
+    let mut file = io::BufWriter::new(fs::File::create(&path)?);
+    let records = write_corpus(&index, &mut file, chunking, config)?;
+    file.flush()?;
+    info!("Wrote {records} records of {} notes to {}", index.notes.len(), path.display());
+    Ok(())
+}
+
+/// Writes the corpus of the site's notes as JSON lines, returning how many were written.
+pub fn write_corpus(index: &SiteIndex, out: &mut dyn Write, chunking: Chunking, config: &Config) -> io::Result<usize> {
+    let comrak_options = make_comrak_options(config);
+    let mut records = 0;
+    for note in &index.notes {
+        let url = public_url(&note.url, config);
+        let links: Vec<String> =
+            index.outgoing_links(note, &note.body).into_iter().map(|target| public_url(&target.url, config)).collect();
+        let text = document_text(&note_markdown(note, index, config), &comrak_options);
+        let chunks = if chunking.words == 0 { vec![text.trim_end().to_string()] } else { chunk_text(&text, chunking) };
+        for (i, plain_text) in chunks.into_iter().enumerate() {
+            let record = CorpusRecord {
+                url: &url,
+                title: &note.title,
+                tags: note.frontmatter.tags.as_deref().unwrap_or_default(),
+                plain_text,
+                links: &links,
+                chunk: (chunking.words > 0).then_some(i),
+            };
+            serde_json::to_writer(&mut *out, &record).map_err(io::Error::other)?;
+            out.write_all(b"\n")?;
+            records += 1;
+        }
+    }
+    Ok(records)
+}
+
+/// Splits `text` into chunks of whole paragraphs of at most `chunking.words`
+/// words, each starting with the last `chunking.overlap` words of the one before.
+/// Paragraphs longer than a chunk are split between words.
+fn chunk_text(text: &str, chunking: Chunking) -> Vec<String> {
+    let mut pieces: Vec<String> = Vec::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|paragraph| !paragraph.is_empty()) {
+        let words: Vec<&str> = paragraph.split_whitespace().collect();
+        if words.len() <= chunking.words {
+            pieces.push(paragraph.to_string());
+        } else {
+            pieces.extend(words.chunks(chunking.words).map(|words| words.join(" ")));
+        }
+    }
+
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut current_words = 0;
+    // Words of the overlap at the start of `current`, which alone don't make a chunk
+    let mut carried = 0;
+    for piece in pieces {
+        let words = piece.split_whitespace().count();
+        if current_words > carried && current_words + words > chunking.words {
+            let chunk = current.join("\n\n");
+            let tail: Vec<&str> = chunk.split_whitespace().rev().take(chunking.overlap).collect();
+            carried = tail.len();
+            current_words = carried;
+            current = Vec::new();
+            if !tail.is_empty() {
+                current.push(tail.into_iter().rev().collect::<Vec<_>>().join(" "));
+            }
+            chunks.push(chunk);
+        }
+        current.push(piece);
+        current_words += words;
+    }
+    if !current.is_empty() {
+        chunks.push(current.join("\n\n"));
+    }
+    chunks
+}
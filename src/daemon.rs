@@ -0,0 +1,139 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+use std::path::Path;
+use serde_json::{json, Value};
+use crate::config::Config;
+use crate::content::split_frontmatter;
+use crate::diagnostics::set_quiet;
+use crate::domain::Frontmatter;
+use crate::index::SiteIndex;
+use crate::vfs::{LocalVault, VaultSource};
+use crate::links::{find_wikilinks, is_note};
+
+/// LSP diagnostic severities.
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+
+/// JSON-RPC error codes.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Serves diagnostics to editor plugins: JSON-RPC 2.0 requests and responses,
+/// one JSON object per line, over stdin and stdout. Methods:
+///
+/// - `check` `{"file": "Note.md", "text": "..."}`: problems in a note, as LSP-style
+///   diagnostics. `text` is the editor's unsaved buffer; without it the file is read.
+/// - `reload`: re-indexes the vault, e.g. after notes were added or renamed.
+/// - `shutdown`: answers, then exits.
+pub fn run_daemon(vault_path: &Path, config: &Config) -> io::Result<()> {
+    // stdout carries the protocol, so progress messages must stay off it
+    set_quiet(true);
+    let mut index = load_index(vault_path, config)?;
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                respond(&mut stdout, &Value::Null, Err((PARSE_ERROR, e.to_string())))?;
+                continue;
+            }
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let result = match request.get("method").and_then(Value::as_str) {
+            Some("check") => check(&index, vault_path, &params),
+            Some("reload") => load_index(vault_path, config)
+                .map(|reloaded| {
+                    index = reloaded;
+                    json!({ "notes": index.notes.len() })
+                })
+                .map_err(|e| (INTERNAL_ERROR, e.to_string())),
+            Some("shutdown") => {
+                respond(&mut stdout, &id, Ok(Value::Null))?;
+                return Ok(());
+            }
+            Some(method) => Err((METHOD_NOT_FOUND, format!("Unknown method {method}"))),
+            None => Err((INVALID_PARAMS, "Missing method".to_string())),
+        };
+        // Requests without an id are notifications, which get no response
+        if request.get("id").is_some() {
+            respond(&mut stdout, &id, result)?;
+        }
+    }
+    Ok(())
+}
+
+fn load_index(vault_path: &Path, config: &Config) -> io::Result<SiteIndex> {
+    let vault = LocalVault::new(vault_path);
+    SiteIndex::build(&vault, &vault.files(config)?, config)
+}
+
+fn respond(out: &mut impl Write, id: &Value, result: Result<Value, (i64, String)>) -> io::Result<()> {
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }),
+    };
+    writeln!(out, "{response}")?;
+    out.flush()
+}
+
+/// Broken wikilinks, embeds of missing attachments, and frontmatter errors in one note.
+fn check(index: &SiteIndex, vault_path: &Path, params: &Value) -> Result<Value, (i64, String)> {
+    let file = params
+        .get("file")
+        .and_then(Value::as_str)
+        .ok_or((INVALID_PARAMS, "check needs a file".to_string()))?;
+    let path = Path::new(file);
+    let path = if path.is_absolute() { path.to_path_buf() } else { vault_path.join(path) };
+    let text = match params.get("text").and_then(Value::as_str) {
+        Some(text) => text.to_string(),
+        None => fs::read_to_string(&path).map_err(|e| (INVALID_PARAMS, format!("Cannot read {file}: {e}")))?,
+    };
+
+    let mut diagnostics = Vec::new();
+    if let (Some(data), _) = split_frontmatter(&text)
+        && let Err(e) = data.deserialize::<Frontmatter>()
+    {
+        diagnostics.push(diagnostic(&text, 0..0, SEVERITY_ERROR, "frontmatter", format!("Invalid frontmatter: {e}")));
+    }
+    for (range, link) in find_wikilinks(&text) {
+        // Links to a heading in the same note
+        if link.target.is_empty() || index.resolve(&link.target).is_some() {
+            continue;
+        }
+        let target = Path::new(&link.target);
+        let (kind, message) = if link.embed && target.extension().is_some() && !is_note(target) {
+            ("missing-asset", format!("Attachment {} is not in the vault", link.target))
+        } else {
+            ("broken-link", format!("No note named {}", link.target))
+        };
+        diagnostics.push(diagnostic(&text, range, SEVERITY_WARNING, kind, message));
+    }
+    Ok(json!({ "file": file, "diagnostics": diagnostics }))
+}
+
+/// An LSP-style diagnostic; positions are 0-based lines and UTF-16 columns.
+fn diagnostic(text: &str, range: Range<usize>, severity: u8, code: &str, message: String) -> Value {
+    json!({
+        "range": { "start": position(text, range.start), "end": position(text, range.end) },
+        "severity": severity,
+        "source": "obs2web",
+        "code": code,
+        "message": message,
+    })
+}
+
+fn position(text: &str, offset: usize) -> Value {
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    json!({ "line": line, "character": character })
+}
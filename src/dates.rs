@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::{DateTime, Locale, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use tera::{Error, Value};
+use crate::config::Config;
+use crate::diagnostics::config_error;
+use crate::index::IndexedNote;
+
+/// Formats written dates are read in, besides RFC 3339 and Unix timestamps.
+const DATE_TIME_FORMATS: [&str; 3] = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"];
+
+/// When the current build started, shared by every page it renders.
+static BUILD_TIME: Mutex<Option<DateTime<Utc>>> = Mutex::new(None);
+
+/// The configured `timezone`, UTC when there is none.
+pub fn site_timezone(config: &Config) -> std::io::Result<Tz> {
+    match &config.timezone {
+        Some(name) => name.parse().map_err(|_| config_error(format!("Unknown timezone {name}"))),
+        None => Ok(Tz::UTC),
+    }
+}
+
+/// Reads a date as notes and templates write it: `2024-02-03`, `2024-02-03 14:30`,
+/// RFC 3339, or a Unix timestamp in seconds. Dates without an offset are taken
+/// to be in `tz`; the result is always in `tz`.
+pub fn parse_date(value: &Value, tz: Tz) -> Option<DateTime<Tz>> {
+    if let Some(seconds) = value.as_i64() {
+        return DateTime::from_timestamp(seconds, 0).map(|date| date.with_timezone(&tz));
+    }
+    let text = value.as_str()?.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+        return Some(date.with_timezone(&tz));
+    }
+    let naive = DATE_TIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(text, f).ok())
+        .or_else(|| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))?;
+    tz.from_local_datetime(&naive).earliest()
+}
+
+/// A date in RFC 3339 in the site's timezone, as templates get it, or the text
+/// itself when it isn't a date.
+pub fn template_date(text: &str, tz: Tz) -> String {
+    parse_date(&Value::String(text.to_string()), tz)
+        .map_or_else(|| text.to_string(), |date| date.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// Marks the start of a build. Its time is taken from `SOURCE_DATE_EPOCH` when
+/// that is set, so reproducible builds get the same `build_time`.
+pub fn start_build() {
+    let epoch = std::env::var("SOURCE_DATE_EPOCH").ok().and_then(|s| s.trim().parse::<i64>().ok());
+    let now = epoch.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64));
+    *BUILD_TIME.lock().unwrap_or_else(|e| e.into_inner()) = DateTime::from_timestamp(now, 0);
+}
+
+/// When the current build started.
+pub fn build_started() -> DateTime<Utc> {
+    let started = *BUILD_TIME.lock().unwrap_or_else(|e| e.into_inner());
+    started.unwrap_or_else(|| {
+        start_build();
+        BUILD_TIME.lock().unwrap_or_else(|e| e.into_inner()).unwrap_or_default()
+    })
+}
+
+/// When the current build started, in RFC 3339 in the site's timezone.
+pub fn build_time(tz: Tz) -> String {
+    build_started().with_timezone(&tz).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// A date in the site's `date_format` and `locale`.
+pub fn format_date(date: &DateTime<Tz>, config: &Config) -> String {
+    let locale = config.locale.as_deref().and_then(|name| Locale::try_from(name).ok()).unwrap_or(Locale::POSIX);
+    let mut text = String::new();
+    match write!(text, "{}", date.format_localized(&config.date_format, locale)) {
+        Ok(()) => text,
+        Err(_) => date.to_rfc3339_opts(SecondsFormat::Secs, true),
+    }
+}
+
+/// The `date` filter: `{{ date | date(format="%-d %B, %Y", locale="vi_VN") }}` renders
+/// "3 Tháng 2, 2024". The format, locale, and timezone (`timezone="Europe/Berlin"`)
+/// default to the site's `date_format`, `locale`, and `timezone`. Values that aren't
+/// dates are left as they are, so a free-form `date` in a note's frontmatter doesn't
+/// fail the build. Fails with a config error when the site's locale or timezone is unknown.
+pub fn date_filter(config: &Config) -> std::io::Result<impl tera::Filter + use<>> {
+    if let Some(locale) = &config.locale {
+        Locale::try_from(locale.as_str()).map_err(|_| config_error(format!("Unknown locale {locale}")))?;
+    }
+    let default_tz = site_timezone(config)?;
+    let default_format = config.date_format.clone();
+    let default_locale = config.locale.clone();
+    Ok(move |value: &Value, args: &HashMap<String, Value>| -> tera::Result<Value> {
+        let tz = match args.get("timezone") {
+            Some(tz) => {
+                let name = tz.as_str().ok_or_else(|| Error::msg("date: `timezone` must be a string"))?;
+                name.parse().map_err(|_| Error::msg(format!("date: unknown timezone {name}")))?
+            }
+            None => default_tz,
+        };
+        let Some(date) = parse_date(value, tz) else {
+            return Ok(value.clone());
+        };
+        let format = match args.get("format") {
+            Some(format) => format.as_str().ok_or_else(|| Error::msg("date: `format` must be a string"))?,
+            None => &default_format,
+        };
+        let locale = match args.get("locale") {
+            Some(locale) => Some(locale.as_str().ok_or_else(|| Error::msg("date: `locale` must be a string"))?),
+            None => default_locale.as_deref(),
+        };
+        let locale = match locale {
+            Some(name) => Locale::try_from(name).map_err(|_| Error::msg(format!("date: unknown locale {name}")))?,
+            None => Locale::POSIX,
+        };
+        let mut text = String::new();
+        write!(text, "{}", date.format_localized(format, locale))
+            .map_err(|_| Error::msg(format!("date: invalid format {format}")))?;
+        Ok(Value::String(text))
+    })
+}
+
+/// A recently updated note as templates see it in `recent_notes`.
+#[derive(Serialize, Debug, Clone)]
+pub struct RecentUpdate {
+    pub title: String,
+    pub url: String,
+    /// When the note was last updated, or else published, in RFC 3339
+    pub date: String,
+    /// Whether `date` is an update rather than the note's publication
+    pub updated: bool,
+}
+
+/// The `count` most recently updated or published of `notes`, newest first.
+pub fn recent_updates<'a>(notes: impl IntoIterator<Item = &'a IndexedNote>, count: usize, tz: Tz) -> Vec<RecentUpdate> {
+    if count == 0 {
+        return Vec::new();
+    }
+    recent_notes(notes, None, tz)
+        .into_iter()
+        .take(count)
+        .map(|recent| RecentUpdate {
+            title: recent.note.title.clone(),
+            url: recent.note.url.clone(),
+            date: recent.date.to_rfc3339_opts(SecondsFormat::Secs, true),
+            updated: recent.note.updated.is_some(),
+        })
+        .collect()
+}
+
+/// A note published or updated recently, with the date that puts it there.
+pub struct RecentNote<'a> {
+    pub note: &'a IndexedNote,
+    pub date: DateTime<Tz>,
+    /// Published in the period rather than updated
+    pub new: bool,
+}
+
+/// The `notes` whose `updated` date, or else their `date`, falls on or after `since`
+/// (all of them without it), newest first. Notes without either are never recent.
+pub fn recent_notes<'a>(
+    notes: impl IntoIterator<Item = &'a IndexedNote>,
+    since: Option<DateTime<Tz>>,
+    tz: Tz,
+) -> Vec<RecentNote<'a>> {
+    let mut recent: Vec<RecentNote> = notes
+        .into_iter()
+        .filter_map(|note| {
+            let published = note.frontmatter.date.as_deref().and_then(|d| parse_date(&Value::from(d), tz));
+            let updated = note.updated.as_deref().and_then(|d| parse_date(&Value::from(d), tz));
+            let date = updated.or(published)?;
+            let new = published.is_some_and(|published| since.is_none_or(|since| published >= since));
+            since.is_none_or(|since| date >= since).then_some(RecentNote { note, date, new })
+        })
+        .collect();
+    recent.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.note.title.cmp(&b.note.title)));
+    recent
+}
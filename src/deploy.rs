@@ -0,0 +1,100 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use crate::archive::read_archive_file;
+use crate::config::Config;
+use crate::content::absolute_url;
+use crate::diagnostics::{config_error, info, warning};
+use crate::http::{percent_encode, HttpClient};
+use crate::sitemap::{sitemap_urls, SITEMAP_URL};
+
+/// Publishes a finished build by running the configured `deploy_command` through
+/// the shell. The command finds the build in `$OBS2WEB_OUTPUT`, e.g.
+/// `rsync -a --delete "$OBS2WEB_OUTPUT/" host:/var/www/notes/`. Once it has
+/// succeeded, the `deploy_pings` are sent and, with `warm_cache`, the pages are requested.
+pub fn deploy(output: &Path, config: &Config, dry_run: bool) -> io::Result<()> {
+    let Some(command) = &config.deploy_command else {
+        return Err(config_error("Set deploy_command in obs2web.toml to deploy".to_string()));
+    };
+    if dry_run {
+        println!("Would run: {command} (with OBS2WEB_OUTPUT={})", output.display());
+        for ping in &config.deploy_pings {
+            println!("Would request: {}", ping_url(ping, config));
+        }
+        if config.warm_cache {
+            println!("Would request every page listed in {SITEMAP_URL}");
+        }
+        return Ok(());
+    }
+    info!("Deploying {}: {command}", output.display());
+    let status = shell_command(command).env("OBS2WEB_OUTPUT", output).status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("Deploy command failed with {status}")));
+    }
+    info!("Deploy finished.");
+    if !config.deploy_pings.is_empty() || config.warm_cache {
+        after_deploy(output, config)?;
+    }
+    Ok(())
+}
+
+/// Sends the `deploy_pings`, such as a search engine's sitemap ping, and with
+/// `warm_cache` requests every page the build's sitemap lists, so caches in front
+/// of the site hold them before readers ask. Failed requests are only reported,
+/// as the site is already published.
+fn after_deploy(output: &Path, config: &Config) -> io::Result<()> {
+    let client = HttpClient::new(config);
+    for ping in &config.deploy_pings {
+        let url = ping_url(ping, config);
+        info!("Pinging {url}");
+        if let Err(e) = client.ping(&url) {
+            warning!("ping of {url} failed: {e}");
+        }
+    }
+    if !config.warm_cache {
+        return Ok(());
+    }
+    let Some(sitemap) = read_sitemap(output)? else {
+        warning!("warm_cache needs a sitemap in the build; set sitemap = true and site_url");
+        return Ok(());
+    };
+    let urls = sitemap_urls(&sitemap);
+    info!("Warming the cache with {} pages", urls.len());
+    let failed = urls.iter().filter(|url| client.ping(url).is_err()).count();
+    if failed > 0 {
+        warning!("{failed} of {} pages could not be requested while warming the cache", urls.len());
+    }
+    Ok(())
+}
+
+/// A `deploy_pings` URL with `{sitemap}` replaced by the sitemap's encoded absolute URL.
+fn ping_url(ping: &str, config: &Config) -> String {
+    ping.replace("{sitemap}", &percent_encode(&absolute_url(SITEMAP_URL, config)))
+}
+
+/// The sitemap of a build directory or archive.
+fn read_sitemap(output: &Path) -> io::Result<Option<String>> {
+    let name = SITEMAP_URL.trim_start_matches('/');
+    let bytes = if output.is_dir() {
+        fs::read(output.join(name)).ok()
+    } else {
+        read_archive_file(output, name)?
+    };
+    Ok(bytes.map(|bytes| String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// Runs `command` through the platform's shell, `sh -c` or `cmd /C`.
+pub(crate) fn shell_command(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
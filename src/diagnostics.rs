@@ -0,0 +1,205 @@
+use std::error::Error;
+use std::fmt::{self, Arguments};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crate::cli::AnnotationFormat;
+
+/// Process exit codes, so CI pipelines can branch on the outcome of a build.
+pub const EXIT_FAILURE: u8 = 1;
+pub const EXIT_WARNINGS: u8 = 2;
+pub const EXIT_BROKEN_LINKS: u8 = 3;
+pub const EXIT_CONFIG_ERROR: u8 = 4;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static COLOR: AtomicBool = AtomicBool::new(false);
+static STRICT: AtomicBool = AtomicBool::new(false);
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+static BROKEN_LINKS: AtomicUsize = AtomicUsize::new(0);
+static GITHUB_ANNOTATIONS: AtomicBool = AtomicBool::new(false);
+/// Vault the files named in diagnostics are relative to.
+static VAULT: Mutex<PathBuf> = Mutex::new(PathBuf::new());
+
+/// An invalid config file, config value, or command line, reported with exit code 4.
+#[derive(Debug)]
+pub struct ConfigError(pub String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for ConfigError {}
+
+/// Wraps a config problem in an `io::Error` that `exit_code_for` recognizes.
+pub fn config_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, ConfigError(message))
+}
+
+/// A problem with a vault file, such as invalid frontmatter, that stops the build.
+#[derive(Debug)]
+pub struct FileError {
+    /// Vault-relative path
+    pub file: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for FileError {}
+
+/// Wraps a problem with a vault file in an `io::Error` that annotations can point at the file.
+pub fn file_error(file: &Path, message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, FileError { file: file.to_path_buf(), message })
+}
+
+/// `quiet` silences progress messages, leaving warnings and errors; colors are
+/// only used on a terminal and never when `NO_COLOR` is set. With GitHub annotations,
+/// warnings and errors are printed as workflow commands that GitHub shows on the
+/// offending lines of a pull request.
+pub fn configure(quiet: bool, no_color: bool, annotations: Option<AnnotationFormat>) {
+    QUIET.store(quiet, Ordering::Relaxed);
+    let no_color = no_color || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    COLOR.store(!no_color && io::stderr().is_terminal(), Ordering::Relaxed);
+    GITHUB_ANNOTATIONS.store(annotations == Some(AnnotationFormat::Github), Ordering::Relaxed);
+}
+
+/// Sets the vault that files in warnings are relative to.
+pub fn set_vault(vault_path: &Path) {
+    *VAULT.lock().unwrap() = vault_path.to_path_buf();
+}
+
+/// Silences progress messages, e.g. while a page is written to stdout.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Makes broken links fail the build with exit code 3.
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+pub fn progress(message: Arguments) {
+    if !QUIET.load(Ordering::Relaxed) {
+        println!("{message}");
+    }
+}
+
+/// A warning, optionally about a vault file; `near` is text on the line it concerns.
+pub fn warn(file: Option<&Path>, near: Option<&str>, message: Arguments) {
+    WARNINGS.fetch_add(1, Ordering::Relaxed);
+    if GITHUB_ANNOTATIONS.load(Ordering::Relaxed) {
+        println!("{}", annotation("warning", file, near, &message.to_string()));
+    } else {
+        eprintln!("{} {message}", paint("Warning:", "33"));
+    }
+}
+
+/// A link to a note or file that isn't in the vault.
+pub fn warn_broken_link(file: Option<&Path>, near: Option<&str>, message: Arguments) {
+    BROKEN_LINKS.fetch_add(1, Ordering::Relaxed);
+    warn(file, near, message);
+}
+
+pub fn error(error: &io::Error) {
+    if GITHUB_ANNOTATIONS.load(Ordering::Relaxed) {
+        let file = error.get_ref().and_then(|e| e.downcast_ref::<FileError>()).map(|e| e.file.as_path());
+        println!("{}", annotation("error", file, None, &error.to_string()));
+    }
+    eprintln!("{} {error}", paint("Error:", "31"));
+}
+
+/// Exit code of a finished run: broken links in strict mode, then any other warnings.
+pub fn exit_code() -> ExitCode {
+    if STRICT.load(Ordering::Relaxed) && BROKEN_LINKS.load(Ordering::Relaxed) > 0 {
+        ExitCode::from(EXIT_BROKEN_LINKS)
+    } else if WARNINGS.load(Ordering::Relaxed) > 0 {
+        ExitCode::from(EXIT_WARNINGS)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Exit code of a run that failed with `error`.
+pub fn exit_code_for(error: &io::Error) -> ExitCode {
+    match error.get_ref().and_then(|e| e.downcast_ref::<ConfigError>()) {
+        Some(_) => ExitCode::from(EXIT_CONFIG_ERROR),
+        None => ExitCode::from(EXIT_FAILURE),
+    }
+}
+
+/// A GitHub Actions workflow command, e.g. `::warning file=notes/a.md,line=3::message`.
+fn annotation(level: &str, file: Option<&Path>, near: Option<&str>, message: &str) -> String {
+    let Some(file) = file else {
+        return format!("::{level}::{}", escape_data(message));
+    };
+    let vault = VAULT.lock().unwrap().clone();
+    let path = vault.join(file);
+    let line = near.and_then(|near| {
+        let text = fs::read_to_string(&path).ok()?;
+        text.lines().position(|line| line.contains(near)).map(|i| i + 1)
+    });
+    // Paths are relative to the repository root, which is where workflows run
+    let workspace = std::env::var_os("GITHUB_WORKSPACE").map(PathBuf::from).or_else(|| std::env::current_dir().ok());
+    let path = match workspace {
+        Some(root) if path.is_absolute() => path.strip_prefix(root).unwrap_or(&path).to_path_buf(),
+        _ => path.strip_prefix(".").unwrap_or(&path).to_path_buf(),
+    };
+    let mut properties = format!("file={}", escape_property(&path.to_string_lossy()));
+    if let Some(line) = line {
+        properties.push_str(&format!(",line={line}"));
+    }
+    format!("::{level} {properties}::{}", escape_data(message))
+}
+
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+fn paint(text: &str, color: &str) -> String {
+    if COLOR.load(Ordering::Relaxed) {
+        format!("\x1b[1;{color}m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+/// Prints a progress message, unless running with `--quiet`.
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::diagnostics::progress(format_args!($($arg)*)) };
+}
+
+/// Prints and counts a warning, optionally about a vault-relative `file`, on the
+/// first line containing `near`: `warning!(file: path, near: "[[x"; "...", args)`.
+macro_rules! warning {
+    (file: $file:expr, near: $near:expr; $($arg:tt)*) => {
+        $crate::diagnostics::warn(Some($file.as_ref()), Some($near.as_ref()), format_args!($($arg)*))
+    };
+    (file: $file:expr; $($arg:tt)*) => { $crate::diagnostics::warn(Some($file.as_ref()), None, format_args!($($arg)*)) };
+    ($($arg:tt)*) => { $crate::diagnostics::warn(None, None, format_args!($($arg)*)) };
+}
+
+/// Prints and counts a broken link warning; takes the same forms as `warning!`.
+macro_rules! broken_link {
+    (file: $file:expr, near: $near:expr; $($arg:tt)*) => {
+        $crate::diagnostics::warn_broken_link(Some($file.as_ref()), Some($near.as_ref()), format_args!($($arg)*))
+    };
+    (file: $file:expr; $($arg:tt)*) => {
+        $crate::diagnostics::warn_broken_link(Some($file.as_ref()), None, format_args!($($arg)*))
+    };
+    ($($arg:tt)*) => { $crate::diagnostics::warn_broken_link(None, None, format_args!($($arg)*)) };
+}
+
+pub(crate) use {broken_link, info, warning};
@@ -0,0 +1,111 @@
+use std::io;
+use chrono::TimeDelta;
+use comrak::Anchorizer;
+use lol_html::html_content::Element;
+use lol_html::{element, rewrite_str, RewriteStrSettings};
+use serde::Serialize;
+use tera::Context;
+use crate::config::Config;
+use crate::content::{absolute_url, make_comrak_options, note_html, standalone_html};
+use crate::dates::{build_started, recent_notes, site_timezone, start_build};
+use crate::diagnostics::{config_error, info};
+use crate::encrypt::encrypted_folder;
+use crate::index::SiteIndex;
+use crate::postprocess::{rewrite_page, HtmlRules};
+use crate::split::headings;
+use crate::template::init_tera;
+
+/// Inline styles given to the elements of a note's content, as mail clients drop stylesheets.
+const EMAIL_STYLES: &[(&str, &str)] = &[
+    ("p", "margin:0 0 16px;"),
+    ("h1, h2, h3", "margin:24px 0 8px;font-size:19px;line-height:1.3;"),
+    ("h4, h5, h6", "margin:16px 0 8px;font-size:16px;"),
+    ("a", "color:#1a5fb4;"),
+    ("img", "max-width:100%;height:auto;border:0;"),
+    ("pre", "margin:0 0 16px;padding:12px;background:#f6f6f6;overflow:auto;font-size:14px;line-height:1.4;"),
+    ("code", "font-family:Menlo,Consolas,monospace;font-size:14px;"),
+    ("blockquote", "margin:0 0 16px;padding-left:12px;border-left:3px solid #dddddd;color:#555555;"),
+    ("table", "border-collapse:collapse;margin:0 0 16px;"),
+    ("th, td", "border:1px solid #dddddd;padding:4px 8px;text-align:left;"),
+    ("ul, ol", "margin:0 0 16px;padding-left:24px;"),
+    ("figcaption", "font-size:14px;color:#666666;"),
+];
+
+/// Elements left out of the digest: scripts and players mail clients won't run,
+/// and the heading anchors, which are empty links.
+const UNSAFE_ELEMENTS: &str = "script, style, iframe, form, button, input, a.anchor, .copy-code";
+
+#[derive(Serialize)]
+struct DigestNote {
+    title: String,
+    url: String,
+    date: String,
+    new: bool,
+    content: String,
+}
+
+/// Renders the notes published or updated in the last `days` days into one HTML
+/// email with the theme's `digest.html`: styles inlined, links and images
+/// pointing at the published site. Needs `site_url`.
+pub fn render_digest(index: &SiteIndex, days: u32, config: &Config) -> io::Result<String> {
+    let Some(site_url) = &config.site_url else {
+        return Err(config_error("The digest links to the published site, so site_url must be set".to_string()));
+    };
+    let tz = site_timezone(config)?;
+    start_build();
+    let until = build_started().with_timezone(&tz);
+    let since = until - TimeDelta::days(days.into());
+
+    let tera = init_tera(config)?;
+    let comrak_options = make_comrak_options(config);
+    let mut notes = Vec::new();
+    // The digest is sent in the clear, so it leaves out the notes of encrypted folders
+    let listed = index.notes.iter().filter(|note| encrypted_folder(&note.source, config).is_none());
+    for recent in recent_notes(listed, Some(since), tz) {
+        let note = recent.note;
+        // The digest shows the title, so an H1 that repeats it is dropped
+        let start = headings(&note.body)
+            .first()
+            .filter(|h| h.level == 1 && h.text == note.title && note.body[..h.line.start].trim().is_empty())
+            .map_or(0, |h| h.line.end);
+        let html = note_html(&note.body[start..], note, index, &tera, &comrak_options, config, Anchorizer::new());
+        notes.push(DigestNote {
+            title: note.title.clone(),
+            url: absolute_url(&note.url, config),
+            date: recent.date.to_rfc3339(),
+            new: recent.new,
+            content: email_safe(&html, &absolute_url(&note.url, config), config)?,
+        });
+    }
+    info!("Digest of {} notes since {}", notes.len(), since.format("%Y-%m-%d"));
+
+    let mut context = Context::new();
+    context.insert("title", config.site_name.as_deref().unwrap_or("Recent notes"));
+    context.insert("site_url", site_url);
+    context.insert("since", &since.to_rfc3339());
+    context.insert("until", &until.to_rfc3339());
+    context.insert("notes", &notes);
+    let html = tera
+        .render("digest.html", &context)
+        .map_err(|e| io::Error::other(format!("Template rendering failed for digest.html: {e}")))?;
+    rewrite_page(&html, &HtmlRules::new(&config.html_rewrite)?, config.base_path.as_deref())
+}
+
+/// Prepares a note's HTML for mail clients: inline styles, absolute URLs, and
+/// nothing that runs or embeds other pages.
+fn email_safe(html: &str, page_url: &str, config: &Config) -> io::Result<String> {
+    let html = standalone_html(html, page_url, UNSAFE_ELEMENTS, config)?;
+    let mut handlers = Vec::new();
+    for (selector, style) in EMAIL_STYLES {
+        handlers.push(element!(selector, move |el: &mut Element| {
+            let style = match el.get_attribute("style") {
+                Some(existing) if !existing.trim().is_empty() => format!("{style}{existing}"),
+                _ => style.to_string(),
+            };
+            el.set_attribute("style", &style)?;
+            Ok(())
+        }));
+    }
+    let settings = RewriteStrSettings { element_content_handlers: handlers, ..RewriteStrSettings::new() };
+    rewrite_str(&html, settings).map_err(|e| io::Error::other(format!("Preparing the digest failed: {e}")))
+}
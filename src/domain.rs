@@ -1,11 +1,171 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
-#[derive(Deserialize, Debug, Serialize)]
+#[derive(Deserialize, Debug, Serialize, Default, Clone)]
 pub struct Frontmatter {
+    /// Stable identifier (`id` or `uid`) the note is published and linked under
+    #[serde(alias = "uid", default, deserialize_with = "text_or_number")]
+    pub id: Option<String>,
     pub title: Option<String>,
     pub date: Option<String>,
+    /// When what the note describes happened, which places it on the timeline instead of `date`
+    pub event_date: Option<String>,
     pub tags: Option<Vec<String>>,
+    /// Template used to render the note instead of base.html
+    pub template: Option<String>,
+    /// Set to false to leave the note out of the site
+    pub publish: Option<bool>,
+    /// Alt text for embedded images, keyed by the embed target or file name
+    pub alt: Option<HashMap<String, String>>,
+    /// Show or hide the print button on this note, overriding the site setting
+    pub print: Option<bool>,
+    /// Show or hide the QR code on this note, overriding the site setting
+    pub qr: Option<bool>,
+    /// `archived` marks the note as archived
+    pub status: Option<String>,
+    /// Marks the note as outdated
+    pub outdated: Option<bool>,
+    /// Note that replaces this one, linked from the archived banner
+    #[serde(alias = "superseded_by")]
+    pub successor: Option<String>,
+    /// Cover image (a vault asset or an absolute URL) shown in listings and used as `og:image`
+    #[serde(alias = "image")]
+    pub cover: Option<String>,
+    /// Split the note into pages at its headings (or keep it whole), whatever its length
+    pub split: Option<bool>,
+    /// When the note was last changed, shown as "Last updated"
+    #[serde(alias = "modified")]
+    pub updated: Option<String>,
+    /// Show the note's external links as bookmark cards (or as plain links), overriding the site setting
+    pub link_cards: Option<bool>,
+    /// Fold the note's H2 and H3 sections into expandable blocks (or not), overriding the site setting
+    pub fold: Option<bool>,
+    /// What the note is: `article`, `blogposting`, or `techarticle`, for its structured data
+    #[serde(rename = "type")]
+    pub page_type: Option<String>,
+    /// Who wrote the note: one name or a list, overriding the site's `author`
+    pub author: Option<OneOrMany>,
+    /// Who may read the note: everyone (`public`, the default) or only the team (`internal`)
+    pub audience: Option<Audience>,
+    /// Render the body as a Tera template, with `site` and `page`, before it is converted
+    pub templating: Option<bool>,
+    /// Embed the note's body where other notes embed it, without publishing it as a page
+    pub partial: Option<bool>,
+    /// Where the note is: `[lat, lng]`, or `"lat,lng"` as Obsidian's Map View writes it, which places it on the map
+    pub location: Option<Location>,
+}
+
+/// Who a note is published for. Internal notes are left out of the public site.
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Audience {
+    Public,
+    Internal,
+}
+
+impl Audience {
+    pub const ALL: [Audience; 2] = [Audience::Public, Audience::Internal];
+
+    /// Name of the audience, as written in frontmatter and used for its site's folder.
+    pub fn name(self) -> &'static str {
+        match self {
+            Audience::Public => "public",
+            Audience::Internal => "internal",
+        }
+    }
+
+    /// Whether a note for `self` is published on the site built for `reader`.
+    pub fn visible_to(self, reader: Audience) -> bool {
+        self == Audience::Public || reader == Audience::Internal
+    }
+}
+
+/// An `id` written as text or, as Zettelkasten plugins write timestamps (`id: 202301011200`), as a number.
+fn text_or_number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Text(String),
+        Integer(i64),
+        Number(f64),
+    }
+    Ok(Option::<Id>::deserialize(deserializer)?.map(|id| match id {
+        Id::Text(text) => text,
+        Id::Integer(number) => number.to_string(),
+        Id::Number(number) => number.to_string(),
+    }))
+}
+
+/// A frontmatter value written either as a single string or as a list of them.
+#[derive(Deserialize, Debug, Serialize, Clone)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    pub fn values(&self) -> &[String] {
+        match self {
+            OneOrMany::One(value) => std::slice::from_ref(value),
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+/// A `location` frontmatter value: a latitude and longitude, as a pair or as text.
+#[derive(Deserialize, Debug, Serialize, Clone)]
+#[serde(untagged)]
+pub enum Location {
+    Pair([f64; 2]),
+    Text(String),
+}
+
+impl Location {
+    /// The latitude and longitude, when they are numbers in range.
+    pub fn coordinates(&self) -> Option<(f64, f64)> {
+        let (lat, lng) = match self {
+            Location::Pair([lat, lng]) => (*lat, *lng),
+            Location::Text(text) => {
+                let (lat, lng) = text.split_once(',')?;
+                (lat.trim().parse().ok()?, lng.trim().parse().ok()?)
+            }
+        };
+        ((-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lng)).then_some((lat, lng))
+    }
+}
+
+/// How notes are ordered within a folder of the navigation tree.
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[serde(rename = "title")]
+    Title,
+    #[serde(rename = "-title")]
+    TitleDesc,
+    #[serde(rename = "date")]
+    Date,
+    #[serde(rename = "-date")]
+    DateDesc,
+    #[serde(rename = "path")]
+    Path,
+    #[default]
+    #[serde(rename = "-path")]
+    PathDesc,
+}
+
+/// Defaults a folder sets for every note below it, read from `_folder.yml` or the
+/// frontmatter of `folder.md`. Subfolders inherit them and can override each field.
+#[derive(Deserialize, Debug, Serialize, Clone, Default)]
+pub struct FolderDefaults {
+    pub template: Option<String>,
+    /// Tags added to every note in the folder
+    pub tags: Option<Vec<String>>,
+    pub publish: Option<bool>,
+    pub sort: Option<SortOrder>,
+    pub audience: Option<Audience>,
+    /// Notes in the folder are partials, only published where they are embedded
+    pub partial: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -18,5 +178,14 @@ pub struct Node {
 #[derive(Debug, Serialize, Clone)]
 pub struct Note {
     pub title: String,
+    /// Vault-relative path of the source note
     pub path: PathBuf,
+    /// Root-relative URL of the rendered page
+    pub url: String,
+    pub date: Option<String>,
+    pub tags: Option<Vec<String>>,
+    /// URL of the note's cover image
+    pub cover: Option<String>,
+    /// Archived or outdated, so listed apart from current notes
+    pub archived: bool,
 }
@@ -0,0 +1,285 @@
+use std::cell::RefCell;
+use std::io;
+use std::sync::LazyLock;
+use lol_html::{element, rewrite_str, text, RewriteStrSettings};
+use regex::Regex;
+use serde_json::Value;
+use crate::content::escape_html;
+use crate::http::HttpClient;
+use crate::postprocess::{HtmlRule, HtmlRules};
+
+static YOUTUBE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^https?://(?:www\.|m\.)?(?:youtube\.com/(?:watch\?(?:.*&)?v=|shorts/|embed/|live/)|youtu\.be/|youtube-nocookie\.com/embed/)([\w-]{11})(?:[?&#].*)?$",
+    )
+    .unwrap()
+});
+static VIMEO: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^https?://(?:www\.|player\.)?vimeo\.com/(?:video/)?(\d+)(?:[/?#].*)?$").unwrap());
+/// `t=90`, `t=1m30s`, or `start=90` in a YouTube URL.
+static START_TIME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[?&#](?:t|start)=(?:(\d+)h)?(?:(\d+)m)?(?:(\d+)s?)?(?:&|$)").unwrap());
+
+static TWEET: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^https?://(?:www\.|mobile\.)?(?:twitter|x)\.com/(\w+)/status(?:es)?/(\d+)/?(?:\?.*)?$").unwrap()
+});
+/// `https://mastodon.social/@user/109...`, also for remote accounts (`@user@other.host`).
+static TOOT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^https://([\w.-]+\.[a-z]+)/@[\w.]+(?:@[\w.-]+)?/(\d+)/?$").unwrap());
+
+/// A video on a hosting site that can be embedded with a player.
+pub struct HostedVideo {
+    /// `youtube` or `vimeo`
+    pub host: &'static str,
+    pub id: String,
+    /// Seconds into the video playback starts at
+    pub start: Option<u32>,
+}
+
+impl HostedVideo {
+    /// Recognizes YouTube and Vimeo video URLs.
+    pub fn parse(url: &str) -> Option<HostedVideo> {
+        if let Some(caps) = YOUTUBE.captures(url) {
+            let start = START_TIME.captures(url).and_then(|caps| {
+                let part = |i: usize| caps.get(i).map_or(0, |m| m.as_str().parse::<u32>().unwrap_or(0));
+                let seconds = part(1) * 3600 + part(2) * 60 + part(3);
+                (seconds > 0).then_some(seconds)
+            });
+            return Some(HostedVideo { host: "youtube", id: caps[1].to_string(), start });
+        }
+        let caps = VIMEO.captures(url)?;
+        Some(HostedVideo { host: "vimeo", id: caps[1].to_string(), start: None })
+    }
+
+    /// The player loaded once the facade is clicked, from the host's cookieless domain where there is one.
+    pub fn player_url(&self) -> String {
+        match self.host {
+            "youtube" => {
+                let start = self.start.map(|s| format!("&start={s}")).unwrap_or_default();
+                format!("https://www.youtube-nocookie.com/embed/{}?autoplay=1{start}", self.id)
+            }
+            _ => format!("https://player.vimeo.com/video/{}?autoplay=1&dnt=1", self.id),
+        }
+    }
+
+    fn thumbnail_url(&self) -> Option<String> {
+        // Vimeo only hands out thumbnails through its API
+        (self.host == "youtube").then(|| format!("https://i.ytimg.com/vi/{}/hqdefault.jpg", self.id))
+    }
+
+    /// A click-to-load stand-in for the player: a thumbnail and play button linking
+    /// to the video, which video-facade.js swaps for the player's iframe when clicked.
+    /// Nothing is requested from the host before then but the thumbnail.
+    pub fn facade_html(&self, url: &str, title: &str) -> String {
+        let thumbnail = self
+            .thumbnail_url()
+            .map(|src| format!("<img src=\"{src}\" alt=\"\" loading=\"lazy\">"))
+            .unwrap_or_default();
+        format!(
+            "<div class=\"video-facade video-facade-{}\" data-player=\"{}\" data-title=\"{}\"><a href=\"{}\">{thumbnail}<span class=\"video-facade-play\" aria-hidden=\"true\">▶</span><span class=\"video-facade-title\">{}</span></a></div>",
+            self.host,
+            escape_html(&self.player_url()),
+            escape_html(title),
+            escape_html(url),
+            escape_html(title)
+        )
+    }
+}
+
+/// A post on Twitter/X or a Mastodon server.
+pub enum SocialPost {
+    Tweet { url: String },
+    Toot { host: String, id: String },
+}
+
+impl SocialPost {
+    pub fn parse(url: &str) -> Option<SocialPost> {
+        if let Some(caps) = TWEET.captures(url) {
+            // The oEmbed endpoint only knows twitter.com URLs
+            return Some(SocialPost::Tweet { url: format!("https://twitter.com/{}/status/{}", &caps[1], &caps[2]) });
+        }
+        let caps = TOOT.captures(url)?;
+        Some(SocialPost::Toot { host: caps[1].to_string(), id: caps[2].to_string() })
+    }
+
+    /// The post as a static blockquote. Scripts the host's markup may carry are
+    /// removed, so the page loads nothing from it.
+    pub fn embed_html(&self, url: &str, http: &HttpClient) -> io::Result<String> {
+        let html = match self {
+            SocialPost::Tweet { url } => {
+                let query = [("url", url.as_str()), ("omit_script", "true"), ("dnt", "true")];
+                let oembed = http.get_json("https://publish.twitter.com/oembed", &query)?;
+                let quote = oembed["html"].as_str().ok_or_else(|| io::Error::other(format!("No embed for {url}")))?;
+                format!("<div class=\"post-embed post-embed-twitter\">{}</div>", quote.trim())
+            }
+            SocialPost::Toot { host, id } => {
+                let status = http.get_json(&format!("https://{host}/api/v1/statuses/{id}"), &[])?;
+                toot_html(&status, url)
+            }
+        };
+        let no_scripts = [HtmlRule { selector: "script".to_string(), remove: true, ..HtmlRule::default() }];
+        HtmlRules::new(&no_scripts)?.rewrite(&html)
+    }
+}
+
+fn toot_html(status: &Value, url: &str) -> String {
+    let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+    let account = &status["account"];
+    let name = match text(&account["display_name"]) {
+        name if name.is_empty() => text(&account["username"]),
+        name => name,
+    };
+    let images: String = status["media_attachments"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|media| media["type"] == "image")
+        .map(|media| {
+            format!(
+                "<img src=\"{}\" alt=\"{}\" loading=\"lazy\">",
+                escape_html(&text(&media["preview_url"])),
+                escape_html(&text(&media["description"]))
+            )
+        })
+        .collect();
+    let created = text(&status["created_at"]);
+    format!(
+        "<blockquote class=\"post-embed post-embed-mastodon\" cite=\"{url}\">\
+         <div class=\"post-embed-content\">{}</div>{images}\
+         <footer>&mdash; <a href=\"{}\">{} (@{})</a>, <a href=\"{url}\"><time datetime=\"{created}\">{}</time></a></footer>\
+         </blockquote>",
+        text(&status["content"]),
+        escape_html(&text(&account["url"])),
+        escape_html(&name),
+        escape_html(&text(&account["acct"])),
+        created.get(..10).unwrap_or(&created),
+        url = escape_html(url),
+        created = escape_html(&created),
+    )
+}
+
+/// What a bookmark card shows of a page, from its Open Graph metadata.
+#[derive(Debug, Default)]
+pub struct LinkCard {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub site_name: Option<String>,
+}
+
+impl LinkCard {
+    pub fn fetch(url: &str, http: &HttpClient) -> io::Result<LinkCard> {
+        Ok(LinkCard::from_html(&http.get(url, &[], "text/html")?, url))
+    }
+
+    /// Reads `og:` meta tags, falling back to the page's `<title>` and description.
+    fn from_html(html: &str, url: &str) -> LinkCard {
+        let card = RefCell::new(LinkCard::default());
+        let page_title = RefCell::new(String::new());
+        let description = RefCell::new(None);
+        let handlers = vec![
+            element!("meta[property][content], meta[name][content]", |el| {
+                let key = el.get_attribute("property").or_else(|| el.get_attribute("name")).unwrap_or_default();
+                let content = el.get_attribute("content").map(|c| unescape_html(c.trim())).filter(|c| !c.is_empty());
+                let mut card = card.borrow_mut();
+                match key.to_lowercase().as_str() {
+                    "og:title" => card.title = content,
+                    "og:description" => card.description = content,
+                    "og:image" => card.image = content.map(|src| absolute(&src, url)),
+                    "og:site_name" => card.site_name = content,
+                    "description" => *description.borrow_mut() = content,
+                    _ => {}
+                }
+                Ok(())
+            }),
+            text!("head > title", |chunk| {
+                page_title.borrow_mut().push_str(chunk.as_str());
+                Ok(())
+            }),
+        ];
+        let settings = RewriteStrSettings { element_content_handlers: handlers, ..RewriteStrSettings::new() };
+        // Malformed markup only means less metadata
+        let _ = rewrite_str(html, settings);
+        let mut card = card.into_inner();
+        let page_title = unescape_html(page_title.into_inner().trim());
+        card.title = card.title.or((!page_title.is_empty()).then_some(page_title));
+        card.description = card.description.or(description.into_inner());
+        card
+    }
+
+    /// The card as a link to `url`, titled with `text` when the page has no title.
+    pub fn html(&self, url: &str, text: &str) -> String {
+        let host = url.split("://").nth(1).and_then(|rest| rest.split(['/', '?', '#']).next()).unwrap_or(url);
+        let title = self.title.as_deref().unwrap_or(text);
+        let description = self
+            .description
+            .as_ref()
+            .map(|d| format!("<span class=\"link-card-description\">{}</span>", escape_html(d)))
+            .unwrap_or_default();
+        let image = self
+            .image
+            .as_ref()
+            .map(|src| format!("<img class=\"link-card-image\" src=\"{}\" alt=\"\" loading=\"lazy\">", escape_html(src)))
+            .unwrap_or_default();
+        format!(
+            "<a class=\"link-card\" href=\"{}\"><span class=\"link-card-text\"><span class=\"link-card-title\">{}</span>{description}<span class=\"link-card-site\">{}</span></span>{image}</a>",
+            escape_html(url),
+            escape_html(title),
+            escape_html(self.site_name.as_deref().unwrap_or(host))
+        )
+    }
+}
+
+/// Decodes the character references lol_html leaves in attribute values and text.
+fn unescape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                code => match code.strip_prefix("#x").or_else(|| code.strip_prefix("#X")) {
+                    Some(hex) => char::from_u32(u32::from_str_radix(hex, 16).ok()?)?,
+                    None => char::from_u32(code.strip_prefix('#')?.parse().ok()?)?,
+                },
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Resolves an image URL found on the page at `base`.
+fn absolute(src: &str, base: &str) -> String {
+    if src.contains("://") {
+        return src.to_string();
+    }
+    let (scheme, rest) = base.split_once("://").unwrap_or(("https", base));
+    if let Some(src) = src.strip_prefix("//") {
+        return format!("{scheme}://{src}");
+    }
+    let origin = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    if src.starts_with('/') {
+        return format!("{scheme}://{origin}{src}");
+    }
+    let dir = base.rsplit_once('/').map_or(base, |(dir, _)| dir);
+    format!("{dir}/{src}")
+}
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tera::{Context, Tera};
+use crate::config::Config;
+use crate::content::href_to_root_style_css;
+use crate::diagnostics::{config_error, info};
+use crate::fs::AssetMode;
+use crate::index::SiteIndex;
+use crate::vfs::{OutputSink, VaultSource};
+
+/// PBKDF2-SHA256 rounds deriving a folder's key from its password, as OWASP recommends.
+const KEY_ITERATIONS: u32 = 600_000;
+
+/// A folder whose pages are published encrypted, readable only with its password,
+/// configured as an `[[encrypted_folder]]` table.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct EncryptedFolder {
+    /// Vault-relative folder; its subfolders are encrypted too
+    pub folder: PathBuf,
+    /// Environment variable holding the password, which keeps it out of obs2web.toml
+    pub password_env: Option<String>,
+    /// The password itself, when `password_env` isn't set
+    pub password: Option<String>,
+}
+
+impl EncryptedFolder {
+    fn password(&self) -> io::Result<String> {
+        let password = match &self.password_env {
+            Some(name) => std::env::var(name).ok(),
+            None => self.password.clone(),
+        };
+        password.filter(|password| !password.is_empty()).ok_or_else(|| {
+            let source = self.password_env.as_ref().map_or("password".to_string(), |name| format!("${name}"));
+            config_error(format!("encrypted_folder {} has no password: {source} is not set", self.folder.display()))
+        })
+    }
+}
+
+/// The folder configured for encryption that the note at `source` is in, if any.
+pub fn encrypted_folder<'a>(source: &Path, config: &'a Config) -> Option<&'a EncryptedFolder> {
+    config.encrypted_folders.iter().find(|encrypted| source.starts_with(&encrypted.folder))
+}
+
+/// What the shell page hands its script to decrypt.
+#[derive(Serialize)]
+struct EncryptedPage {
+    salt: String,
+    iterations: u32,
+    iv: String,
+    data: String,
+}
+
+/// A folder's key, derived once per build.
+struct FolderKey {
+    folder: PathBuf,
+    salt: [u8; 16],
+    cipher: Aes256Gcm,
+    /// Keys the HMAC that picks each page's nonce, so a nonce says nothing of the page to anyone without the password
+    nonce_key: [u8; 32],
+}
+
+/// Encrypts the pages of the notes in `[[encrypted_folder]]`s, and any other page
+/// written below those folders, with AES-256-GCM under a key derived from the
+/// folder's password. Each page is replaced by `encrypted.html`, a shell that asks
+/// for the password and decrypts the page in the browser with the Web Crypto API;
+/// other files pass through untouched.
+pub struct EncryptPages<'a> {
+    inner: &'a dyn OutputSink,
+    tera: &'a Tera,
+    keys: Vec<FolderKey>,
+    /// Output path of every encrypted note's page, by the index of its folder's key
+    pages: HashMap<PathBuf, usize>,
+}
+
+impl<'a> EncryptPages<'a> {
+    pub fn new(inner: &'a dyn OutputSink, tera: &'a Tera, index: &SiteIndex, config: &Config) -> io::Result<EncryptPages<'a>> {
+        let site = config.site_url.as_deref().or(config.site_name.as_deref()).unwrap_or_default();
+        let mut keys = Vec::new();
+        for encrypted in &config.encrypted_folders {
+            // The salt is derived rather than random so that rebuilding an unchanged vault gives the same site
+            let digest = Sha256::new()
+                .chain_update("obs2web encrypted folder\0")
+                .chain_update(site)
+                .chain_update("\0")
+                .chain_update(encrypted.folder.to_string_lossy().as_bytes())
+                .finalize();
+            let mut salt = [0; 16];
+            salt.copy_from_slice(&digest[..16]);
+            let mut key = [0; 32];
+            pbkdf2::pbkdf2_hmac::<Sha256>(encrypted.password()?.as_bytes(), &salt, KEY_ITERATIONS, &mut key);
+            let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+            let nonce_key = keyed(&key, &[b"obs2web page nonce"]);
+            keys.push(FolderKey { folder: encrypted.folder.clone(), salt, cipher, nonce_key });
+        }
+        let pages: HashMap<PathBuf, usize> = index
+            .notes
+            .iter()
+            .filter_map(|note| {
+                let i = config.encrypted_folders.iter().position(|encrypted| note.source.starts_with(&encrypted.folder))?;
+                let outputs = note.parts.iter().map(|part| part.output.clone()).chain([note.output.clone()]);
+                Some(outputs.map(move |output| (output, i)))
+            })
+            .flatten()
+            .collect();
+        if !keys.is_empty() {
+            info!("Encrypting {} pages in {} folders", pages.len(), keys.len());
+        }
+        Ok(EncryptPages { inner, tera, keys, pages })
+    }
+
+    fn key_for(&self, path: &Path) -> Option<&FolderKey> {
+        match self.pages.get(path) {
+            Some(&i) => Some(&self.keys[i]),
+            None => self.keys.iter().find(|key| path.starts_with(&key.folder)),
+        }
+    }
+
+    fn encrypt(&self, path: &Path, key: &FolderKey, html: &[u8]) -> io::Result<String> {
+        // A nonce keyed on the page's path and contents is never reused for different text, and keeps builds
+        // reproducible; keying it stops it confirming a guess at the page
+        let digest = keyed(&key.nonce_key, &[path.to_string_lossy().as_bytes(), b"\0", html]);
+        let iv = &digest[..12];
+        let data = key
+            .cipher
+            .encrypt(Nonce::from_slice(iv), html)
+            .map_err(|e| io::Error::other(format!("Encrypting {} failed: {e}", path.display())))?;
+        let page = EncryptedPage {
+            salt: BASE64.encode(key.salt),
+            iterations: KEY_ITERATIONS,
+            iv: BASE64.encode(iv),
+            data: BASE64.encode(data),
+        };
+        let mut context = Context::new();
+        context.insert("relative_path", &href_to_root_style_css(path));
+        context.insert("page", &serde_json::to_string(&page).map_err(io::Error::other)?);
+        self.tera
+            .render("encrypted.html", &context)
+            .map_err(|e| io::Error::other(format!("Template rendering failed for {}: {e}", path.display())))
+    }
+}
+
+impl OutputSink for EncryptPages<'_> {
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let is_page = path.extension().is_some_and(|ext| ext == "html");
+        match self.key_for(path) {
+            Some(key) if is_page => self.inner.write(path, self.encrypt(path, key, contents)?.as_bytes()),
+            _ => self.inner.write(path, contents),
+        }
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.inner.read(path)
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        self.inner.size(path)
+    }
+
+    fn files(&self) -> io::Result<Vec<PathBuf>> {
+        self.inner.files()
+    }
+
+    fn copy_asset(&self, vault: &dyn VaultSource, source: &Path, dest: &Path, mode: AssetMode) -> io::Result<()> {
+        self.inner.copy_asset(vault, source, dest, mode)
+    }
+
+    fn local_dir(&self) -> Option<&Path> {
+        self.inner.local_dir()
+    }
+}
+
+/// HMAC-SHA256 of `parts`, in order, under `key`.
+fn keyed(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes a key of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    let mut digest = [0; 32];
+    digest.copy_from_slice(&mac.finalize().into_bytes());
+    digest
+}
@@ -0,0 +1,163 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+pub use crate::cli::ExportFormat;
+use crate::config::Config;
+use crate::content::split_frontmatter;
+use crate::diagnostics::{broken_link, file_error, info, set_vault};
+use crate::domain::Frontmatter;
+use crate::fs::{prepare_output_dir, process_asset, vault_files};
+use crate::links::{is_note, replace_wikilinks, slugify, LinkResolver};
+
+#[derive(Serialize)]
+struct HugoFrontmatter<'a> {
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    tags: &'a [String],
+}
+
+#[derive(Serialize)]
+struct ZolaFrontmatter<'a> {
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    taxonomies: Option<ZolaTaxonomies<'a>>,
+}
+
+#[derive(Serialize)]
+struct ZolaTaxonomies<'a> {
+    tags: &'a [String],
+}
+
+/// Exports the vault as SSG content: every note becomes a page bundle
+/// (`content/<folder>/<slug>/index.md`) holding cleaned markdown with resolved
+/// links, normalized frontmatter, and the attachments it references.
+pub fn export_site(
+    vault_path: &Path,
+    output_dir: &Path,
+    format: ExportFormat,
+    config: &Config,
+) -> io::Result<()> {
+    info!("Exporting {format:?} content...");
+    set_vault(vault_path);
+    prepare_output_dir(output_dir)?;
+
+    let files: Vec<PathBuf> = vault_files(vault_path, config)
+        .into_iter()
+        .filter_map(|p| p.strip_prefix(vault_path).ok().map(Path::to_path_buf))
+        .collect();
+    let resolver = LinkResolver::new(files.iter().cloned());
+    let content_dir = output_dir.join("content");
+    let mut sections = BTreeSet::from([content_dir.clone()]);
+
+    for relative in files.iter().filter(|p| is_note(p)) {
+        let path = vault_path.join(relative);
+        info!("Exporting note: {}", path.display());
+        let (data, body) = split_frontmatter(&fs::read_to_string(&path)?);
+        let frontmatter = match data {
+            Some(data) => data.deserialize::<Frontmatter>().map_err(|e| {
+                file_error(relative, format!("Frontmatter deserialize error in {}: {e}", path.display()))
+            })?,
+            None => Frontmatter::default(),
+        };
+
+        let bundle = content_dir.join(bundle_path(relative));
+        fs::create_dir_all(&bundle)?;
+        let mut section = bundle.parent();
+        while let Some(dir) = section.filter(|d| d.starts_with(&content_dir)) {
+            sections.insert(dir.to_path_buf());
+            section = dir.parent();
+        }
+
+        let mut resources = Vec::new();
+        let body = replace_wikilinks(&body, |link| {
+            let label = link.label();
+            let Some(target) = resolver.resolve(&link.target) else {
+                if !link.target.is_empty() {
+                    broken_link!(file: relative, near: &format!("[[{}", link.target); "unresolved link [[{}]] in {}", link.target, path.display());
+                }
+                return label;
+            };
+            if is_note(target) {
+                let anchor = link.heading.as_deref().map(|h| format!("#{}", slugify(h))).unwrap_or_default();
+                return format!("[{label}]({}{anchor})", page_ref(format, &bundle_path(target)));
+            }
+            // Attachments become page resources stored next to the exported page
+            let name = target.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            resources.push(target.to_path_buf());
+            if link.embed {
+                format!("![{}](<{name}>)", link.alias.as_deref().unwrap_or(""))
+            } else {
+                format!("[{label}](<{name}>)")
+            }
+        });
+
+        let title = frontmatter.title.clone().unwrap_or_else(|| {
+            relative.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+        });
+        let tags = frontmatter.tags.clone().unwrap_or_default();
+        let page = match format {
+            ExportFormat::Hugo => {
+                let fm = HugoFrontmatter { title: &title, date: frontmatter.date.as_deref(), tags: &tags };
+                format!("---\n{}---\n\n{}", serde_yaml::to_string(&fm).map_err(io::Error::other)?, body.trim_start())
+            }
+            ExportFormat::Zola => {
+                let fm = ZolaFrontmatter {
+                    title: &title,
+                    date: frontmatter.date.as_deref(),
+                    taxonomies: (!tags.is_empty()).then_some(ZolaTaxonomies { tags: &tags }),
+                };
+                format!("+++\n{}+++\n\n{}", toml::to_string(&fm).map_err(io::Error::other)?, body.trim_start())
+            }
+            ExportFormat::Corpus => unreachable!("corpus exports are written by export_corpus"),
+        };
+        fs::write(bundle.join("index.md"), page)?;
+
+        for resource in resources {
+            let name = resource.file_name().unwrap_or_default();
+            process_asset(&vault_path.join(&resource), &bundle.join(name), config.asset_mode)?;
+        }
+    }
+
+    // Zola only renders pages that live inside a section
+    if format == ExportFormat::Zola {
+        for section in sections {
+            let index = section.join("_index.md");
+            if !index.exists() {
+                let title = section.file_name().unwrap_or_default().to_string_lossy();
+                let fm = ZolaFrontmatter { title: &title, date: None, taxonomies: None };
+                fs::write(index, format!("+++\n{}+++\n", toml::to_string(&fm).map_err(io::Error::other)?))?;
+            }
+        }
+    }
+
+    info!("Export finished.");
+    Ok(())
+}
+
+/// Content-relative directory of a note's page bundle, e.g. `Projects/My Note.md` -> `projects/my-note`.
+fn bundle_path(note: &Path) -> PathBuf {
+    let mut bundle: PathBuf = note
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .map(|c| slugify(&c.as_os_str().to_string_lossy()))
+        .collect();
+    bundle.push(slugify(&note.file_stem().unwrap_or_default().to_string_lossy()));
+    bundle
+}
+
+/// A link the generator validates and turns into the page's final URL.
+fn page_ref(format: ExportFormat, bundle: &Path) -> String {
+    let bundle = bundle.to_string_lossy().replace('\\', "/");
+    match format {
+        ExportFormat::Hugo => format!("{{{{< relref \"/{bundle}\" >}}}}"),
+        ExportFormat::Zola => format!("@/{bundle}/index.md"),
+        ExportFormat::Corpus => unreachable!("corpus exports are written by export_corpus"),
+    }
+}
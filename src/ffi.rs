@@ -0,0 +1,92 @@
+use std::ffi::{c_char, CString};
+use std::path::Path;
+use std::{slice, str};
+use comrak::Anchorizer;
+use crate::config::Config;
+use crate::content::{escape_html, make_comrak_options, split_frontmatter};
+use crate::links::replace_wikilinks;
+use crate::markdown::markdown_to_html;
+
+// A C-compatible API for embedding the renderer, e.g. in an Obsidian plugin through
+// WebAssembly. Strings go in as UTF-8 pointer and length pairs, in memory the host
+// allocates with `obs2web_alloc`, and come out as NUL-terminated strings the host
+// hands back to `obs2web_free_string`.
+
+/// Renders a note's Markdown the way a site build does (callouts, code blocks,
+/// heading levels, and so on, as `config` sets them), without its frontmatter.
+/// Wikilinks become their labels, since resolving them needs the whole vault.
+pub fn render_markdown(markdown: &str, config: &Config) -> String {
+    let (_, body) = split_frontmatter(markdown);
+    let body = replace_wikilinks(&body, |link| escape_html(&link.label()));
+    markdown_to_html(&body, &make_comrak_options(config), config, Path::new("note.md"), Anchorizer::new())
+}
+
+/// Allocates `len` bytes for the host to write an argument into.
+#[unsafe(no_mangle)]
+pub extern "C" fn obs2web_alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len);
+    let ptr = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    ptr
+}
+
+/// Frees memory from `obs2web_alloc`.
+///
+/// # Safety
+///
+/// `ptr` must come from `obs2web_alloc(len)` and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn obs2web_dealloc(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        drop(unsafe { Vec::from_raw_parts(ptr, 0, len) });
+    }
+}
+
+/// Renders Markdown to HTML with `render_markdown`. `config` holds obs2web.toml
+/// settings as TOML and may be empty. Returns NULL when an argument isn't UTF-8
+/// or the config is invalid.
+///
+/// # Safety
+///
+/// `markdown` and `config` must point to `markdown_len` and `config_len` readable
+/// bytes (either may be NULL when its length is 0).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn obs2web_render_markdown(
+    markdown: *const u8,
+    markdown_len: usize,
+    config: *const u8,
+    config_len: usize,
+) -> *mut c_char {
+    let (Some(markdown), Some(config)) = (unsafe { utf8(markdown, markdown_len) }, unsafe { utf8(config, config_len) })
+    else {
+        return std::ptr::null_mut();
+    };
+    let Ok(config) = toml::from_str::<Config>(config) else {
+        return std::ptr::null_mut();
+    };
+    let html = render_markdown(markdown, &config);
+    // A NUL in the note can't be represented in a C string
+    CString::new(html.replace('\0', "")).map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+/// Frees a string returned by obs2web.
+///
+/// # Safety
+///
+/// `string` must come from an obs2web function and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn obs2web_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+unsafe fn utf8<'a>(ptr: *const u8, len: usize) -> Option<&'a str> {
+    if len == 0 {
+        return Some("");
+    }
+    if ptr.is_null() {
+        return None;
+    }
+    str::from_utf8(unsafe { slice::from_raw_parts(ptr, len) }).ok()
+}
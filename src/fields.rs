@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json::Value;
+use crate::content::escape_html;
+use crate::links::slugify;
+
+/// A field key: letters, digits, spaces, `-`, and `_`, starting with a letter, digit, or `_`.
+const KEY: &str = r"[\p{L}\p{N}_][\p{L}\p{N}_ -]*?";
+
+/// A field on a line of its own, optionally as a list item: `Status:: In progress`.
+static LINE_FIELD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(&format!(r"^(\s*(?:[-*+]|\d+[.)])\s+)?({KEY})::[ \t]*(.*?)\s*$")).unwrap());
+
+/// A field within text: `[Status:: In progress]`, or `(Status:: In progress)`,
+/// which is shown without its key. Values may hold wikilinks.
+static BRACKETED_FIELD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"\[({KEY})::[ \t]*((?:\[\[[^\]]*\]\]|[^\]])*?)\s*\]|\(({KEY})::[ \t]*((?:\[\[[^\]]*\]\]|[^)])*?)\s*\)"
+    ))
+    .unwrap()
+});
+
+/// How Dataview inline fields appear on the published page.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InlineFields {
+    /// As they are written
+    #[default]
+    Plain,
+    /// Left out
+    Hide,
+    /// As `inline-field` spans with the key and value styled apart
+    Style,
+}
+
+/// The Dataview inline fields of a note body, keyed as written and, when that
+/// differs, also as Dataview normalizes them (`Due Date` is also `due-date`). A key
+/// given more than once holds a list of its values. Fenced code is ignored.
+pub fn inline_fields(body: &str) -> BTreeMap<String, Value> {
+    let mut fields: BTreeMap<String, Value> = BTreeMap::new();
+    let mut add = |key: &str, value: &str| {
+        let key = key.trim();
+        let mut keys = vec![key.to_string()];
+        if slugify(key) != key {
+            keys.push(slugify(key));
+        }
+        for key in keys {
+            let value = Value::String(value.to_string());
+            match fields.get_mut(&key) {
+                Some(Value::Array(values)) => values.push(value),
+                Some(existing) => *existing = Value::Array(vec![existing.take(), value]),
+                None => {
+                    fields.insert(key, value);
+                }
+            }
+        }
+    };
+    for_each_line(body, |line| {
+        match LINE_FIELD.captures(line) {
+            Some(caps) => add(&caps[2], &caps[3]),
+            None => {
+                for caps in BRACKETED_FIELD.captures_iter(line) {
+                    let (key, value) = bracketed(&caps);
+                    add(key, value);
+                }
+            }
+        }
+        line.to_string()
+    });
+    fields
+}
+
+/// Rewrites the inline fields of a note body for display: kept, left out, or
+/// wrapped in spans to style (a `(Key:: Value)` field only shows its value).
+pub fn render_inline_fields(body: &str, display: InlineFields) -> String {
+    if display == InlineFields::Plain {
+        return body.to_string();
+    }
+    let rendered = for_each_line(body, |line| {
+        if let Some(caps) = LINE_FIELD.captures(line) {
+            let prefix = caps.get(1).map_or("", |m| m.as_str());
+            return match display {
+                // A field on its own line or list item goes with it
+                InlineFields::Hide => String::new(),
+                _ => format!("{prefix}{}", field_html(Some(&caps[2]), &caps[3])),
+            };
+        }
+        BRACKETED_FIELD
+            .replace_all(line, |caps: &Captures| match display {
+                InlineFields::Hide => String::new(),
+                _ => {
+                    let (key, value) = bracketed(caps);
+                    field_html(caps.get(1).map(|_| key), value)
+                }
+            })
+            .into_owned()
+    });
+    // Lines that held nothing but hidden fields are dropped rather than left blank
+    rendered
+        .into_iter()
+        .zip(body.lines())
+        .filter(|(rendered, line)| !rendered.trim().is_empty() || line.trim().is_empty())
+        .map(|(rendered, _)| rendered)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Removes the inline fields named in `names` from a note body, matching keys as
+/// written or as Dataview normalizes them. A line that held only such a field is
+/// dropped with it; fenced code is left alone.
+pub fn remove_fields(body: &str, names: &[String]) -> String {
+    let private = |key: &str| {
+        let key = key.trim();
+        names.iter().any(|name| name == key || slugify(name) == slugify(key))
+    };
+    let mut removed = false;
+    let lines = for_each_line(body, |line| {
+        if let Some(caps) = LINE_FIELD.captures(line) {
+            if private(&caps[2]) {
+                removed = true;
+                return String::new();
+            }
+            return line.to_string();
+        }
+        BRACKETED_FIELD
+            .replace_all(line, |caps: &Captures| {
+                if private(bracketed(caps).0) {
+                    removed = true;
+                    return String::new();
+                }
+                caps[0].to_string()
+            })
+            .into_owned()
+    });
+    if !removed {
+        return body.to_string();
+    }
+    lines
+        .into_iter()
+        .zip(body.lines())
+        .filter(|(kept, line)| !kept.trim().is_empty() || line.trim().is_empty())
+        .map(|(kept, _)| kept)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The key and value of a `[Key:: Value]` or `(Key:: Value)` match.
+fn bracketed<'a>(caps: &'a Captures) -> (&'a str, &'a str) {
+    match (caps.get(1), caps.get(2)) {
+        (Some(key), Some(value)) => (key.as_str(), value.as_str()),
+        _ => (&caps[3], &caps[4]),
+    }
+}
+
+fn field_html(key: Option<&str>, value: &str) -> String {
+    let key = key.map_or(String::new(), |key| {
+        format!(r#"<span class="inline-field-key">{}</span>"#, escape_html(key.trim()))
+    });
+    // The value stays Markdown, so links and emphasis in it still render
+    format!(r#"<span class="inline-field">{key}<span class="inline-field-value">{value}</span></span>"#)
+}
+
+/// Applies `f` to every line of `body` outside fenced code, returning the lines
+/// with code lines unchanged.
+fn for_each_line(body: &str, mut f: impl FnMut(&str) -> String) -> Vec<String> {
+    let mut fence: Option<&str> = None;
+    body.lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            if let Some(open) = fence {
+                if trimmed.starts_with(open) {
+                    fence = None;
+                }
+                return line.to_string();
+            }
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                fence = Some(&trimmed[..3]);
+                return line.to_string();
+            }
+            f(line)
+        })
+        .collect()
+}
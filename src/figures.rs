@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::LazyLock;
+use regex::{Captures, Regex};
+use crate::content::escape_html;
+use crate::diagnostics::broken_link;
+use crate::links::{find_wikilinks, WikiLink};
+
+/// `fig:label: Caption` (or `fig: Caption`) in the alias of an image embed.
+static FIGURE_ALIAS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^fig:(?:([\w-]+):)?\s*(.*)$").unwrap());
+/// `Table: Caption {#tbl:label}` on its own line next to a table.
+static TABLE_CAPTION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^Table:\s*(.*?)\s*(?:\{#tbl:([\w-]+)\})?\s*$").unwrap());
+/// A line that is all emphasis, `*Caption*` or `_Caption_`, read as the caption of the image above it.
+static ITALIC_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:\*([^*\s][^*]*?)\*|_([^_\s][^_]*?)_)\s*$").unwrap());
+/// `@fig:label` or `@tbl:label` referring to a numbered figure or table.
+static CROSS_REFERENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"@(fig|tbl):([\w-]+)").unwrap());
+
+/// The label and caption of an image embed written as a numbered figure.
+pub fn figure_alias(alias: &str) -> Option<(Option<&str>, &str)> {
+    let caps = FIGURE_ALIAS.captures(alias)?;
+    Some((caps.get(1).map(|m| m.as_str()), caps.get(2).map_or("", |m| m.as_str())))
+}
+
+/// The caption of an image embed written as `![[image.png|caption: Caption]]`.
+pub fn caption_alias(alias: &str) -> Option<&str> {
+    alias.strip_prefix("caption:").map(str::trim)
+}
+
+/// Wraps an image in a `<figure>`, captioned `Figure <number>: <caption>` when numbered.
+pub fn figure_html(image: &str, id: Option<&str>, number: Option<usize>, caption: &str) -> String {
+    let id = id.map(|id| format!(" id=\"{}\"", escape_html(id))).unwrap_or_default();
+    let number = number
+        .map(|n| format!("<span class=\"figure-number\">Figure {n}:</span> "))
+        .unwrap_or_default();
+    format!("<figure{id}>{image}<figcaption>{number}{}</figcaption></figure>", escape_html(caption))
+}
+
+/// Numbers a note's table captions and resolves its `@fig:`/`@tbl:` cross-references
+/// to links reading "Figure 2" or "Table 1". Figures are numbered in the order of
+/// their embeds, matching the order `rewrite_links` renders them in.
+pub fn number_figures(body: &str, source: &Path) -> String {
+    // Keyed by the reference, e.g. `fig:overview`
+    let mut labels: HashMap<String, usize> = HashMap::new();
+    let figures = find_wikilinks(body).filter_map(|(_, link)| {
+        let alias = link.alias.filter(|_| link.embed)?;
+        figure_alias(&alias).map(|(label, _)| label.map(str::to_string))
+    });
+    for (i, label) in figures.enumerate() {
+        if let Some(label) = label {
+            labels.insert(format!("fig:{label}"), i + 1);
+        }
+    }
+
+    let mut tables = 0;
+    let mut lines = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+            lines.push(line.to_string());
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            lines.push(line.to_string());
+            continue;
+        }
+        match TABLE_CAPTION.captures(line) {
+            Some(caps) => {
+                tables += 1;
+                let id = match caps.get(2) {
+                    Some(label) => {
+                        labels.insert(format!("tbl:{}", label.as_str()), tables);
+                        format!(" id=\"tbl-{}\"", label.as_str())
+                    }
+                    None => String::new(),
+                };
+                lines.push(format!(
+                    "<p class=\"table-caption\"{id}><span class=\"table-number\">Table {tables}:</span> {}</p>",
+                    escape_html(&caps[1])
+                ));
+            }
+            None => lines.push(line.to_string()),
+        }
+    }
+    let body = lines.join("\n");
+
+    CROSS_REFERENCE
+        .replace_all(&body, |caps: &Captures| {
+            let (kind, label) = (&caps[1], &caps[2]);
+            match labels.get(&caps[0][1..]) {
+                Some(n) => {
+                    let name = if kind == "fig" { "Figure" } else { "Table" };
+                    format!("[{name} {n}](#{kind}-{label})")
+                }
+                None => {
+                    broken_link!(file: source, near: &caps[0]; "no figure or table labelled {} in {}", &caps[0], source.display());
+                    caps[0].to_string()
+                }
+            }
+        })
+        .into_owned()
+}
+
+/// Turns an image embed alone on its line, followed directly by an italic line,
+/// into a captioned figure. The caption doubles as the image's alt text unless the
+/// embed has its own; numbered figures and `caption:` embeds are left as they are.
+pub fn caption_images(body: &str, is_image: impl Fn(&Path) -> bool) -> String {
+    let lines: Vec<&str> = body.lines().collect();
+    let mut out = Vec::with_capacity(lines.len());
+    let mut fence: Option<&str> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+            out.push(line.to_string());
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            out.push(line.to_string());
+            continue;
+        }
+        let caption = lines.get(i).and_then(|next| ITALIC_LINE.captures(next));
+        match (lone_image_embed(line, &is_image), caption) {
+            (Some(link), Some(caps)) => {
+                let caption = caps.get(1).or(caps.get(2)).map_or("", |m| m.as_str());
+                let embed = match &link.alias {
+                    Some(_) => line.trim().to_string(),
+                    None => format!("![[{}|{caption}]]", link.target),
+                };
+                // A blank line ends the HTML block, so Markdown after the figure still renders
+                out.push(figure_html(&embed, None, None, caption));
+                out.push(String::new());
+                i += 1;
+            }
+            _ => out.push(line.to_string()),
+        }
+    }
+    out.join("\n")
+}
+
+/// The embed on a line that holds nothing but one image embed without a figure or caption alias.
+fn lone_image_embed(line: &str, is_image: impl Fn(&Path) -> bool) -> Option<WikiLink> {
+    let mut links = find_wikilinks(line);
+    let (range, link) = links.next()?;
+    let alone = links.next().is_none() && line[..range.start].trim().is_empty() && line[range.end..].trim().is_empty();
+    let special = link.alias.as_deref().is_some_and(|alias| figure_alias(alias).is_some() || caption_alias(alias).is_some());
+    (alone && link.embed && link.heading.is_none() && !special && is_image(Path::new(&link.target))).then_some(link)
+}
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use similar::TextDiff;
+use walkdir::WalkDir;
+use crate::build_site_with;
+use crate::config::load_config;
+use crate::diagnostics::{info, set_vault, warning};
+use crate::vfs::{LocalVault, MemoryOutput};
+
+/// In a fixture folder, the vault to build and the snapshot of the site it should build into.
+const VAULT_DIR: &str = "vault";
+const EXPECTED_DIR: &str = "expected";
+/// Responses the fixture's embeds and link cards are built from, as fixtures never fetch
+const CACHE_DIR: &str = "cache";
+
+/// Builds every fixture in `fixtures_dir` (a folder holding a `vault/`, with its
+/// `obs2web.toml` if any, and a `cache/` of fetched responses if it needs them)
+/// offline with the current theme and compares the output with
+/// the fixture's `expected/` snapshot, file by file. With `update`, the snapshots
+/// are rewritten from the output instead.
+pub fn run_fixtures(fixtures_dir: &Path, update: bool) -> io::Result<()> {
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(fixtures_dir)
+        .map_err(|e| io::Error::other(format!("Cannot read fixtures in {}: {e}", fixtures_dir.display())))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.join(VAULT_DIR).is_dir())
+        .collect();
+    if fixtures.is_empty() {
+        return Err(io::Error::other(format!(
+            "No fixtures in {}: each fixture is a folder with a {VAULT_DIR}/ inside",
+            fixtures_dir.display()
+        )));
+    }
+    fixtures.sort();
+
+    let mut failed = 0;
+    for fixture in &fixtures {
+        let site = build_fixture(fixture)?;
+        let expected_dir = fixture.join(EXPECTED_DIR);
+        if update {
+            write_snapshot(&expected_dir, &site)?;
+            info!("Updated snapshot {}", expected_dir.display());
+            continue;
+        }
+        let differences = compare(&expected_dir, &site)?;
+        if differences.is_empty() {
+            info!("Fixture {} matches its snapshot", fixture.display());
+        } else {
+            failed += 1;
+            for difference in differences {
+                warning!("fixture {}: {difference}", fixture.display());
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(io::Error::other(format!(
+            "{failed} of {} fixtures differ from their snapshots; rerun with --update to accept the new output",
+            fixtures.len()
+        )));
+    }
+    Ok(())
+}
+
+fn build_fixture(fixture: &Path) -> io::Result<BTreeMap<PathBuf, Vec<u8>>> {
+    let vault_path = fixture.join(VAULT_DIR);
+    let mut config = load_config(None, &vault_path)?;
+    config.cache_dir = fixture.join(CACHE_DIR);
+    config.offline = true;
+    set_vault(&vault_path);
+    let output = MemoryOutput::new();
+    build_site_with(&LocalVault::new(&vault_path), &output, &config)?;
+    Ok(output.into_files())
+}
+
+/// Describes every file that was added, removed, or changed against the snapshot.
+fn compare(expected_dir: &Path, site: &BTreeMap<PathBuf, Vec<u8>>) -> io::Result<Vec<String>> {
+    let mut differences = Vec::new();
+    let expected = snapshot_files(expected_dir)?;
+    for (path, contents) in site {
+        match fs::read(expected_dir.join(path)) {
+            Ok(old) if old == *contents => {}
+            Ok(old) => differences.push(format!("{} changed\n{}", path.display(), diff(&old, contents))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                differences.push(format!("{} is new", path.display()));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    for path in expected.iter().filter(|path| !site.contains_key(*path)) {
+        differences.push(format!("{} is no longer written", path.display()));
+    }
+    Ok(differences)
+}
+
+fn diff(old: &[u8], new: &[u8]) -> String {
+    match (std::str::from_utf8(old), std::str::from_utf8(new)) {
+        (Ok(old), Ok(new)) => TextDiff::from_lines(old, new)
+            .unified_diff()
+            .context_radius(2)
+            .header("expected", "built")
+            .to_string(),
+        _ => format!("binary content differs ({} bytes expected, {} built)", old.len(), new.len()),
+    }
+}
+
+/// Snapshot-relative paths of the files in a snapshot; none when it doesn't exist yet.
+fn snapshot_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry.map_err(io::Error::other)?;
+        if entry.file_type().is_file() {
+            files.push(entry.path().strip_prefix(dir).map_err(io::Error::other)?.to_path_buf());
+        }
+    }
+    Ok(files)
+}
+
+fn write_snapshot(dir: &Path, site: &BTreeMap<PathBuf, Vec<u8>>) -> io::Result<()> {
+    if dir.exists() {
+        fs::remove_dir_all(dir)?;
+    }
+    for (path, contents) in site {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use std::io;
+use std::path::Path;
+use std::sync::LazyLock;
+use comrak::{markdown_to_html, ComrakOptions};
+use regex::Regex;
+use tera::Tera;
+use crate::config::Config;
+use crate::content::{escape_html, page_context};
+use crate::diagnostics::info;
+use crate::index::{IndexedNote, SiteIndex};
+use crate::template::theme_file;
+use crate::vfs::OutputSink;
+
+/// File the deck is written to, in the tab-separated format Anki imports.
+pub const DECK_FILE: &str = "flashcards.tsv";
+
+/// A question and its answer, as the Spaced Repetition plugin reads them.
+struct Flashcard<'a> {
+    front: String,
+    back: String,
+    note: &'a IndexedNote,
+}
+
+/// Writes the flashcards of the notes tagged `flashcard_tag` as `flashcards.tsv`,
+/// a deck Anki imports with File > Import, and as `flashcards.html`, a page to
+/// review them one at a time in the browser. Cards are written as the Spaced
+/// Repetition plugin reads them: `Question::Answer` on one line, `Question:::Answer`
+/// for a card in both directions, and a question and answer on lines around a
+/// lone `?` (`??` for both directions) within a paragraph.
+pub fn write_flashcards(
+    notes: &[IndexedNote],
+    index: &SiteIndex,
+    tera: &Tera,
+    comrak_options: &ComrakOptions,
+    output: &dyn OutputSink,
+    config: &Config,
+) -> io::Result<()> {
+    let cards: Vec<Flashcard> = notes
+        .iter()
+        .filter(|note| has_tag(note, &config.flashcard_tag))
+        .flat_map(|note| {
+            note_cards(&note.body).into_iter().map(move |(front, back)| Flashcard {
+                front: unwrap_paragraph(markdown_to_html(&front, comrak_options)),
+                back: markdown_to_html(&back, comrak_options),
+                note,
+            })
+        })
+        .collect();
+
+    let deck = config.site_name.as_deref().unwrap_or("obs2web");
+    let mut tsv = format!("#separator:tab\n#html:true\n#deck:{deck}\n#tags column:3\n");
+    for card in &cards {
+        let tags: Vec<String> = card
+            .note
+            .frontmatter
+            .tags
+            .iter()
+            .flatten()
+            .map(|tag| tag.replace(' ', "_").replace('/', "::"))
+            .collect();
+        tsv.push_str(&format!("{}\t{}\t{}\n", tsv_field(&card.front), tsv_field(&card.back), tsv_field(&tags.join(" "))));
+    }
+    output.write(Path::new(DECK_FILE), tsv.as_bytes())?;
+
+    let page = Path::new("flashcards.html");
+    let mut context = page_context(page, index, config);
+    context.insert("title", "Flashcards");
+    context.insert("content", &review_html(&cards));
+    let rendered = tera
+        .render("base.html", &context)
+        .map_err(|e| io::Error::other(format!("Template rendering failed for flashcards.html: {e}")))?;
+    output.write(page, rendered.as_bytes())?;
+    output.write(Path::new("flashcards.js"), theme_file("flashcards.js")?.as_bytes())?;
+    info!("Wrote {} flashcards", cards.len());
+    Ok(())
+}
+
+/// Whether the note has `tag`, or a tag nested under it, in its frontmatter or written as `#tag` in its body.
+fn has_tag(note: &IndexedNote, tag: &str) -> bool {
+    let nested = format!("{tag}/");
+    let is_tag = |t: &str| {
+        let t = t.trim_start_matches('#');
+        t == tag || t.starts_with(&nested)
+    };
+    note.frontmatter.tags.iter().flatten().any(|t| is_tag(t))
+        || INLINE_TAG.captures_iter(&note.body).any(|caps| is_tag(&caps[1]))
+}
+
+static INLINE_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?:^|\s)#([\w/-]+)").unwrap());
+
+static CARD_SEPARATOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(.+?)\s*(:::?)\s*(.+)$").unwrap());
+
+/// The front and back of every card of a note body, reversed cards twice. Fenced code is skipped.
+fn note_cards(body: &str) -> Vec<(String, String)> {
+    let mut cards = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut fence: Option<&str> = None;
+    for line in body.lines().chain([""]) {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            paragraph_cards(&paragraph, &mut cards);
+            paragraph.clear();
+        } else if trimmed.is_empty() {
+            paragraph_cards(&paragraph, &mut cards);
+            paragraph.clear();
+        } else {
+            paragraph.push(line);
+        }
+    }
+    cards
+}
+
+fn paragraph_cards(lines: &[&str], cards: &mut Vec<(String, String)>) {
+    let mut push = |front: String, back: String, reversed: bool| {
+        if reversed {
+            cards.push((back.clone(), front.clone()));
+        }
+        cards.push((front, back));
+    };
+    if let Some(split) = lines.iter().position(|line| matches!(line.trim(), "?" | "??")) {
+        let front = lines[..split].join("\n");
+        let back = lines[split + 1..].join("\n");
+        if !front.trim().is_empty() && !back.trim().is_empty() {
+            let reversed = lines[split].trim() == "??";
+            push(front, back, reversed);
+        }
+        return;
+    }
+    for line in lines {
+        if let Some(caps) = CARD_SEPARATOR.captures(line.trim()) {
+            let reversed = &caps[2] == ":::";
+            push(caps[1].to_string(), caps[3].to_string(), reversed);
+        }
+    }
+}
+
+/// A question rendered as a single paragraph, without the `<p>` that can't go in a `<summary>`.
+fn unwrap_paragraph(html: String) -> String {
+    match html.trim_end().strip_prefix("<p>").and_then(|inner| inner.strip_suffix("</p>")) {
+        Some(inner) if !inner.contains("<p>") => inner.to_string(),
+        _ => html,
+    }
+}
+
+/// A field of the deck, quoted so it may hold tabs, newlines, and quotes.
+fn tsv_field(value: &str) -> String {
+    format!("\"{}\"", value.trim().replace('"', "\"\""))
+}
+
+/// The cards as a list flashcards.js steps through, each front a `<details>`
+/// summary that reveals the back, and each card linked to its note.
+fn review_html(cards: &[Flashcard]) -> String {
+    let mut html = String::from(concat!(
+        "<div class=\"flashcards\">\n",
+        "<p class=\"flashcard-controls\">",
+        "<button type=\"button\" data-action=\"previous\">Previous</button> ",
+        "<button type=\"button\" data-action=\"next\">Next</button> ",
+        "<button type=\"button\" data-action=\"shuffle\">Shuffle</button> ",
+    ));
+    html.push_str(&format!("<a href=\"{DECK_FILE}\" download>Download for Anki</a></p>\n<ol>\n"));
+    for card in cards {
+        html.push_str(&format!(
+            "<li class=\"flashcard\"><details>\n<summary>{}</summary>\n<div class=\"flashcard-back\">{}<p class=\"flashcard-source\"><a href=\"{}\">{}</a></p></div>\n</details></li>\n",
+            card.front,
+            card.back,
+            escape_html(&card.note.url),
+            escape_html(&card.note.title)
+        ));
+    }
+    html.push_str("</ol>\n</div>\n");
+    html
+}
@@ -1,21 +1,268 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use serde::Deserialize;
+pub use crate::cli::AssetMode;
+use crate::config::{Config, CONFIG_FILE};
+use crate::diagnostics::{config_error, info, warning};
+use crate::vfs::{OutputSink, VaultSource};
 
 pub fn prepare_output_dir(output_dir: &Path) -> std::io::Result<()> {
     // Remove old output and recreate
     if output_dir.exists() {
-        println!("Cleaning output directory: {}", output_dir.display());
-        fs::remove_dir_all(&output_dir)?;
+        info!("Cleaning output directory: {}", output_dir.display());
+        fs::remove_dir_all(output_dir)?;
     }
-    fs::create_dir_all(&output_dir)?;
+    fs::create_dir_all(output_dir)?;
     Ok(())
 }
 
-pub fn process_asset(path: &Path, output_path: &Path) -> std::io::Result<()> {
+/// How a finished build replaces the previous one in the output folder; the `output_swap` setting.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputSwap {
+    /// Empty the output folder and write the build into it
+    #[default]
+    None,
+    /// Build into a folder next to the output and rename it into place once the build succeeds
+    Rename,
+    /// Build into a new folder of `.<name>.builds/` next to the output and flip the output,
+    /// a symbolic link, to it once the build succeeds
+    Symlink,
+}
+
+/// Runs `build` on the folder the site is written to. With `swap`, that is a new
+/// folder next to `output_dir` that replaces it only when the build succeeds, so a
+/// failed build never leaves a served site half-written. Renaming leaves a moment
+/// without the output between moving the old build aside and the new one in; a
+/// symlink is flipped in one step where the platform renames atomically, as Unix does.
+pub fn build_with_swap(
+    output_dir: &Path,
+    swap: OutputSwap,
+    build: impl FnOnce(&Path) -> std::io::Result<()>,
+) -> std::io::Result<()> {
+    if swap == OutputSwap::None {
+        return build(output_dir);
+    }
+    let parent = output_dir.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = output_dir
+        .file_name()
+        .ok_or_else(|| config_error(format!("Cannot swap in the output {}: it has no folder name", output_dir.display())))?
+        .to_string_lossy()
+        .into_owned();
+    fs::create_dir_all(parent)?;
+    let builds = match swap {
+        OutputSwap::Symlink => parent.join(format!(".{name}.builds")),
+        _ => parent.to_path_buf(),
+    };
+    fs::create_dir_all(&builds)?;
+    // Dropped, and so removed, when the build fails
+    let staging = tempfile::Builder::new().prefix(&format!(".{name}.")).tempdir_in(&builds)?;
+    build(staging.path())?;
+    let staging = staging.keep();
+    // Temporary folders are only readable by their owner, which a web server may not be
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staging, fs::Permissions::from_mode(0o755))?;
+    }
+
+    let previous = parent.join(format!(".{name}.previous"));
+    remove_path(&previous)?;
+    if swap == OutputSwap::Rename {
+        if output_dir.symlink_metadata().is_ok() {
+            fs::rename(output_dir, &previous)?;
+        }
+        fs::rename(&staging, output_dir)?;
+        info!("Swapped the new build into {}", output_dir.display());
+        return remove_path(&previous);
+    }
+
+    // The link points into the builds folder relative to where it is
+    let target = Path::new(&format!(".{name}.builds")).join(staging.file_name().unwrap_or_default());
+    let link = parent.join(format!(".{name}.link"));
+    remove_path(&link)?;
+    symlink_dir(&target, &link)?;
+    // An output folder of an earlier build is moved aside once; a link is replaced in place
+    if output_dir.symlink_metadata().is_ok_and(|meta| !meta.file_type().is_symlink()) {
+        fs::rename(output_dir, &previous)?;
+    }
+    fs::rename(&link, output_dir)?;
+    info!("Pointed {} at the new build", output_dir.display());
+    remove_path(&previous)?;
+    for entry in fs::read_dir(&builds)? {
+        let path = entry?.path();
+        if path != staging {
+            remove_path(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes a file, symbolic link, or folder with everything in it, if there is one.
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    match path.symlink_metadata() {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(_) => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn symlink_dir(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink_dir(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink_dir(_target: &Path, _link: &Path) -> std::io::Result<()> {
+    Err(config_error("output_swap = \"symlink\" needs a platform with symbolic links".to_string()))
+}
+
+/// Lists the publishable files of the vault, reporting entries that can't be read.
+pub fn vault_files(vault_path: &Path, config: &Config) -> Vec<PathBuf> {
+    // Hidden entries (.obsidian, .git, ...) are pruned together with everything below them, as are
+    // paths matched by .obs2webignore and, unless disabled, .gitignore files. When following links
+    // the walker detects symlink loops and reports them as errors.
+    let entries = WalkBuilder::new(vault_path)
+        .follow_links(config.follow_symlinks)
+        .hidden(true)
+        .git_ignore(config.respect_gitignore)
+        .git_exclude(config.respect_gitignore)
+        .git_global(false)
+        .require_git(false)
+        .add_custom_ignore_filename(".obs2webignore")
+        .build();
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warning!("skipping vault entry: {e}");
+                continue;
+            }
+        };
+        let path = entry.path();
+        if entry.path_is_symlink() && !path.exists() {
+            warning!("skipping broken symlink: {}", path.display());
+            continue;
+        }
+        if path.is_dir() {
+            if entry.path_is_symlink() && !config.follow_symlinks {
+                warning!(
+                    "skipping symlinked directory {} (use --follow-symlinks to include it)",
+                    path.display()
+                );
+            }
+            continue;
+        }
+        // The site config lives in the vault but isn't part of the site
+        if entry.depth() == 1 && entry.file_name() == CONFIG_FILE {
+            continue;
+        }
+        files.push(path.to_path_buf());
+    }
+    files
+}
+
+/// Matches vault-relative paths against `--only` globs; `None` when every note is built.
+pub fn only_filter(patterns: &[String]) -> std::io::Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        // `*` stays within a folder, `**` crosses folders
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| config_error(format!("Invalid --only pattern {pattern}: {e}")))?;
+        builder.add(glob);
+    }
+    builder.build().map(Some).map_err(|e| config_error(e.to_string()))
+}
+
+pub fn process_asset(path: &Path, output_path: &Path, mode: AssetMode) -> std::io::Result<()> {
     if let Some(parent) = output_path.parent() {
         fs::create_dir_all(parent)?;
     }
-    println!("Copying asset: {} -> {}", path.display(), output_path.display());
-    fs::copy(path, output_path)?;
+    unlink_output(output_path)?;
+    info!("Copying asset: {} -> {}", path.display(), output_path.display());
+    // Link the symlink's target rather than the link itself, which may be relative to the vault
+    let resolved;
+    let path = if path.is_symlink() {
+        resolved = fs::canonicalize(path)?;
+        resolved.as_path()
+    } else {
+        path
+    };
+    match mode {
+        AssetMode::Auto => {
+            reflink_copy::reflink_or_copy(path, output_path)?;
+        }
+        // Linking fails across filesystems (or on filesystems without links), so fall back to a copy
+        AssetMode::Hardlink => {
+            if fs::hard_link(path, output_path).is_err() {
+                fs::copy(path, output_path)?;
+            }
+        }
+        AssetMode::Copy => {
+            fs::copy(path, output_path)?;
+        }
+    }
     Ok(())
 }
+
+/// Removes the output file at `path`, if there is one, so that what is written there
+/// next goes to a new file. An attachment placed with `asset_mode = "hardlink"` is
+/// the vault's own file under another name, and writing it in place would change
+/// the vault.
+pub fn unlink_output(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Places all vault attachments at the same path in the output concurrently, returning the first error.
+pub fn process_assets(
+    vault: &dyn VaultSource,
+    output: &dyn OutputSink,
+    assets: &[PathBuf],
+    mode: AssetMode,
+) -> std::io::Result<()> {
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(assets.len());
+    // Without parallelism (or threads at all, as in WebAssembly) there is nothing to spawn
+    if workers <= 1 {
+        return assets.iter().try_for_each(|path| output.copy_asset(vault, path, path, mode));
+    }
+    let next = AtomicUsize::new(0);
+    let first_error = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Some(path) = assets.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if let Err(e) = output.copy_asset(vault, path, path, mode) {
+                        first_error.lock().unwrap().get_or_insert(e);
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    match first_error.into_inner().unwrap() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
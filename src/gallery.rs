@@ -0,0 +1,136 @@
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use comrak::Anchorizer;
+use tera::Tera;
+use crate::config::Config;
+use crate::content::{escape_html, is_image, make_comrak_options, page_context, split_frontmatter};
+use crate::diagnostics::{info, warning};
+use crate::domain::Frontmatter;
+use crate::icons::thumbnail;
+use crate::index::{url_for, SiteIndex};
+use crate::markdown::markdown_to_html;
+use crate::vfs::{OutputSink, VaultSource};
+
+/// A note that makes its folder a gallery; its title and text head the gallery page.
+pub const GALLERY_FILE: &str = "_gallery.md";
+
+/// Folder the thumbnails are written to, mirroring the vault's folders.
+const THUMBNAIL_DIR: &str = "thumbnails";
+
+pub fn is_gallery_file(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == GALLERY_FILE)
+}
+
+/// Writes a `gallery.html` for every folder listed in `galleries` or holding a
+/// `_gallery.md`: a grid of thumbnails of the images directly in the folder, each
+/// opening the image in a lightbox, done with CSS `:target` alone. PNG and SVG
+/// images get PNG thumbnails of at most `gallery_thumbnail_size` pixels; other
+/// formats are shown as they are.
+pub fn write_galleries(
+    vault: &dyn VaultSource,
+    files: &[PathBuf],
+    assets: &[PathBuf],
+    index: &SiteIndex,
+    tera: &Tera,
+    output: &dyn OutputSink,
+    config: &Config,
+) -> io::Result<()> {
+    let folders: BTreeSet<&Path> = config
+        .galleries
+        .iter()
+        .map(PathBuf::as_path)
+        .chain(files.iter().filter(|file| is_gallery_file(file)).map(|file| file.parent().unwrap_or(Path::new(""))))
+        .collect();
+    for folder in folders {
+        let images: Vec<&PathBuf> =
+            assets.iter().filter(|asset| asset.parent() == Some(folder) && is_image(asset)).collect();
+        if images.is_empty() {
+            warning!(file: folder; "The gallery has no images");
+        }
+        let page = config.url_style.output(&folder.join("gallery.html"));
+        let (title, intro) = gallery_intro(vault, folder, config)?;
+        let mut html = format!("{intro}<ul class=\"gallery\" id=\"gallery\">\n");
+        let mut lightboxes = String::new();
+        for (i, image) in images.iter().enumerate() {
+            let src = match gallery_thumbnail(vault, image, output, config) {
+                Some(thumbnail) => url_for(&thumbnail),
+                None => url_for(image),
+            };
+            let name = escape_html(&image.file_stem().unwrap_or_default().to_string_lossy());
+            html.push_str(&format!(
+                "<li><a href=\"#image-{}\"><img src=\"{}\" alt=\"{name}\" loading=\"lazy\"></a></li>\n",
+                i + 1,
+                escape_html(&src)
+            ));
+            // Each image's lightbox is shown while its anchor is the page's target, so it needs no script
+            let previous = if i == 0 { images.len() } else { i };
+            let next = if i + 1 == images.len() { 1 } else { i + 2 };
+            lightboxes.push_str(&format!(
+                concat!(
+                    "<div class=\"lightbox\" id=\"image-{}\" role=\"dialog\" aria-label=\"{name}\">\n",
+                    "<a class=\"lightbox-close\" href=\"#gallery\" aria-label=\"Close\"></a>\n",
+                    "<img src=\"{}\" alt=\"{name}\" loading=\"lazy\">\n",
+                    "<a class=\"lightbox-previous\" href=\"#image-{}\" aria-label=\"Previous image\">&lsaquo;</a>\n",
+                    "<a class=\"lightbox-next\" href=\"#image-{}\" aria-label=\"Next image\">&rsaquo;</a>\n",
+                    "</div>\n"
+                ),
+                i + 1,
+                escape_html(&url_for(image)),
+                previous,
+                next,
+                name = name
+            ));
+        }
+        html.push_str("</ul>\n");
+        html.push_str(&lightboxes);
+
+        let mut context = page_context(&page, index, config);
+        context.insert("title", &title);
+        context.insert("content", &html);
+        let rendered = tera
+            .render("base.html", &context)
+            .map_err(|e| io::Error::other(format!("Template rendering failed for {}: {e}", page.display())))?;
+        output.write(&page, rendered.as_bytes())?;
+        info!("Wrote the gallery of {} images in {}", images.len(), folder.display());
+    }
+    Ok(())
+}
+
+/// The gallery's title and the HTML of the text of its `_gallery.md`, when it has one.
+fn gallery_intro(vault: &dyn VaultSource, folder: &Path, config: &Config) -> io::Result<(String, String)> {
+    let folder_name =
+        folder.file_name().map_or_else(|| "Gallery".to_string(), |name| name.to_string_lossy().into_owned());
+    let source = folder.join(GALLERY_FILE);
+    let Ok(text) = vault.read_to_string(&source) else {
+        return Ok((folder_name, String::new()));
+    };
+    let (data, body) = split_frontmatter(&text);
+    let frontmatter = match data {
+        Some(data) => data
+            .deserialize::<Frontmatter>()
+            .map_err(|e| io::Error::other(format!("Frontmatter deserialize error in {}: {e}", source.display())))?,
+        None => Frontmatter::default(),
+    };
+    let intro = markdown_to_html(&body, &make_comrak_options(config), config, &source, Anchorizer::new());
+    Ok((frontmatter.title.unwrap_or(folder_name), intro))
+}
+
+/// Writes the thumbnail of `image` and returns its output path, or none when the
+/// image is shown as it is.
+fn gallery_thumbnail(vault: &dyn VaultSource, image: &Path, output: &dyn OutputSink, config: &Config) -> Option<PathBuf> {
+    let written = vault
+        .read(image)
+        .and_then(|data| thumbnail(image, &data, config.gallery_thumbnail_size))
+        .and_then(|png| match png {
+            Some(png) => {
+                let path = Path::new(THUMBNAIL_DIR).join(image).with_extension("png");
+                output.write(&path, &png).map(|()| Some(path))
+            }
+            None => Ok(None),
+        });
+    written.unwrap_or_else(|e| {
+        warning!(file: image; "Cannot make a thumbnail, showing the image itself: {e}");
+        None
+    })
+}
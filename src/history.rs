@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use serde::Serialize;
+
+/// One commit that touched a note.
+#[derive(Debug, Serialize, Clone)]
+pub struct Revision {
+    pub hash: String,
+    pub short_hash: String,
+    pub date: String,
+    pub message: String,
+    /// Link to the commit's diff on the repository host, when configured
+    pub diff_url: Option<String>,
+}
+
+/// Lists the commits that changed `source` (vault-relative), newest first,
+/// following renames. Returns nothing when the vault isn't a git checkout.
+pub fn note_history(vault_path: &Path, source: &Path, repo_url: Option<&str>) -> Vec<Revision> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(vault_path)
+        .args(["log", "--follow", "--format=%H%x1f%h%x1f%as%x1f%s", "--"])
+        .arg(source)
+        .output();
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        _ => return Vec::new(),
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\u{1f}');
+            let hash = fields.next()?.to_string();
+            let short_hash = fields.next()?.to_string();
+            let date = fields.next()?.to_string();
+            let message = fields.next().unwrap_or_default().to_string();
+            let diff_url = repo_url.map(|url| format!("{}/commit/{hash}", url.trim_end_matches('/')));
+            Some(Revision { hash, short_hash, date, message, diff_url })
+        })
+        .collect()
+}
+
+/// When each file of the vault was last committed (RFC 3339), keyed by its
+/// vault-relative path, from a single walk over the log. Returns nothing when
+/// the vault isn't a git checkout.
+pub fn last_commit_dates(vault_path: &Path) -> HashMap<PathBuf, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(vault_path)
+        .args(["-c", "core.quotePath=false", "log", "--format=%x1e%cI", "--name-only", "--relative", "--", "."])
+        .output();
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        _ => return HashMap::new(),
+    };
+
+    let mut dates = HashMap::new();
+    for commit in String::from_utf8_lossy(&output.stdout).split('\u{1e}') {
+        let mut lines = commit.lines();
+        let Some(date) = lines.next() else { continue };
+        // Newest first, so the first commit listing a file is its last change
+        for file in lines.filter(|line| !line.is_empty()) {
+            dates.entry(PathBuf::from(file)).or_insert_with(|| date.to_string());
+        }
+    }
+    dates
+}
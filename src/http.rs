@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use crate::config::Config;
+
+/// When the last request to each host was made, shared by every client so the
+/// interval between requests holds across notes and builds.
+static LAST_REQUEST: LazyLock<Mutex<HashMap<String, Instant>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A response as it is kept in the cache.
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    url: String,
+    /// Seconds since the Unix epoch
+    fetched: u64,
+    body: String,
+}
+
+/// Makes every request of a build: GETs whose responses are kept in
+/// `cache_dir/http/` and served from there on later builds, so content fetched
+/// once stays the same until the cache is cleared. Requests to the same host are
+/// spaced out by the configured interval; offline, only the cache is used.
+pub struct HttpClient {
+    cache_dir: PathBuf,
+    offline: bool,
+    timeout: Duration,
+    interval: Duration,
+}
+
+impl HttpClient {
+    pub fn new(config: &Config) -> HttpClient {
+        HttpClient {
+            cache_dir: config.cache_dir.join("http"),
+            offline: config.offline,
+            timeout: Duration::from_secs(config.http_timeout),
+            interval: Duration::from_millis(config.http_request_interval_ms),
+        }
+    }
+
+    /// The body of the response to `url` with `query` appended, from the cache when it's there.
+    pub fn get(&self, url: &str, query: &[(&str, &str)], accept: &str) -> io::Result<String> {
+        let url = with_query(url, query);
+        let cached = self.cache_dir.join(format!("{}.json", cache_key(&url)));
+        if let Ok(json) = fs::read_to_string(&cached)
+            && let Ok(response) = serde_json::from_str::<CachedResponse>(&json)
+        {
+            return Ok(response.body);
+        }
+        if self.offline {
+            return Err(io::Error::other(format!("{url} is not in the cache and fetching is off (offline)")));
+        }
+        self.wait_for_host(&url);
+        let body = fetch(&url, accept, self.timeout)?;
+        let fetched = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let response = CachedResponse { url, fetched, body };
+        fs::create_dir_all(&self.cache_dir)?;
+        fs::write(&cached, serde_json::to_string_pretty(&response).map_err(io::Error::other)?)?;
+        Ok(response.body)
+    }
+
+    pub fn get_json(&self, url: &str, query: &[(&str, &str)]) -> io::Result<Value> {
+        let body = self.get(url, query, "application/json")?;
+        serde_json::from_str(&body).map_err(|e| io::Error::other(format!("Unexpected response from {url}: {e}")))
+    }
+
+    /// Requests `url` bypassing the cache and drops the response, for pings and
+    /// cache warming; spaced out like every other request.
+    pub fn ping(&self, url: &str) -> io::Result<()> {
+        if self.offline {
+            return Err(io::Error::other(format!("not requesting {url}: fetching is off (offline)")));
+        }
+        self.wait_for_host(url);
+        fetch(url, "*/*", self.timeout).map(drop)
+    }
+
+    /// The body of the response to `url` as bytes, bypassing the cache, to compare
+    /// a published file with the build.
+    pub fn get_bytes(&self, url: &str) -> io::Result<Vec<u8>> {
+        if self.offline {
+            return Err(io::Error::other(format!("not requesting {url}: fetching is off (offline)")));
+        }
+        self.wait_for_host(url);
+        fetch_bytes(url, self.timeout)
+    }
+
+    fn wait_for_host(&self, url: &str) {
+        let host = url.split("://").nth(1).and_then(|rest| rest.split(['/', '?', '#']).next()).unwrap_or(url);
+        let mut last_request = LAST_REQUEST.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(last) = last_request.get(host) {
+            let next = *last + self.interval;
+            let now = Instant::now();
+            if next > now {
+                std::thread::sleep(next - now);
+            }
+        }
+        last_request.insert(host.to_string(), Instant::now());
+    }
+}
+
+fn with_query(url: &str, query: &[(&str, &str)]) -> String {
+    let mut url = url.to_string();
+    for (i, (name, value)) in query.iter().enumerate() {
+        let separator = if i == 0 && !url.contains('?') { '?' } else { '&' };
+        url.push_str(&format!("{separator}{}={}", percent_encode(name), percent_encode(value)));
+    }
+    url
+}
+
+pub(crate) fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+fn cache_key(url: &str) -> String {
+    Sha256::digest(url.as_bytes()).iter().take(16).map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(feature = "network")]
+fn fetch(url: &str, accept: &str, timeout: Duration) -> io::Result<String> {
+    ureq::get(url)
+        .timeout(timeout)
+        .set("Accept", accept)
+        .call()
+        .map_err(|e| io::Error::other(format!("Fetching failed: {e}")))?
+        .into_string()
+}
+
+#[cfg(feature = "network")]
+fn fetch_bytes(url: &str, timeout: Duration) -> io::Result<Vec<u8>> {
+    use std::io::Read;
+    let response = ureq::get(url).timeout(timeout).call().map_err(|e| io::Error::other(format!("Fetching failed: {e}")))?;
+    let mut body = Vec::new();
+    response.into_reader().read_to_end(&mut body)?;
+    Ok(body)
+}
+
+#[cfg(not(feature = "network"))]
+fn fetch_bytes(url: &str, _timeout: Duration) -> io::Result<Vec<u8>> {
+    Err(io::Error::other(format!("Cannot fetch {url}: obs2web was built without the network feature")))
+}
+
+#[cfg(not(feature = "network"))]
+fn fetch(url: &str, _accept: &str, _timeout: Duration) -> io::Result<String> {
+    Err(io::Error::other(format!("Cannot fetch {url}: obs2web was built without the network feature")))
+}
@@ -0,0 +1,158 @@
+use std::io;
+use std::path::Path;
+use resvg::{tiny_skia, usvg};
+use serde_json::json;
+use crate::config::Config;
+use crate::diagnostics::info;
+use crate::vfs::{OutputSink, VaultSource};
+
+/// Sizes packed into favicon.ico.
+const ICO_SIZES: [u32; 3] = [16, 32, 48];
+/// PNG icons written next to it, as (file name, size).
+const PNG_ICONS: [(&str, u32); 5] = [
+    ("favicon-16x16.png", 16),
+    ("favicon-32x32.png", 32),
+    ("apple-touch-icon.png", 180),
+    ("icon-192.png", 192),
+    ("icon-512.png", 512),
+];
+
+/// Generates favicon.ico, PNG icons in the common sizes, and an apple-touch-icon
+/// from a single source icon (PNG or SVG).
+pub fn write_site_icons(vault: &dyn VaultSource, source: &Path, output: &dyn OutputSink) -> io::Result<()> {
+    info!("Generating site icons from {}", source.display());
+    let data = vault.read(source).map_err(|e| io::Error::other(format!("Cannot read icon {}: {e}", source.display())))?;
+    let icon = SourceIcon::load(source, &data)?;
+
+    let mut ico_images = Vec::new();
+    for size in ICO_SIZES {
+        ico_images.push((size, icon.render(size)?));
+    }
+    output.write(Path::new("favicon.ico"), &ico(&ico_images))?;
+    for (name, size) in PNG_ICONS {
+        output.write(Path::new(name), &icon.render(size)?)?;
+    }
+    Ok(())
+}
+
+/// Writes site.webmanifest, which makes the site installable; it lists the
+/// generated icons when a source icon is configured.
+pub fn write_web_manifest(output: &dyn OutputSink, config: &Config) -> io::Result<()> {
+    let name = config.site_name.as_deref().unwrap_or("Notes");
+    let mut manifest = json!({
+        "name": name,
+        "short_name": name,
+        "start_url": ".",
+        "display": "standalone",
+        "background_color": "#0D1012",
+        "theme_color": "#0D1012",
+    });
+    if config.icon.is_some() {
+        manifest["icons"] = json!([
+            { "src": "icon-192.png", "sizes": "192x192", "type": "image/png" },
+            { "src": "icon-512.png", "sizes": "512x512", "type": "image/png" },
+        ]);
+    }
+    let manifest = serde_json::to_string_pretty(&manifest).map_err(io::Error::other)?;
+    output.write(Path::new("site.webmanifest"), manifest.as_bytes())
+}
+
+/// A PNG of the PNG or SVG image `path` scaled down to fit a `max`×`max` square,
+/// keeping its proportions, or none for formats that can't be scaled here.
+pub(crate) fn thumbnail(path: &Path, data: &[u8], max: u32) -> io::Result<Option<Vec<u8>>> {
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or_default().to_lowercase();
+    if !matches!(ext.as_str(), "png" | "svg") {
+        return Ok(None);
+    }
+    SourceIcon::load(path, data)?.render_fit(max).map(Some)
+}
+
+enum SourceIcon {
+    Raster(tiny_skia::Pixmap),
+    Vector(Box<usvg::Tree>),
+}
+
+impl SourceIcon {
+    fn load(path: &Path, data: &[u8]) -> io::Result<SourceIcon> {
+        let invalid = |e: String| io::Error::other(format!("Invalid icon {}: {e}", path.display()));
+        match path.extension().and_then(|s| s.to_str()).map(str::to_lowercase).as_deref() {
+            Some("svg") => usvg::Tree::from_data(data, &usvg::Options::default())
+                .map(|tree| SourceIcon::Vector(Box::new(tree)))
+                .map_err(|e| invalid(e.to_string())),
+            Some("png") => tiny_skia::Pixmap::decode_png(data)
+                .map(SourceIcon::Raster)
+                .map_err(|e| invalid(e.to_string())),
+            _ => Err(invalid("the icon must be a .png or .svg file".to_string())),
+        }
+    }
+
+    fn size(&self) -> (f32, f32) {
+        match self {
+            SourceIcon::Raster(image) => (image.width() as f32, image.height() as f32),
+            SourceIcon::Vector(tree) => (tree.size().width(), tree.size().height()),
+        }
+    }
+
+    /// Renders the icon scaled to fit a `size`×`size` square, centered, as PNG.
+    fn render(&self, size: u32) -> io::Result<Vec<u8>> {
+        let mut pixmap = tiny_skia::Pixmap::new(size, size).ok_or_else(|| io::Error::other("Empty icon size"))?;
+        let (width, height) = self.size();
+        let scale = size as f32 / width.max(height);
+        let transform = tiny_skia::Transform::from_translate(
+            (size as f32 - width * scale) / 2.0,
+            (size as f32 - height * scale) / 2.0,
+        )
+        .pre_scale(scale, scale);
+        self.draw(&mut pixmap, transform);
+        pixmap.encode_png().map_err(io::Error::other)
+    }
+
+    /// Renders the image scaled to fit a `max`×`max` square, never enlarging a raster image, as PNG.
+    fn render_fit(&self, max: u32) -> io::Result<Vec<u8>> {
+        let (width, height) = self.size();
+        let mut scale = max as f32 / width.max(height);
+        if let SourceIcon::Raster(_) = self {
+            scale = scale.min(1.0);
+        }
+        let mut pixmap = tiny_skia::Pixmap::new(
+            ((width * scale).round() as u32).max(1),
+            ((height * scale).round() as u32).max(1),
+        )
+        .ok_or_else(|| io::Error::other("Empty image size"))?;
+        self.draw(&mut pixmap, tiny_skia::Transform::from_scale(scale, scale));
+        pixmap.encode_png().map_err(io::Error::other)
+    }
+
+    fn draw(&self, pixmap: &mut tiny_skia::Pixmap, transform: tiny_skia::Transform) {
+        match self {
+            SourceIcon::Raster(image) => {
+                let paint = tiny_skia::PixmapPaint { quality: tiny_skia::FilterQuality::Bicubic, ..Default::default() };
+                pixmap.draw_pixmap(0, 0, image.as_ref(), &paint, transform, None);
+            }
+            SourceIcon::Vector(tree) => resvg::render(tree, transform, &mut pixmap.as_mut()),
+        }
+    }
+}
+
+/// Packs PNG images into an ICO container (every current browser reads PNG-compressed entries).
+fn ico(images: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut ico = Vec::new();
+    ico.extend_from_slice(&0u16.to_le_bytes());
+    ico.extend_from_slice(&1u16.to_le_bytes());
+    ico.extend_from_slice(&(images.len() as u16).to_le_bytes());
+    let mut offset = 6 + 16 * images.len();
+    for (size, png) in images {
+        // A dimension of 0 means 256
+        let dimension = if *size >= 256 { 0 } else { *size as u8 };
+        ico.extend_from_slice(&[dimension, dimension, 0, 0]);
+        ico.extend_from_slice(&1u16.to_le_bytes());
+        ico.extend_from_slice(&32u16.to_le_bytes());
+        ico.extend_from_slice(&(png.len() as u32).to_le_bytes());
+        ico.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += png.len();
+    }
+    for (_, png) in images {
+        ico.extend_from_slice(png);
+    }
+    ico
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+use regex::{Captures, Regex};
+use walkdir::WalkDir;
+use crate::content::split_frontmatter;
+use crate::diagnostics::{info, warning};
+
+static SHORTCODE_REF: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\{\{<\s*(?:rel)?ref\s+"([^"]+)"\s*>\}\}|\{%\s*link\s+(\S+)\s*%\}"#).unwrap()
+});
+static POST_URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{%\s*post_url\s+(\S+)\s*%\}").unwrap());
+static MARKDOWN_LINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(!?)\[([^\]]*)\]\((?:<([^>]+)>|([^)\s]+))(?:\s+"[^"]*")?\)"#).unwrap()
+});
+
+/// Converts the content of an existing Hugo, Zola, or Jekyll site into
+/// Obsidian-flavored notes inside `vault_path`: relative markdown links become
+/// wikilinks, frontmatter is rewritten as YAML, and page bundles are flattened
+/// into plain notes. Existing vault files are never overwritten.
+pub fn import_site(site_path: &Path, vault_path: &Path) -> io::Result<()> {
+    info!("Importing site: {}", site_path.display());
+    // Hugo and Zola keep pages under content/ and verbatim files under static/
+    let content_root = if site_path.join("content").is_dir() {
+        site_path.join("content")
+    } else {
+        site_path.to_path_buf()
+    };
+    let static_root = site_path.join("static");
+    // Without content/ the site root is walked, where a Jekyll site keeps its build, layouts, and tooling
+    let jekyll = content_root == site_path;
+    let is_content = |entry: &walkdir::DirEntry| {
+        let name = entry.file_name().to_string_lossy();
+        entry.depth() == 0
+            || !(name.starts_with('.')
+                || (jekyll && name.starts_with('_') && name != "_posts")
+                || (jekyll && entry.depth() == 1 && JEKYLL_TOOLING.contains(&&*name)))
+    };
+
+    let mut pages: HashMap<PathBuf, PathBuf> = HashMap::new();
+    let mut files = Vec::new();
+    for entry in WalkDir::new(&content_root).into_iter().filter_entry(is_content).filter_map(|e| e.ok()) {
+        if entry.file_type().is_file() {
+            let relative = entry.path().strip_prefix(&content_root).map_err(io::Error::other)?.to_path_buf();
+            if is_markdown(&relative) {
+                pages.insert(page_key(&relative), note_path(&relative));
+            }
+            files.push(relative);
+        }
+    }
+
+    // Notes are linked by name unless another note shares it
+    let mut name_counts: HashMap<String, usize> = HashMap::new();
+    for note in pages.values() {
+        *name_counts.entry(note_name(note)).or_default() += 1;
+    }
+    let link_target = |note: &Path| {
+        let name = note_name(note);
+        if name_counts[&name] > 1 {
+            note.with_extension("").to_string_lossy().replace('\\', "/")
+        } else {
+            name
+        }
+    };
+
+    for relative in &files {
+        let source = content_root.join(relative);
+        if !is_markdown(relative) {
+            write_new(&vault_path.join(relative), &fs::read(&source)?)?;
+            continue;
+        }
+
+        info!("Importing page: {}", source.display());
+        let (data, body) = split_frontmatter(&fs::read_to_string(&source)?);
+        let base = relative.parent().unwrap_or(Path::new(""));
+        // `{% post_url 2023-01-05-title %}` names a post by its file name, in whichever folder it is
+        let body = POST_URL.replace_all(&body, |caps: &Captures| {
+            let name = caps[1].trim_matches('/');
+            let file_name = Path::new(name).file_name();
+            match pages.keys().filter(|key| key.file_name() == file_name).min() {
+                Some(key) => format!("@/{}.md", key.to_string_lossy().replace('\\', "/")),
+                None => {
+                    warning!("{}: no post {name} for {}; left as is", source.display(), &caps[0]);
+                    caps[0].to_string()
+                }
+            }
+        });
+        let body = SHORTCODE_REF.replace_all(&body, |caps: &Captures| {
+            let target = caps.get(1).or(caps.get(2)).map_or("", |m| m.as_str());
+            format!("@/{}", target.trim_start_matches('/'))
+        });
+        let body = MARKDOWN_LINK.replace_all(&body, |caps: &Captures| {
+            let original = caps[0].to_string();
+            let embed = !caps[1].is_empty();
+            let text = &caps[2];
+            let url = caps.get(3).or(caps.get(4)).map_or("", |m| m.as_str());
+            if url.contains("://") || url.starts_with('#') || url.starts_with("mailto:") {
+                return original;
+            }
+            let (path, anchor) = match url.split_once('#') {
+                Some((path, anchor)) => (path, Some(anchor)),
+                None => (url, None),
+            };
+            let Some(resolved) = resolve(base, path) else {
+                return original;
+            };
+
+            let is_page = resolved.extension().is_none() || is_markdown(&resolved);
+            if is_page && let Some(note) = pages.get(&page_key(&resolved)) {
+                let target = link_target(note);
+                let heading = anchor.map(|a| format!("#{}", a.replace('-', " "))).unwrap_or_default();
+                return if text.is_empty() || text == target {
+                    format!("[[{target}{heading}]]")
+                } else {
+                    format!("[[{target}{heading}|{text}]]")
+                };
+            }
+            let asset = if content_root.join(&resolved).is_file() {
+                Some(resolved)
+            } else if static_root.join(&resolved).is_file() {
+                Some(Path::new("static").join(resolved))
+            } else {
+                None
+            };
+            match asset {
+                Some(asset) => {
+                    let asset = asset.to_string_lossy().replace('\\', "/");
+                    let bang = if embed { "!" } else { "" };
+                    if text.is_empty() {
+                        format!("{bang}[[{asset}]]")
+                    } else {
+                        format!("{bang}[[{asset}|{text}]]")
+                    }
+                }
+                None => original,
+            }
+        });
+
+        let mut note = String::new();
+        if let Some(data) = data {
+            let mut frontmatter = data.deserialize::<serde_yaml::Mapping>().map_err(|e| {
+                io::Error::other(format!("Frontmatter deserialize error in {}: {e}", source.display()))
+            })?;
+            // Zola nests tags under [taxonomies]
+            if let Some(serde_yaml::Value::Mapping(taxonomies)) = frontmatter.remove("taxonomies")
+                && let Some(tags) = taxonomies.get("tags")
+            {
+                frontmatter.insert("tags".into(), tags.clone());
+            }
+            if !frontmatter.is_empty() {
+                let yaml = serde_yaml::to_string(&frontmatter).map_err(io::Error::other)?;
+                note.push_str(&format!("---\n{yaml}---\n\n"));
+            }
+        }
+        note.push_str(body.trim_start());
+        write_new(&vault_path.join(&pages[&page_key(relative)]), note.as_bytes())?;
+    }
+
+    // Files from static/ are copied so absolute links to them keep resolving
+    if static_root.is_dir() {
+        for entry in WalkDir::new(&static_root).into_iter().filter_map(|e| e.ok()) {
+            if entry.file_type().is_file() {
+                let relative = entry.path().strip_prefix(&static_root).map_err(io::Error::other)?;
+                write_new(&vault_path.join("static").join(relative), &fs::read(entry.path())?)?;
+            }
+        }
+    }
+
+    info!("Import finished.");
+    Ok(())
+}
+
+/// Files and folders at the root of a Jekyll site that belong to its tooling rather than its content.
+const JEKYLL_TOOLING: [&str; 6] = ["Gemfile", "Gemfile.lock", "node_modules", "vendor", "package.json", "package-lock.json"];
+
+fn is_markdown(path: &Path) -> bool {
+    matches!(path.extension().and_then(|s| s.to_str()), Some("md" | "markdown"))
+}
+
+/// The key a page is addressed by: its path without extension, with bundles and
+/// section indexes (`post/index.md`, `post/_index.md`) addressed by their folder.
+fn page_key(path: &Path) -> PathBuf {
+    let without_ext = path.with_extension("");
+    match without_ext.file_name().and_then(|s| s.to_str()) {
+        Some("index" | "_index") => without_ext.parent().unwrap_or(Path::new("")).to_path_buf(),
+        _ => without_ext,
+    }
+}
+
+/// Where a page lands in the vault: bundles become `post.md` next to their
+/// folder and section indexes become a folder note `section/section.md`.
+fn note_path(path: &Path) -> PathBuf {
+    let key = page_key(path);
+    let is_section = path.file_stem().and_then(|s| s.to_str()) == Some("_index");
+    match key.file_name() {
+        _ if key.as_os_str().is_empty() => PathBuf::from("Home.md"),
+        Some(name) if is_section => key.join(name).with_extension("md"),
+        _ => key.with_extension("md"),
+    }
+}
+
+fn note_name(note: &Path) -> String {
+    note.file_stem().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// Resolves a link relative to the linking page's folder (`@/` and `/` links
+/// are relative to the content root), normalizing `.` and `..` segments.
+fn resolve(base: &Path, link: &str) -> Option<PathBuf> {
+    let link = link.trim_end_matches('/');
+    let (start, link) = match link.strip_prefix("@/").or_else(|| link.strip_prefix('/')) {
+        Some(rooted) => (PathBuf::new(), rooted),
+        None => (base.to_path_buf(), link),
+    };
+    let mut resolved = start;
+    for component in Path::new(link).components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::ParentDir if !resolved.pop() => return None,
+            _ => {}
+        }
+    }
+    // Jekyll sites commonly link to the rendered .html page
+    if resolved.extension().and_then(|s| s.to_str()) == Some("html") {
+        resolved.set_extension("md");
+    }
+    Some(resolved)
+}
+
+fn write_new(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if path.exists() {
+        warning!("not overwriting existing vault file: {}", path.display());
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)
+}
@@ -0,0 +1,625 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+use comrak::{Anchorizer, ComrakOptions};
+use serde::Deserialize;
+use crate::artifacts::PageArtifact;
+use crate::bibliography::Bibliography;
+use crate::blocks::{note_blocks, NoteBlock};
+use crate::config::{Config, TitleSource};
+use crate::content::{make_comrak_options, split_frontmatter};
+use crate::dates::{recent_updates, site_timezone, RecentUpdate};
+use crate::diagnostics::{broken_link, file_error, info, warning};
+use crate::encrypt::encrypted_folder;
+use crate::domain::{Audience, FolderDefaults, Frontmatter, Note, SortOrder};
+use crate::fields::{inline_fields, remove_fields, render_inline_fields, InlineFields};
+use crate::gallery::is_gallery_file;
+use crate::history::last_commit_dates;
+use crate::links::{find_wikilinks, heading_slug, is_note, replace_wikilinks, slugify, LinkResolver};
+use crate::markdown::{heading_text, summary};
+use crate::mentions::{link_mentions, unlinked_mentions};
+use crate::partials::include_partials;
+use crate::placeholders::check_placeholders;
+use crate::split::{headings, split_note, NotePart};
+use crate::templating::expand_templates;
+use crate::vfs::VaultSource;
+
+/// Longest note summary, in characters.
+const SUMMARY_MAX_CHARS: usize = 280;
+
+/// A note read during the indexing phase, before anything is rendered.
+#[derive(Debug, Clone)]
+pub struct IndexedNote {
+    /// Vault-relative path of the markdown source
+    pub source: PathBuf,
+    /// Output-relative path of the rendered page
+    pub output: PathBuf,
+    /// Root-relative URL of the rendered page
+    pub url: String,
+    pub title: String,
+    pub frontmatter: Frontmatter,
+    /// Markdown body without the frontmatter
+    pub body: String,
+    /// URL of the resolved `cover` image
+    pub cover: Option<String>,
+    /// The pages the note is split into, or none when it is published as one page
+    pub parts: Vec<NotePart>,
+    /// When the note was last changed: its `updated` frontmatter, or with
+    /// `git_history` its last commit
+    pub updated: Option<String>,
+    /// Dataview inline fields (`Key:: Value`) of the body
+    pub fields: BTreeMap<String, serde_json::Value>,
+    /// The body's headings, in order
+    pub headings: Vec<NoteHeading>,
+    /// The blocks of the body marked with `^id`, in order
+    pub blocks: Vec<NoteBlock>,
+    /// With `unlinked_mentions`, the notes that write this note's title without linking to it
+    pub mentioned_in: Vec<PathBuf>,
+    /// Files generated for the note's page by `[[page_artifact]]` commands
+    pub artifacts: Vec<PageArtifact>,
+    /// Every field of the frontmatter as written, known to obs2web or not
+    pub properties: BTreeMap<String, serde_json::Value>,
+}
+
+/// A heading of a note, with the anchor its page gives it.
+#[derive(Debug, Clone)]
+pub struct NoteHeading {
+    pub level: usize,
+    /// The heading's text without markup
+    pub text: String,
+    pub anchor: String,
+    /// Byte offset of the heading's line in the body
+    pub start: usize,
+}
+
+impl IndexedNote {
+    pub fn to_note(&self) -> Note {
+        Note {
+            title: self.title.clone(),
+            path: self.source.clone(),
+            url: self.url.clone(),
+            date: self.frontmatter.date.clone(),
+            tags: self.frontmatter.tags.clone(),
+            cover: self.cover.clone(),
+            archived: self.is_archived(),
+        }
+    }
+
+    /// The anchor of the heading a `[[Note#Heading]]` link names. Like Obsidian, a
+    /// heading can be named with the headings it is nested in, `[[Note#Setup#Linux]]`,
+    /// to tell repeated ones apart; otherwise the first one with the name is meant.
+    pub fn heading_anchor(&self, heading: &str) -> Option<&str> {
+        let mut path: Vec<String> = heading.split('#').map(heading_slug).filter(|key| !key.is_empty()).collect();
+        let target = path.pop()?;
+        let mut parents: Vec<(usize, String)> = Vec::new();
+        for heading in &self.headings {
+            while parents.last().is_some_and(|(level, _)| *level >= heading.level) {
+                parents.pop();
+            }
+            let key = heading_slug(&heading.text);
+            let mut keys = parents.iter().map(|(_, key)| key);
+            if key == target && path.iter().all(|parent| keys.any(|key| key == parent)) {
+                return Some(&heading.anchor);
+            }
+            parents.push((heading.level, key));
+        }
+        None
+    }
+
+    /// An `Anchorizer` that has given out the anchors of the headings before `offset`
+    /// in the body, to render the rest of the note with the anchors it has in whole.
+    pub fn anchors_before(&self, offset: usize) -> Anchorizer {
+        let mut anchors = Anchorizer::new();
+        // An anchor is its own slug, so each comes back as it is and is marked as used
+        for heading in self.headings.iter().take_while(|heading| heading.start < offset) {
+            anchors.anchorize(heading.anchor.clone());
+        }
+        anchors
+    }
+
+    /// URL of the page showing the heading with this slug, or the block with this
+    /// `^id`: the note's own page unless it is split.
+    pub fn heading_url(&self, anchor: &str) -> &str {
+        if let Some(id) = anchor.strip_prefix('^') {
+            let block = self.blocks.iter().find(|block| block.id == id);
+            return block
+                .and_then(|block| self.parts.iter().rev().find(|part| part.body.start <= block.start))
+                .map_or(&self.url, |part| &part.url);
+        }
+        self.parts
+            .iter()
+            .find(|part| part.anchors.iter().any(|a| a == anchor))
+            .map_or(&self.url, |part| &part.url)
+    }
+
+    /// The plain text of the note's first paragraph, shortened to at most
+    /// `SUMMARY_MAX_CHARS`. Inline fields and embeds are left out, and links read as their text.
+    pub fn summary(&self, comrak_options: &ComrakOptions) -> String {
+        let text = render_inline_fields(&self.body, InlineFields::Hide);
+        let text = replace_wikilinks(&text, |link| if link.embed { String::new() } else { link.label() });
+        summary(&text, comrak_options, SUMMARY_MAX_CHARS)
+    }
+
+    /// Whether the note is marked `status: archived` or `outdated: true`.
+    pub fn is_archived(&self) -> bool {
+        self.frontmatter.status.as_deref().is_some_and(|s| s.eq_ignore_ascii_case("archived"))
+            || self.frontmatter.outdated == Some(true)
+    }
+}
+
+/// What a wikilink target resolved to.
+pub enum LinkTarget<'a> {
+    Note(&'a IndexedNote),
+    Asset(&'a Path),
+    /// A note of the vault left out of this site: unpublished, or for another audience
+    Unpublished(&'a Path),
+}
+
+/// Every publishable note and attachment of the vault, built before rendering
+/// so links can be resolved against the whole site.
+pub struct SiteIndex {
+    /// The vault's folder, when it is read from disk
+    pub vault_path: Option<PathBuf>,
+    pub notes: Vec<IndexedNote>,
+    /// Works that notes can cite with `[@citekey]`
+    pub bibliography: Bibliography,
+    resolver: LinkResolver,
+    by_source: HashMap<PathBuf, usize>,
+    by_uid: HashMap<String, usize>,
+    folders: HashMap<PathBuf, FolderDefaults>,
+    /// The notes templates list as `recent_notes`, newest first
+    pub recent: Vec<RecentUpdate>,
+}
+
+impl SiteIndex {
+    /// Reads every note among `files` (vault-relative paths) and works out where it will be published.
+    pub fn build(vault: &dyn VaultSource, files: &[PathBuf], config: &Config) -> io::Result<SiteIndex> {
+        let folders = folder_defaults(vault, files)?;
+        let commit_dates = match vault.local_dir() {
+            Some(vault_path) if config.git_history => last_commit_dates(vault_path),
+            _ => HashMap::new(),
+        };
+        let mut notes = Vec::new();
+        let mut partials = HashMap::new();
+        // A `_gallery.md` heads its folder's gallery page, and a `folder.md` holds its folder's
+        // defaults, rather than being published as notes
+        for source in files.iter().filter(|p| is_note(p) && !is_gallery_file(p) && !is_folder_config(p)) {
+            let (data, mut body) = split_frontmatter(&vault.read_to_string(source)?);
+            let frontmatter_error =
+                |e: String| file_error(source, format!("Frontmatter deserialize error in {}: {e}", source.display()));
+            let (mut frontmatter, mut properties) = match data {
+                Some(data) => (
+                    data.deserialize::<Frontmatter>().map_err(|e| frontmatter_error(e.to_string()))?,
+                    data.deserialize::<BTreeMap<String, serde_json::Value>>().unwrap_or_default(),
+                ),
+                None => (Frontmatter::default(), BTreeMap::new()),
+            };
+            // Private fields are dropped before anything reads them, so no page, context, or file can show them
+            if properties.keys().any(|name| config.private_fields.contains(name)) {
+                properties.retain(|name, _| !config.private_fields.contains(name));
+                frontmatter = serde_json::from_value(serde_json::Value::Object(properties.clone().into_iter().collect()))
+                    .map_err(|e| frontmatter_error(e.to_string()))?;
+            }
+            if !config.private_fields.is_empty() {
+                body = remove_fields(&body, &config.private_fields);
+            }
+            if let Some(defaults) = folders.get(source.parent().unwrap_or(Path::new(""))) {
+                apply_folder_defaults(defaults, &mut frontmatter);
+            }
+            // The id names the note's folder in the output, so it may not lead out of it
+            if let Some(id) = &frontmatter.id
+                && (id.contains(['/', '\\']) || id.contains("..") || slugify(id).is_empty())
+            {
+                warning!(file: source, near: id; "id \"{id}\" can't name a page; publishing the note at its path instead");
+                frontmatter.id = None;
+            }
+            if frontmatter.publish == Some(false) {
+                info!("Skipping unpublished note: {}", source.display());
+                continue;
+            }
+            if let Some(reader) = config.audience
+                && !frontmatter.audience.unwrap_or(Audience::Public).visible_to(reader)
+            {
+                info!("Skipping note not for the {} site: {}", reader.name(), source.display());
+                continue;
+            }
+            // A templated note's `{{ ... }}` are expanded once the index is built
+            let body = match frontmatter.templating {
+                Some(true) => body,
+                _ => check_placeholders(body, source, config.template_placeholders),
+            };
+            if frontmatter.partial == Some(true) {
+                partials.insert(source.clone(), body);
+                continue;
+            }
+            let (title, body) = note_title(source, &frontmatter, body, config);
+            let output = output_path(source, &frontmatter, config.url_style);
+            let updated = frontmatter.updated.clone().or_else(|| commit_dates.get(source).cloned());
+            let fields = inline_fields(&body);
+            notes.push(IndexedNote {
+                source: source.clone(),
+                url: config.url_style.url(&output),
+                output,
+                title,
+                frontmatter,
+                body,
+                cover: None,
+                parts: Vec::new(),
+                updated,
+                fields,
+                headings: Vec::new(),
+                blocks: Vec::new(),
+                mentioned_in: Vec::new(),
+                artifacts: Vec::new(),
+                properties,
+            });
+        }
+
+        let resolver = LinkResolver::new(files.iter().cloned());
+        // Partials are read first so every note can embed any of them
+        if !partials.is_empty() {
+            for note in &mut notes {
+                note.body = include_partials(&note.body, &note.source, &partials, &resolver);
+                note.fields = inline_fields(&note.body);
+            }
+        }
+
+        let mut by_uid = HashMap::new();
+        for (i, note) in notes.iter().enumerate() {
+            if let Some(uid) = &note.frontmatter.id
+                && let Some(previous) = by_uid.insert(uid.to_lowercase(), i)
+            {
+                warning!(
+                    file: &note.source, near: uid;
+                    "notes {} and {} share the id \"{uid}\"",
+                    notes[previous].source.display(),
+                    note.source.display()
+                );
+            }
+        }
+        let comrak_options = make_comrak_options(config);
+        for note in &mut notes {
+            read_headings(note, &comrak_options, config);
+        }
+        let by_source = notes.iter().enumerate().map(|(i, n)| (n.source.clone(), i)).collect();
+
+        let mut index = SiteIndex {
+            vault_path: vault.local_dir().map(Path::to_path_buf),
+            resolver,
+            notes,
+            bibliography: Bibliography::load(vault, &config.bibliography)?,
+            by_source,
+            by_uid,
+            folders,
+            recent: Vec::new(),
+        };
+        // Covers can point at any attachment, so they are resolved once the whole vault is known
+        let covers: Vec<_> = index.notes.iter().map(|note| index.cover_url(note)).collect();
+        for (note, cover) in index.notes.iter_mut().zip(covers) {
+            note.cover = cover;
+        }
+        // Templated notes can list the others, so they are rendered last
+        for (i, body) in expand_templates(&index.notes, config)? {
+            let note = &mut index.notes[i];
+            note.fields = inline_fields(&body);
+            note.body = body;
+            read_headings(note, &comrak_options, config);
+        }
+        if config.link_mentions || config.unlinked_mentions {
+            let mentions = unlinked_mentions(&index);
+            if config.link_mentions {
+                for (i, body) in link_mentions(&index.notes, &mentions) {
+                    let note = &mut index.notes[i];
+                    note.fields = inline_fields(&body);
+                    note.body = body;
+                    read_headings(note, &comrak_options, config);
+                }
+            } else {
+                for mention in mentions {
+                    let source = index.notes[mention.note].source.clone();
+                    let mentioned_in = &mut index.notes[mention.target].mentioned_in;
+                    if !mentioned_in.contains(&source) {
+                        mentioned_in.push(source);
+                    }
+                }
+            }
+        }
+        // Every page lists them, so an encrypted note's title would show on pages anyone can read
+        let listed = index.notes.iter().filter(|note| encrypted_folder(&note.source, config).is_none());
+        index.recent = recent_updates(listed, config.recent_notes, site_timezone(config)?);
+        Ok(index)
+    }
+
+    /// URL of a note's `cover`/`image`.
+    fn cover_url(&self, note: &IndexedNote) -> Option<String> {
+        let cover = note.frontmatter.cover.as_deref()?.trim();
+        let url = self.attachment_url(cover);
+        if url.is_none() {
+            broken_link!(file: &note.source, near: cover; "cover image {cover} not found in the vault for {}", note.source.display());
+        }
+        url
+    }
+
+    /// URL of an image given as an absolute URL or a vault attachment
+    /// (`cover.png` or `"[[cover.png]]"`), or none when there is no such attachment.
+    pub fn attachment_url(&self, value: &str) -> Option<String> {
+        let value = value.trim();
+        if value.contains("://") {
+            return Some(value.to_string());
+        }
+        match self.resolve(frontmatter_link(value)) {
+            Some(LinkTarget::Asset(path)) => Some(url_for(path)),
+            _ => None,
+        }
+    }
+
+    /// Sort order configured for a vault folder, inherited from its parents.
+    pub fn folder_sort(&self, dir: &Path) -> SortOrder {
+        self.folders.get(dir).and_then(|d| d.sort).unwrap_or_default()
+    }
+
+    /// The published note read from `source` (a vault-relative path).
+    pub fn note(&self, source: &Path) -> Option<&IndexedNote> {
+        self.by_source.get(source).map(|&i| &self.notes[i])
+    }
+
+    /// The other notes `body`, all or part of `note`'s, links to with wikilinks, in
+    /// the order of their first link.
+    pub fn outgoing_links(&self, note: &IndexedNote, body: &str) -> Vec<&IndexedNote> {
+        let mut seen = HashSet::new();
+        find_wikilinks(body)
+            .filter(|(_, link)| !link.embed && !link.target.is_empty())
+            .filter_map(|(_, link)| match self.resolve(&link.target) {
+                Some(LinkTarget::Note(target)) if target.source != note.source => Some(target),
+                _ => None,
+            })
+            .filter(|target| seen.insert(target.source.as_path()))
+            .collect()
+    }
+
+    /// Resolves a wikilink target. A note's id wins over file names, so links
+    /// written against an id keep working when the note is renamed or moved.
+    pub fn resolve(&self, target: &str) -> Option<LinkTarget<'_>> {
+        if let Some(&i) = self.by_uid.get(&target.trim().to_lowercase()) {
+            return Some(LinkTarget::Note(&self.notes[i]));
+        }
+        let path = self.resolver.resolve(target)?;
+        Some(match self.by_source.get(path) {
+            Some(&i) => LinkTarget::Note(&self.notes[i]),
+            None if is_note(path) => LinkTarget::Unpublished(path),
+            None => LinkTarget::Asset(path),
+        })
+    }
+}
+
+/// The target of a link written in frontmatter, either plain (`Note`) or as a
+/// quoted wikilink (`"[[Note|Alias]]"`).
+pub fn frontmatter_link(value: &str) -> &str {
+    let target = value.trim().trim_start_matches('!').trim_start_matches("[[").trim_end_matches("]]");
+    target.split(['|', '#']).next().unwrap_or(target).trim()
+}
+
+/// Finds the note's headings and their anchors and its `^id` blocks, and splits the
+/// note into pages at the headings when its frontmatter or, for long notes, the
+/// site settings ask for it.
+fn read_headings(note: &mut IndexedNote, comrak_options: &ComrakOptions, config: &Config) {
+    let mut anchors = Anchorizer::new();
+    note.headings = headings(&note.body)
+        .into_iter()
+        .map(|heading| {
+            // Links in a heading read as their text, as they do once rendered
+            let text = replace_wikilinks(&heading.text, |link| if link.embed { String::new() } else { link.label() });
+            let text = heading_text(&text, comrak_options);
+            NoteHeading { level: heading.level, anchor: anchors.anchorize(text.clone()), text, start: heading.line.start }
+        })
+        .collect();
+    note.blocks = note_blocks(&note.body);
+    let long = note.body.split_whitespace().count() >= config.split_min_words;
+    if note.frontmatter.split.unwrap_or(config.split_notes && long) {
+        note.parts = split_note(note, config.split_heading_level, config.url_style);
+    }
+}
+
+/// Picks the note's title from the configured sources, optionally stripping a
+/// leading H1 that repeats it from the body.
+fn note_title(source: &Path, frontmatter: &Frontmatter, body: String, config: &Config) -> (String, String) {
+    let heading = first_heading(&body);
+    let title = config
+        .title_sources
+        .iter()
+        .find_map(|title_source| match title_source {
+            TitleSource::Frontmatter => frontmatter.title.clone(),
+            TitleSource::Heading => heading.as_ref().map(|(text, _)| text.clone()),
+            TitleSource::Filename => source.file_stem().and_then(|s| s.to_str()).map(str::to_string),
+        })
+        .unwrap_or_else(|| "untitled".to_string());
+
+    if config.strip_title_heading
+        && let Some((text, line)) = heading
+        && text == title
+        && body.lines().take_while(|l| l.trim().is_empty()).count() == line
+    {
+        let body = body.lines().skip(line + 1).collect::<Vec<_>>().join("\n");
+        return (title, body);
+    }
+    (title, body)
+}
+
+/// The text and line number of the first ATX `# Heading`, ignoring fenced code.
+fn first_heading(body: &str) -> Option<(String, usize)> {
+    let mut fence: Option<&str> = None;
+    for (i, line) in body.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+        } else if let Some(text) = trimmed.strip_prefix("# ") {
+            let text = text.trim().trim_end_matches('#').trim();
+            if !text.is_empty() {
+                return Some((text.to_string(), i));
+            }
+        }
+    }
+    None
+}
+
+pub fn is_folder_config(path: &Path) -> bool {
+    matches!(path.file_name().and_then(|s| s.to_str()), Some("_folder.yml" | "folder.md"))
+}
+
+/// Reads every folder's `_folder.yml`/`folder.md` and cascades them, so each folder
+/// maps to the defaults in effect for the notes directly inside it.
+fn folder_defaults(vault: &dyn VaultSource, files: &[PathBuf]) -> io::Result<HashMap<PathBuf, FolderDefaults>> {
+    let mut own: HashMap<PathBuf, FolderDefaults> = HashMap::new();
+    for file in files.iter().filter(|p| is_folder_config(p)) {
+        let text = vault.read_to_string(file)?;
+        let defaults = if is_note(file) {
+            match split_frontmatter(&text).0 {
+                Some(data) => data.deserialize::<FolderDefaults>().map_err(io::Error::other),
+                None => Ok(FolderDefaults::default()),
+            }
+        } else {
+            serde_yaml::from_str::<Option<FolderDefaults>>(&text)
+                .map(Option::unwrap_or_default)
+                .map_err(io::Error::other)
+        }
+        .map_err(|e| io::Error::other(format!("Folder defaults error in {}: {e}", file.display())))?;
+        let dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+        let merged = merge_defaults(own.get(&dir).cloned().unwrap_or_default(), &defaults);
+        own.insert(dir, merged);
+    }
+
+    let mut cascaded = HashMap::new();
+    for file in files {
+        let dir = file.parent().unwrap_or(Path::new(""));
+        if cascaded.contains_key(dir) {
+            continue;
+        }
+        // Apply ancestors from the vault root down so nearer folders win
+        let mut ancestors: Vec<&Path> = dir.ancestors().collect();
+        ancestors.reverse();
+        let effective = ancestors.into_iter().fold(FolderDefaults::default(), |acc, ancestor| {
+            match own.get(ancestor) {
+                Some(defaults) => merge_defaults(acc, defaults),
+                None => acc,
+            }
+        });
+        cascaded.insert(dir.to_path_buf(), effective);
+    }
+    Ok(cascaded)
+}
+
+/// Layers `child` over `parent`: set fields override, tags accumulate.
+fn merge_defaults(parent: FolderDefaults, child: &FolderDefaults) -> FolderDefaults {
+    let tags = match (parent.tags, &child.tags) {
+        (Some(mut tags), Some(more)) => {
+            tags.extend(more.iter().filter(|t| !tags.contains(t)).cloned().collect::<Vec<_>>());
+            Some(tags)
+        }
+        (tags, None) => tags,
+        (None, more) => more.clone(),
+    };
+    FolderDefaults {
+        template: child.template.clone().or(parent.template),
+        tags,
+        publish: child.publish.or(parent.publish),
+        sort: child.sort.or(parent.sort),
+        audience: child.audience.or(parent.audience),
+        partial: child.partial.or(parent.partial),
+    }
+}
+
+/// Fills in what the note's own frontmatter leaves unset; folder tags are added to the note's.
+fn apply_folder_defaults(defaults: &FolderDefaults, frontmatter: &mut Frontmatter) {
+    if frontmatter.template.is_none() {
+        frontmatter.template = defaults.template.clone();
+    }
+    if frontmatter.publish.is_none() {
+        frontmatter.publish = defaults.publish;
+    }
+    if frontmatter.audience.is_none() {
+        frontmatter.audience = defaults.audience;
+    }
+    if frontmatter.partial.is_none() {
+        frontmatter.partial = defaults.partial;
+    }
+    if let Some(folder_tags) = &defaults.tags {
+        let tags = frontmatter.tags.get_or_insert_with(Vec::new);
+        for tag in folder_tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+    }
+}
+
+/// Where a note is published: `n/<id>/index.html` for notes with an `id`/`uid`,
+/// slugified, otherwise the vault path with an `.html` extension, as `style` names pages.
+fn output_path(source: &Path, frontmatter: &Frontmatter, style: UrlStyle) -> PathBuf {
+    if let Some(uid) = &frontmatter.id {
+        return Path::new("n").join(slugify(uid)).join("index.html");
+    }
+    let file_name = source.file_name().unwrap_or_default().to_string_lossy().replace('?', "");
+    style.output(&source.with_file_name(file_name).with_extension("html"))
+}
+
+/// How pages are named in the output, and so what their URLs look like.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UrlStyle {
+    /// `Note.html`, linked as `/Note.html`
+    #[default]
+    Html,
+    /// `Note/index.html`, linked as `/Note/`
+    Directory,
+    /// `Note.html`, linked as `/Note`, for servers that answer `/Note` with `Note.html`
+    Clean,
+}
+
+impl UrlStyle {
+    /// Where the page named `page` (`Note.html`) is written.
+    pub fn output(self, page: &Path) -> PathBuf {
+        match self {
+            UrlStyle::Directory if page.file_name().is_some_and(|name| name != "index.html") => {
+                page.with_extension("").join("index.html")
+            }
+            _ => page.to_path_buf(),
+        }
+    }
+
+    /// URL of the page written at `output`.
+    pub fn url(self, output: &Path) -> String {
+        let url = url_for(output);
+        match self {
+            UrlStyle::Clean => url.strip_suffix(".html").map_or(url.clone(), str::to_string),
+            _ => url,
+        }
+    }
+}
+
+/// Root-relative URL of an output-relative path; directory indexes end in `/`.
+pub fn url_for(output: &Path) -> String {
+    let mut url = String::from("/");
+    let parts: Vec<String> = output.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+    for (i, part) in parts.iter().enumerate() {
+        if i + 1 == parts.len() && part == "index.html" {
+            break;
+        }
+        for c in part.chars() {
+            match c {
+                ' ' => url.push_str("%20"),
+                '#' => url.push_str("%23"),
+                '?' => url.push_str("%3F"),
+                '%' => url.push_str("%25"),
+                c => url.push(c),
+            }
+        }
+        if i + 1 < parts.len() {
+            url.push('/');
+        }
+    }
+    url
+}
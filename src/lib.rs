@@ -1,81 +1,291 @@
-use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use clap::Parser;
-use walkdir::WalkDir;
+use crate::activitypub::write_activitypub;
+use crate::artifacts::generate_artifacts;
+use crate::authors::{check_avatars, write_author_pages};
+use crate::book::write_books;
+use crate::changes::write_changes_page;
 use crate::content::{make_comrak_options, process_markdown_file};
-use crate::domain::Note;
-use crate::fs::{prepare_output_dir, process_asset};
-use crate::template::{init_tera, render_index};
+use crate::dates::start_build;
+use crate::encrypt::{encrypted_folder, EncryptPages};
+use crate::diagnostics::{info, set_strict, set_vault, warning};
+use crate::domain::{Audience, Note};
+use crate::config::Config;
+use crate::index::{is_folder_config, IndexedNote, SiteIndex};
+use crate::icons::{write_site_icons, write_web_manifest};
+use crate::limits::{check_output_size, limit_asset_sizes};
+use crate::links::is_note;
+use crate::lock::BuildLock;
+use crate::manifest::write_manifest;
+use crate::map::write_map;
+use crate::metadata::write_note_metadata;
+use crate::sources::write_note_sources;
+use crate::notes_table::write_notes_table;
+use crate::palette::write_command_palette;
+use crate::postprocess::RewriteHtml;
+use crate::pwa::write_service_worker;
+use crate::random::write_random_page;
+use crate::scan::scan_notes;
+use crate::search::{write_opensearch, write_search_index};
+use crate::sitemap::write_sitemap;
+use crate::social::write_social_cards;
+use crate::flashcards::write_flashcards;
+use crate::gallery::write_galleries;
+use crate::fs::{build_with_swap, only_filter, process_assets};
+use crate::timeline::write_timeline;
+use crate::template::{init_tera, render_index, theme_file, write_navigation_script};
+use crate::vfs::{LocalDir, LocalVault, MemoryVault, OutputSink, OutputSubdir, VaultSource};
 
 pub mod domain;
 pub mod template;
 pub mod content;
 pub mod fs;
+pub mod archive;
+#[cfg(feature = "cli")]
+pub mod serve;
+pub mod config;
+pub mod links;
+pub mod export;
+pub mod import;
+pub mod index;
+pub mod history;
+pub mod markdown;
+pub mod social;
+pub mod qr;
+pub mod icons;
+pub mod pwa;
+pub mod bibliography;
+pub mod search;
+pub mod scaffold;
+pub mod cli;
+#[cfg(feature = "cli")]
+pub mod watch;
+#[cfg(feature = "cli")]
+mod commands;
+pub mod deploy;
+pub mod diagnostics;
+pub mod daemon;
+pub mod ffi;
+pub mod vfs;
+pub mod fixtures;
+pub mod postprocess;
+pub mod split;
+pub mod book;
+pub mod figures;
+pub mod embeds;
+pub mod http;
+pub mod dates;
+pub mod changes;
+pub mod digest;
+pub mod activitypub;
+pub mod metadata;
+pub mod fields;
+pub mod placeholders;
+pub mod structured_data;
+pub mod authors;
+pub mod templating;
+pub mod partials;
+pub mod notes_table;
+pub mod random;
+pub mod mentions;
+pub mod blocks;
+pub mod sources;
+pub mod corpus;
+pub mod artifacts;
+pub mod sitemap;
+pub mod lock;
+pub mod limits;
+pub mod manifest;
+pub mod palette;
+pub mod flashcards;
+pub mod timeline;
+pub mod map;
+pub mod gallery;
+pub mod properties;
+pub mod scan;
+pub mod encrypt;
+#[cfg(feature = "network")]
+pub mod s3;
 
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-pub struct Args {
-    /// Path to the Obsidian vault
-    #[arg(short, long)]
-    pub vault_path: PathBuf,
+pub use crate::cli::{Args, Command};
+#[cfg(feature = "cli")]
+pub use crate::commands::run;
 
-    /// Path to the output directory
-    #[arg(short, long)]
-    pub output_dir: PathBuf,
+/// Builds the site from a vault folder, or a `.zip` of one, into `output_dir`,
+/// swapping the new build in as `output_swap` says. Another build writing to the
+/// same output or cache at the time is waited for or reported, per `wait_for_lock`.
+pub fn build_site(vault_path: &Path, output_dir: &Path, config: &Config) -> std::io::Result<()> {
+    let _lock = BuildLock::acquire(output_dir, config)?;
+    build_with_swap(output_dir, config.output_swap, |output_dir| {
+        build_site_into(vault_path, &LocalDir::create(output_dir)?, config)
+    })
 }
 
-pub fn build_site(vault_path: &Path, output_dir: &Path) -> std::io::Result<()> {
-    println!("Building site...");
+/// Builds the site from a vault folder, or a `.zip` of one, into `output`, such as
+/// a bucket the site is published to without a local copy.
+pub fn build_site_into(vault_path: &Path, output: &dyn OutputSink, config: &Config) -> std::io::Result<()> {
+    set_vault(vault_path);
+    if vault_path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip")) && vault_path.is_file() {
+        let vault = MemoryVault::from_zip(std::fs::File::open(vault_path)?)
+            .map_err(|e| std::io::Error::other(format!("Cannot read vault archive {}: {e}", vault_path.display())))?;
+        build_site_with(&vault, output, config)
+    } else {
+        build_site_with(&LocalVault::new(vault_path), output, config)
+    }
+}
 
-    let tera = init_tera()?;
-    prepare_output_dir(output_dir)?;
-    let comrak_options = make_comrak_options();
+/// Builds the site from any vault source into any output sink.
+pub fn build_site_with(vault: &dyn VaultSource, output: &dyn OutputSink, config: &Config) -> std::io::Result<()> {
+    if config.audience_variants {
+        for audience in Audience::ALL {
+            info!("Building the {} site...", audience.name());
+            let config = Config { audience: Some(audience), audience_variants: false, ..config.clone() };
+            build_site_with(vault, &OutputSubdir::new(output, Path::new(audience.name())), &config)?;
+        }
+        return Ok(());
+    }
+    info!("Building site...");
+    set_strict(config.strict);
+    start_build();
+
+    let tera = init_tera(config)?;
+    if config.qr_codes && config.site_url.is_none() {
+        warning!("qr_codes needs site_url to be set; no QR codes will be rendered");
+    }
+    let comrak_options = make_comrak_options(config);
 
-    let mut notes: Vec<Note> = Vec::new();
     let mut tags: HashMap<String, Vec<Note>> = HashMap::new();
-    let mut processed_files: HashSet<PathBuf> = HashSet::new();
-    let entries = WalkDir::new(vault_path).into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| !e.file_name()
-            .to_str()
-            .map_or(false, |s| s.starts_with(".")));
+    let mut files = vault.files(config)?;
 
-    for entry in entries {
-        let path = entry.path();
-        if path.is_dir() {
-            continue;
+    // Index every note first so links can be resolved against the whole vault
+    let mut index = SiteIndex::build(vault, &files, config)?;
+    if config.secret_scan {
+        let flagged = scan_notes(vault, &index, config)?;
+        if !flagged.is_empty() {
+            files.retain(|file| !flagged.contains(file));
+            index = SiteIndex::build(vault, &files, config)?;
         }
-
-        // Preserve relative structure under output_dir
-        let relative_path = path.strip_prefix(vault_path).map_err(|_e| {
-            std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Failed to compute relative path",
-            )
-        })?;
-        let output_path = output_dir.join(relative_path);
-
-        if path.extension().and_then(|s| s.to_str()) == Some("md") {
-            if !processed_files.contains(path) {
-                process_markdown_file(
-                    path,
-                    &output_dir.join(relative_path.parent().unwrap_or_else(|| Path::new(""))),
-                    &tera,
-                    &comrak_options,
-                    &mut notes,
-                    &mut tags,
-                )?;
-                processed_files.insert(path.to_path_buf());
-            }
-        } else {
-            process_asset(path, &output_path)?;
+    }
+    // Pages are rewritten before they are encrypted, so the decrypted pages have their links in place
+    let encrypted = EncryptPages::new(output, &tera, &index, config)?;
+    let output = &RewriteHtml::new(&encrypted, &config.html_rewrite, config.base_path.as_deref())?;
+    check_avatars(&index, config);
+    let only = only_filter(&config.only)?;
+    // Before rendering, so templates can embed the artifacts
+    if !config.page_artifacts.is_empty() {
+        let selected = |note: &IndexedNote| only.as_ref().is_none_or(|only| only.is_match(&note.source));
+        generate_artifacts(&mut index, selected, output, config)?;
+    }
+    let notes: Cow<[IndexedNote]> = match only {
+        Some(only) => {
+            let selected: Vec<IndexedNote> = index.notes.iter().filter(|n| only.is_match(&n.source)).cloned().collect();
+            info!("Rendering {} of {} notes", selected.len(), index.notes.len());
+            Cow::Owned(selected)
         }
+        None => Cow::Borrowed(&index.notes),
+    };
+    // What the files and pages written from these notes show of them would give an encrypted page away, so only
+    // the notes outside encrypted folders reach them: `public` of the notes rendered, `listed` of the whole site
+    let unencrypted = |notes: &[IndexedNote]| -> Vec<IndexedNote> {
+        notes.iter().filter(|n| encrypted_folder(&n.source, config).is_none()).cloned().collect()
+    };
+    let (public, listed): (Cow<[IndexedNote]>, Cow<[IndexedNote]>) = if config.encrypted_folders.is_empty() {
+        (Cow::Borrowed(&notes), Cow::Borrowed(&index.notes))
+    } else {
+        (Cow::Owned(unencrypted(&notes)), Cow::Owned(unencrypted(&index.notes)))
+    };
+    for note in notes.iter() {
+        process_markdown_file(note, &index, output, &tera, &comrak_options, config, &mut tags)?;
+    }
+    write_books(&listed, &index, &tera, &comrak_options, output, config)?;
+    if config.changes_page {
+        write_changes_page(&listed, &index, &tera, output, config)?;
+    }
+    if config.notes_table {
+        write_notes_table(&listed, &index, &tera, output, config)?;
+    }
+    if config.random_note {
+        write_random_page(&listed, &tera, output, config)?;
+    }
+    if config.command_palette {
+        write_command_palette(&listed, &index, output, config)?;
+    }
+    if config.flashcards {
+        write_flashcards(&listed, &index, &tera, &comrak_options, output, config)?;
+    }
+    if config.timeline {
+        write_timeline(&listed, &index, &tera, output, config)?;
+    }
+    if config.map {
+        write_map(&listed, &index, &tera, output, config)?;
+    }
+    if config.author_pages {
+        write_author_pages(&listed, &index, &tera, output, config)?;
+    }
+    if config.note_metadata {
+        write_note_metadata(&public, &index, output, config)?;
+    }
+    if config.markdown_files || config.text_files {
+        write_note_sources(&public, &index, output, config)?;
+    }
+    if config.activitypub {
+        write_activitypub(&listed, &index, &tera, &comrak_options, output, config)?;
+    }
+    if config.sitemap {
+        write_sitemap(&listed, output, config)?;
     }
 
-    std::fs::copy("templates/style.css", output_dir.join("style.css")).unwrap();
-    render_index(&tera, output_dir, &notes)?;
-    // render_tag_pages(&tera, output_dir, tags)?;
+    // Attachments keep their vault-relative paths in the output
+    let assets: Vec<PathBuf> = files
+        .iter()
+        .filter(|p| !is_note(p) && !is_folder_config(p))
+        .cloned()
+        .collect();
+    let assets = limit_asset_sizes(vault, assets, config)?;
+    process_assets(vault, output, &assets, config.asset_mode)?;
+    write_galleries(vault, &files, &assets, &index, &tera, output, config)?;
+    if let Some(icon) = &config.icon {
+        write_site_icons(vault, icon, output)?;
+    }
+    if config.icon.is_some() || config.pwa {
+        write_web_manifest(output, config)?;
+    }
+    if config.social_cards {
+        write_social_cards(&public, output, config)?;
+    }
 
-    println!("Site built successfully.");
+    let home: Vec<Note> = public.iter().map(|n| n.to_note()).collect();
+    output.write(Path::new("style.css"), theme_file("style.css")?.as_bytes())?;
+    if config.code_block_headers {
+        output.write(Path::new("copy-code.js"), theme_file("copy-code.js")?.as_bytes())?;
+    }
+    if config.notes_table {
+        output.write(Path::new("notes-table.js"), theme_file("notes-table.js")?.as_bytes())?;
+    }
+    if config.video_facades {
+        output.write(Path::new("video-facade.js"), theme_file("video-facade.js")?.as_bytes())?;
+    }
+    if config.instant_navigation {
+        write_navigation_script(output, config)?;
+    }
+    render_index(&tera, output, &home, &index, config)?;
+    if config.search {
+        write_search_index(&public, output, &tera, config)?;
+    }
+    if config.opensearch {
+        write_opensearch(output, config)?;
+    }
+    // render_tag_pages(&tera, output, tags)?;
+    // Last, so the service worker sees every file of the finished build
+    if config.pwa {
+        write_service_worker(output)?;
+    }
+    if config.checksum_manifest {
+        write_manifest(output)?;
+    }
+    check_output_size(output, config)?;
+
+    info!("Site built successfully.");
     Ok(())
-}
\ No newline at end of file
+}
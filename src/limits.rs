@@ -0,0 +1,85 @@
+use std::io;
+use std::path::PathBuf;
+use serde::Deserialize;
+use crate::config::Config;
+use crate::diagnostics::{info, warning};
+use crate::vfs::{OutputSink, VaultSource};
+
+/// What happens to an attachment larger than `max_asset_size`, or to a site larger
+/// than `max_output_size`; the `size_policy` setting.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SizePolicy {
+    /// Publish it and report it
+    #[default]
+    Warn,
+    /// Leave the attachment out of the site and report it; a site too large is only reported
+    Skip,
+    /// Stop the build
+    Fail,
+}
+
+/// The attachments among `assets` within `max_asset_size`, or all of them as
+/// `size_policy` says: larger ones are reported, and left out or fail the build.
+pub fn limit_asset_sizes(vault: &dyn VaultSource, assets: Vec<PathBuf>, config: &Config) -> io::Result<Vec<PathBuf>> {
+    let Some(max) = config.max_asset_size else {
+        return Ok(assets);
+    };
+    let mut kept = Vec::with_capacity(assets.len());
+    for asset in assets {
+        let size = vault.size(&asset)?;
+        if size <= max {
+            kept.push(asset);
+            continue;
+        }
+        let message = format!("{} is {}, above max_asset_size ({})", asset.display(), format_size(size), format_size(max));
+        match config.size_policy {
+            SizePolicy::Warn => {
+                warning!(file: &asset; "{message}");
+                kept.push(asset);
+            }
+            SizePolicy::Skip => warning!(file: &asset; "{message}; leaving it out of the site"),
+            SizePolicy::Fail => return Err(io::Error::other(message)),
+        }
+    }
+    Ok(kept)
+}
+
+/// Adds up the size of everything the build wrote and reports a site above
+/// `max_output_size`, failing the build when `size_policy` is `fail`.
+pub fn check_output_size(output: &dyn OutputSink, config: &Config) -> io::Result<()> {
+    let Some(max) = config.max_output_size else {
+        return Ok(());
+    };
+    let mut total = 0;
+    for file in output.files()? {
+        total += output.size(&file)?;
+    }
+    if total <= max {
+        info!("The site is {} of the {} max_output_size allows", format_size(total), format_size(max));
+        return Ok(());
+    }
+    let message = format!("The site is {}, above max_output_size ({})", format_size(total), format_size(max));
+    match config.size_policy {
+        SizePolicy::Fail => Err(io::Error::other(message)),
+        SizePolicy::Warn | SizePolicy::Skip => {
+            warning!("{message}");
+            Ok(())
+        }
+    }
+}
+
+/// A byte count in the largest binary unit that keeps it at least 1, e.g. `1.5 GiB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} bytes");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
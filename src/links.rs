@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use comrak::Anchorizer;
+use regex::{Captures, Regex};
+
+static WIKILINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(!?)\[\[([^\[\]|#]*)(?:#([^\[\]|]*))?(?:\|([^\[\]]*))?\]\]").unwrap()
+});
+
+/// An Obsidian `[[Target#Heading|Alias]]` link, or a `![[...]]` embed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiLink {
+    pub embed: bool,
+    pub target: String,
+    pub heading: Option<String>,
+    pub alias: Option<String>,
+}
+
+impl WikiLink {
+    fn from_captures(caps: &Captures) -> Self {
+        let non_empty = |i: usize| {
+            caps.get(i)
+                .map(|m| m.as_str().trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+        WikiLink {
+            embed: !caps[1].is_empty(),
+            target: caps[2].trim().to_string(),
+            heading: non_empty(3),
+            alias: non_empty(4),
+        }
+    }
+
+    /// The text Obsidian shows for the link: the alias, or the target as written.
+    pub fn label(&self) -> String {
+        match (&self.alias, &self.heading) {
+            (Some(alias), _) => alias.clone(),
+            (None, Some(heading)) if self.target.is_empty() => heading.clone(),
+            (None, Some(heading)) => format!("{} > {}", self.target, heading),
+            (None, None) => self.target.clone(),
+        }
+    }
+}
+
+/// Replaces every wikilink and embed in `content` with the output of `f`.
+pub fn replace_wikilinks(content: &str, mut f: impl FnMut(&WikiLink) -> String) -> String {
+    WIKILINK
+        .replace_all(content, |caps: &Captures| f(&WikiLink::from_captures(caps)))
+        .into_owned()
+}
+
+/// Every wikilink and embed in `content` with the byte range it spans.
+pub fn find_wikilinks(content: &str) -> impl Iterator<Item = (Range<usize>, WikiLink)> + '_ {
+    WIKILINK.captures_iter(content).map(|caps| (caps.get(0).unwrap().range(), WikiLink::from_captures(&caps)))
+}
+
+/// Turns a note name or heading into a URL-safe slug.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    for c in text.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || c == '_' || c == '-' {
+            slug.push(c);
+        } else if c.is_whitespace() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug
+}
+
+/// The anchor GitHub (and comrak) give a heading with `text`: lowercased, without
+/// punctuation, and with spaces as dashes. Repeated headings are numbered by the
+/// `Anchorizer` rendering the page (`setup`, `setup-1`).
+pub fn heading_slug(text: &str) -> String {
+    Anchorizer::new().anchorize(text.to_string())
+}
+
+/// Resolves wikilink targets to vault-relative file paths the way Obsidian does:
+/// `Note` and `Folder/Note` match notes without their `.md` extension, file names
+/// match attachments anywhere in the vault, and ambiguous names prefer the
+/// shortest path.
+pub struct LinkResolver {
+    by_name: HashMap<String, Vec<PathBuf>>,
+}
+
+impl LinkResolver {
+    pub fn new<I: IntoIterator<Item = PathBuf>>(files: I) -> Self {
+        let mut by_name: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for file in files {
+            let name = file.file_name().unwrap_or_default().to_string_lossy().to_lowercase();
+            by_name.entry(name).or_default().push(file);
+        }
+        for paths in by_name.values_mut() {
+            paths.sort_by_key(|p| (p.components().count(), p.clone()));
+        }
+        LinkResolver { by_name }
+    }
+
+    pub fn resolve(&self, target: &str) -> Option<&Path> {
+        let target = target.trim().trim_start_matches('/').to_lowercase();
+        if target.is_empty() {
+            return None;
+        }
+        // Names like `2024.01.01` look like they have an extension, so both forms are tried
+        [target.clone(), format!("{target}.md")]
+            .into_iter()
+            .find_map(|candidate| {
+                let name = candidate.rsplit('/').next().unwrap_or(&candidate);
+                self.by_name.get(name)?.iter().find(|path| {
+                    let path = path.to_string_lossy().replace('\\', "/").to_lowercase();
+                    path == candidate || path.ends_with(&format!("/{candidate}"))
+                })
+            })
+            .map(PathBuf::as_path)
+    }
+}
+
+pub fn is_note(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("md")
+}
@@ -0,0 +1,53 @@
+use std::fs::{self, File, TryLockError};
+use std::io;
+use std::path::Path;
+use crate::config::Config;
+use crate::diagnostics::{config_error, info};
+
+/// Advisory locks a build holds on its output folder and on the cache while it
+/// runs, so two builds, such as a cron job and one started by hand, can't write
+/// to them at the same time. The locks are released when this is dropped.
+pub struct BuildLock {
+    _files: Vec<File>,
+}
+
+impl BuildLock {
+    /// Locks `output_dir`, through a `.<name>.lock` file next to it, and the cache,
+    /// through `build.lock` in it. When another build holds either, waits for it
+    /// with `wait_for_lock` and fails otherwise.
+    pub fn acquire(output_dir: &Path, config: &Config) -> io::Result<BuildLock> {
+        let parent = output_dir.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let name = output_dir
+            .file_name()
+            .ok_or_else(|| config_error(format!("Cannot lock the output {}: it has no folder name", output_dir.display())))?
+            .to_string_lossy();
+        // Always in the same order, so two builds can't each hold the lock the other waits for
+        let output = lock(&parent.join(format!(".{name}.lock")), output_dir, config.wait_for_lock)?;
+        let cache = lock(&config.cache_dir.join("build.lock"), &config.cache_dir, config.wait_for_lock)?;
+        Ok(BuildLock { _files: vec![output, cache] })
+    }
+}
+
+/// Takes the lock of `path`, which guards the folder `guarded`.
+fn lock(path: &Path, guarded: &Path, wait: bool) -> io::Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = File::options().create(true).write(true).truncate(false).open(path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) if wait => {
+            info!("Waiting for another build writing to {}...", guarded.display());
+            file.lock()?;
+            Ok(file)
+        }
+        Err(TryLockError::WouldBlock) => Err(io::Error::other(format!(
+            "Another obs2web build is writing to {} (it holds {}); pass --wait to wait for it",
+            guarded.display(),
+            path.display()
+        ))),
+        // Without file locks on the platform, builds go unguarded
+        Err(TryLockError::Error(e)) if e.kind() == io::ErrorKind::Unsupported => Ok(file),
+        Err(TryLockError::Error(e)) => Err(e),
+    }
+}
@@ -1,10 +1,25 @@
+use std::process::ExitCode;
 use clap::Parser;
-use obs2web::{build_site, Args};
+use obs2web::diagnostics::{self, EXIT_CONFIG_ERROR};
+use obs2web::{run, Args};
 
-fn main() -> std::io::Result<()> {
-    let args = Args::parse();
+/// Exits with 0 on success, 1 on failure, 2 when the build had warnings, 3 on
+/// broken links in strict mode, and 4 on invalid configuration or arguments.
+fn main() -> ExitCode {
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            let _ = e.print();
+            return if e.use_stderr() { ExitCode::from(EXIT_CONFIG_ERROR) } else { ExitCode::SUCCESS };
+        }
+    };
+    diagnostics::configure(args.quiet, args.no_color, args.annotations);
 
-    build_site(&args.vault_path, &args.output_dir)?;
-
-    Ok(())
+    match run(&args) {
+        Ok(()) => diagnostics::exit_code(),
+        Err(e) => {
+            diagnostics::error(&e);
+            diagnostics::exit_code_for(&e)
+        }
+    }
 }
@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+use crate::config::Config;
+use crate::diagnostics::{info, warning};
+use crate::http::HttpClient;
+use crate::index::url_for;
+use crate::vfs::OutputSink;
+
+/// File the checksums of a build are written to, in the format of `sha256sum`.
+pub const MANIFEST_FILE: &str = "SHA256SUMS";
+
+/// Writes `SHA256SUMS`, the SHA-256 of every file of the build, one
+/// `<hash>  <path>` line each, which `obs2web verify` and `sha256sum -c` check.
+pub fn write_manifest(output: &dyn OutputSink) -> io::Result<()> {
+    let mut manifest = String::new();
+    let files = output.files()?;
+    for relative in files.iter().filter(|path| *path != Path::new(MANIFEST_FILE)) {
+        manifest.push_str(&format!("{}  {}\n", sha256_hex(&output.read(relative)?), manifest_path(relative)));
+    }
+    output.write(Path::new(MANIFEST_FILE), manifest.as_bytes())?;
+    info!("Wrote checksums of {} files", files.len());
+    Ok(())
+}
+
+/// Checks that a copy of the site, a folder or the `http(s)://` URL it is served
+/// at, holds every file of `manifest` (by default the copy's own `SHA256SUMS`)
+/// unchanged. Each missing or changed file is reported, and the check fails when
+/// there is any; files of a folder the manifest doesn't list are only reported.
+pub fn verify(target: &str, manifest: Option<&Path>, config: &Config) -> io::Result<()> {
+    let remote = target.starts_with("http://") || target.starts_with("https://");
+    let client = HttpClient::new(config);
+    let read = |path: &Path| -> io::Result<Vec<u8>> {
+        if remote {
+            client.get_bytes(&format!("{}{}", target.trim_end_matches('/'), url_for(path)))
+        } else {
+            fs::read(Path::new(target).join(path))
+        }
+    };
+    let manifest = match manifest {
+        Some(path) => fs::read(path)
+            .map_err(|e| io::Error::other(format!("Cannot read manifest {}: {e}", path.display())))?,
+        None => read(Path::new(MANIFEST_FILE))
+            .map_err(|e| io::Error::other(format!("Cannot read {MANIFEST_FILE} of {target}: {e}")))?,
+    };
+    let expected = parse_manifest(&String::from_utf8_lossy(&manifest))?;
+
+    info!("Verifying {} files of {target}", expected.len());
+    let mut problems = 0;
+    for (path, hash) in &expected {
+        match read(path) {
+            Ok(contents) if sha256_hex(&contents) == *hash => {}
+            Ok(_) => {
+                warning!("{} differs from the build", path.display());
+                problems += 1;
+            }
+            Err(e) => {
+                warning!("{} is missing: {e}", path.display());
+                problems += 1;
+            }
+        }
+    }
+    if !remote {
+        for entry in WalkDir::new(target).sort_by_file_name() {
+            let entry = entry.map_err(io::Error::other)?;
+            let relative = entry.path().strip_prefix(target).map_err(io::Error::other)?;
+            if entry.file_type().is_file() && relative != Path::new(MANIFEST_FILE) && !expected.contains_key(relative) {
+                warning!("{} is not part of the build", relative.display());
+            }
+        }
+    }
+    if problems > 0 {
+        return Err(io::Error::other(format!("{problems} of {} files don't match the build", expected.len())));
+    }
+    info!("All {} files match the build.", expected.len());
+    Ok(())
+}
+
+/// Reads the `<hash>  <path>` lines of a manifest, by path.
+fn parse_manifest(manifest: &str) -> io::Result<BTreeMap<PathBuf, String>> {
+    manifest
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            // `sha256sum` marks files read in binary mode with `*` instead of the second space
+            let (hash, path) = line
+                .split_once("  ")
+                .or_else(|| line.split_once(" *"))
+                .ok_or_else(|| io::Error::other(format!("Not a checksum line: {line}")))?;
+            Ok((PathBuf::from(path), hash.to_lowercase()))
+        })
+        .collect()
+}
+
+/// An output-relative path with forward slashes, as manifests list them on every platform.
+fn manifest_path(path: &Path) -> String {
+    path.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+fn sha256_hex(contents: &[u8]) -> String {
+    Sha256::digest(contents).iter().map(|b| format!("{b:02x}")).collect()
+}
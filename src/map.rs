@@ -0,0 +1,80 @@
+use std::io;
+use std::path::Path;
+use serde::Serialize;
+use tera::Tera;
+use crate::config::Config;
+use crate::content::{escape_html, page_context};
+use crate::diagnostics::{info, warning};
+use crate::index::{IndexedNote, SiteIndex};
+use crate::postprocess::with_base_path;
+use crate::template::theme_file;
+use crate::vfs::OutputSink;
+
+/// A note placed on the map.
+#[derive(Serialize)]
+struct MapMarker<'a> {
+    title: &'a str,
+    url: String,
+    lat: f64,
+    lng: f64,
+}
+
+/// Writes `map.html`, which plots every note with a `location` on a Leaflet map
+/// whose markers link to the notes, and the `map.js` that draws it. The page
+/// also lists the places, which is all readers without JavaScript see.
+pub fn write_map(
+    notes: &[IndexedNote],
+    index: &SiteIndex,
+    tera: &Tera,
+    output: &dyn OutputSink,
+    config: &Config,
+) -> io::Result<()> {
+    let mut places = Vec::new();
+    for note in notes {
+        let Some(location) = &note.frontmatter.location else {
+            continue;
+        };
+        match location.coordinates() {
+            Some((lat, lng)) => places.push((note, lat, lng)),
+            None => warning!(file: &note.source; "location is not a latitude and longitude: {location:?}"),
+        }
+    }
+    places.sort_by(|a, b| a.0.title.cmp(&b.0.title));
+
+    // The script reads the URLs as they are, so they get base_path here rather than from RewriteHtml
+    let markers: Vec<MapMarker> = places
+        .iter()
+        .map(|(note, lat, lng)| MapMarker {
+            title: &note.title,
+            url: with_base_path(&note.url, config.base_path.as_deref()),
+            lat: *lat,
+            lng: *lng,
+        })
+        .collect();
+    let mut html = format!(
+        "<div class=\"note-map\" data-markers=\"{}\" data-tiles=\"{}\" data-attribution=\"{}\"></div>\n<ul class=\"map-places\">\n",
+        escape_html(&serde_json::to_string(&markers).map_err(io::Error::other)?),
+        escape_html(&config.map_tiles),
+        escape_html(&config.map_attribution)
+    );
+    for (note, lat, lng) in &places {
+        html.push_str(&format!(
+            "<li><a href=\"{}\">{}</a> <span class=\"map-coordinates\">{lat:.4}, {lng:.4}</span></li>\n",
+            escape_html(&note.url),
+            escape_html(&note.title)
+        ));
+    }
+    html.push_str("</ul>\n");
+
+    let page = Path::new("map.html");
+    let mut context = page_context(page, index, config);
+    context.insert("title", "Map");
+    context.insert("content", &html);
+    let rendered = tera
+        .render("base.html", &context)
+        .map_err(|e| io::Error::other(format!("Template rendering failed for map.html: {e}")))?;
+    output.write(page, rendered.as_bytes())?;
+    output.write(Path::new("map.js"), theme_file("map.js")?.as_bytes())?;
+    info!("Wrote the map of {} places", places.len());
+    Ok(())
+}
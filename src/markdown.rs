@@ -0,0 +1,909 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::LazyLock;
+use comrak::nodes::{Ast, AstNode, LineColumn, NodeDescriptionItem, NodeHtmlBlock, NodeValue};
+use comrak::{format_html, parse_document, Anchorizer, Arena, ComrakOptions};
+use regex::Regex;
+use crate::config::Config;
+use crate::content::escape_html;
+use crate::diagnostics::warning;
+use crate::embeds::{HostedVideo, LinkCard, SocialPost};
+use crate::http::HttpClient;
+
+/// A `^block-id` ending a paragraph, as `blocks` finds them in the source.
+static BLOCK_MARKER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?:^|\s)\^([A-Za-z0-9-]+)\s*$").unwrap());
+
+static CALLOUT_MARKER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[!([A-Za-z0-9_-]+)\]([+-]?)\s*(.*)$").unwrap());
+
+/// Obsidian's callout aliases, mapped to the type that is styled.
+const CALLOUT_ALIASES: &[(&str, &str)] = &[
+    ("summary", "abstract"),
+    ("tldr", "abstract"),
+    ("hint", "tip"),
+    ("check", "success"),
+    ("done", "success"),
+    ("help", "question"),
+    ("faq", "question"),
+    ("attention", "warning"),
+    ("fail", "failure"),
+    ("missing", "failure"),
+    ("error", "danger"),
+    ("cite", "quote"),
+];
+
+/// Renders a note body to HTML, applying the configured transformations to the
+/// parsed document before it is formatted. Heading anchors are numbered after
+/// those `anchors` has already given out, so a page showing part of a note keeps
+/// the anchors the whole note would have.
+pub fn markdown_to_html(
+    markdown: &str,
+    options: &ComrakOptions,
+    config: &Config,
+    source: &Path,
+    mut anchors: Anchorizer,
+) -> String {
+    let arena = Arena::new();
+    let root = parse_document(&arena, markdown, options);
+
+    if options.extension.description_lists {
+        definition_paragraphs(&arena, root);
+    }
+    block_anchors(&arena, root);
+    if config.markdown.subscript {
+        render_subscripts(&arena, root, markdown, config.markdown.strikethrough);
+    }
+    if config.strict {
+        check_image_alt_text(root, source);
+    }
+    if config.shift_headings {
+        shift_headings(root);
+    }
+    if config.number_headings {
+        number_headings(&arena, root);
+    }
+    // Anchors are added here rather than by comrak, which always starts numbering afresh
+    let mut options = Cow::Borrowed(options);
+    if let Some(prefix) = options.extension.header_ids.clone() {
+        anchor_headings(&arena, root, &prefix, &mut anchors);
+        options.to_mut().extension.header_ids = None;
+    }
+    if config.fold_sections {
+        fold_sections(&arena, root);
+    }
+    if config.callouts {
+        render_callouts(&arena, root, markdown);
+    }
+    render_code_blocks(root, config.code_block_headers);
+    if config.video_facades {
+        render_video_facades(&arena, root);
+    }
+    if config.post_embeds || config.link_cards {
+        let http = HttpClient::new(config);
+        if config.post_embeds {
+            embed_posts(&arena, root, &http, source);
+        }
+        if config.link_cards {
+            render_link_cards(&arena, root, &http, source);
+        }
+    }
+    if config.external_link_citations {
+        cite_external_links(&arena, root);
+    }
+
+    let mut html = Vec::new();
+    format_html(root, &options, &mut html).expect("writing HTML to memory cannot fail");
+    String::from_utf8(html).expect("comrak emits UTF-8")
+}
+
+/// Demotes every heading one level (capped at h6), so a page whose template
+/// renders the title as `<h1>` keeps a single top-level heading.
+fn shift_headings<'a>(root: &'a AstNode<'a>) {
+    for node in root.descendants() {
+        if let NodeValue::Heading(heading) = &mut node.data.borrow_mut().value {
+            heading.level = (heading.level + 1).min(6);
+        }
+    }
+}
+
+/// Prefixes headings with their section number (1, 1.1, 1.2, 2, ...), counting
+/// from the page's highest heading level. Anchors keep being derived from the text alone.
+fn number_headings<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let headings: Vec<_> = root
+        .descendants()
+        .filter_map(|node| match &node.data.borrow().value {
+            NodeValue::Heading(heading) => Some((node, heading.level as usize)),
+            _ => None,
+        })
+        .collect();
+    let Some(top) = headings.iter().map(|(_, level)| *level).min() else {
+        return;
+    };
+    let mut counters = [0usize; 6];
+    for (node, level) in headings {
+        let depth = level - top;
+        counters[depth] += 1;
+        counters[depth + 1..].fill(0);
+        let number = counters[..=depth].iter().map(usize::to_string).collect::<Vec<_>>().join(".");
+        node.prepend(arena.alloc(AstNode::new(RefCell::new(Ast::new(
+            NodeValue::HtmlInline(format!("<span class=\"heading-number\">{number}</span> ")),
+            LineColumn { line: 0, column: 0 },
+        )))));
+    }
+}
+
+/// Starts every heading with an empty anchor link whose id `anchors` makes from
+/// the heading's text, as comrak's `header_ids` does.
+fn anchor_headings<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>, prefix: &str, anchors: &mut Anchorizer) {
+    let headings: Vec<_> =
+        root.descendants().filter(|node| matches!(node.data.borrow().value, NodeValue::Heading(_))).collect();
+    for node in headings {
+        let id = anchors.anchorize(plain_text(node));
+        node.prepend(arena.alloc(AstNode::new(RefCell::new(Ast::new(
+            NodeValue::HtmlInline(format!(
+                "<a href=\"#{prefix}{id}\" aria-hidden=\"true\" class=\"anchor\" id=\"{prefix}{id}\"></a>"
+            )),
+            LineColumn { line: 0, column: 0 },
+        )))));
+    }
+}
+
+/// Wraps each H2 and H3 section, the heading and everything up to the next
+/// heading of its level or above, in an open `<details>` whose summary is the
+/// heading, so readers can fold sections away as they can in Obsidian.
+fn fold_sections<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let blocks: Vec<_> = root.children().collect();
+    let mut open: Vec<u8> = Vec::new();
+    for block in blocks {
+        let level = match &block.data.borrow().value {
+            NodeValue::Heading(heading) => heading.level,
+            _ => continue,
+        };
+        if level > 3 {
+            continue;
+        }
+        while open.last().is_some_and(|outer| *outer >= level) {
+            open.pop();
+            block.insert_before(html_block(arena, "</div>\n</details>\n".to_string()));
+        }
+        if level >= 2 {
+            block.insert_before(html_block(arena, "<details class=\"fold\" open>\n<summary>\n".to_string()));
+            block.insert_after(html_block(arena, "</summary>\n<div class=\"fold-content\">\n".to_string()));
+            open.push(level);
+        }
+    }
+    for _ in open {
+        root.append(html_block(arena, "</div>\n</details>\n".to_string()));
+    }
+}
+
+/// The text of a heading written as `text`, without its markup, which its anchor is made from.
+pub fn heading_text(text: &str, options: &ComrakOptions) -> String {
+    let arena = Arena::new();
+    let root = parse_document(&arena, &format!("# {text}"), options);
+    root.first_child().map(plain_text).unwrap_or_default()
+}
+
+/// Turns paragraphs written the way glossaries usually are in Obsidian, a term
+/// followed directly by one or more `: definition` lines, into description lists.
+/// A paragraph can hold several terms, each with its definitions. comrak itself only reads a term and a single definition separated by a blank
+/// line. Terms in consecutive paragraphs join the same list.
+fn definition_paragraphs<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let paragraphs: Vec<_> =
+        root.descendants().filter(|node| matches!(node.data.borrow().value, NodeValue::Paragraph)).collect();
+    for paragraph in paragraphs {
+        let mut lines = vec![Vec::new()];
+        for inline in paragraph.children() {
+            match inline.data.borrow().value {
+                NodeValue::SoftBreak | NodeValue::LineBreak => lines.push(Vec::new()),
+                _ => lines.last_mut().unwrap().push(inline),
+            }
+        }
+        // Each line is a term or a definition; every term needs at least one definition
+        let definitions: Vec<Option<String>> =
+            lines.iter().map(|line| line.first().and_then(|first| definition_text(first))).collect();
+        let is_term = |i: usize| definitions[i].is_none();
+        if !is_term(0) || (0..lines.len()).any(|i| is_term(i) && definitions.get(i + 1).is_none_or(Option::is_none)) {
+            continue;
+        }
+
+        let mut items = Vec::new();
+        for (line, definition) in lines.into_iter().zip(definitions) {
+            let block = match definition {
+                Some(definition) => {
+                    line[0].data.borrow_mut().value = NodeValue::Text(definition);
+                    new_node(arena, NodeValue::DescriptionDetails)
+                }
+                None => {
+                    items.push(new_node(
+                        arena,
+                        NodeValue::DescriptionItem(NodeDescriptionItem { marker_offset: 0, padding: 2 }),
+                    ));
+                    new_node(arena, NodeValue::DescriptionTerm)
+                }
+            };
+            let text = new_node(arena, NodeValue::Paragraph);
+            for inline in line {
+                text.append(inline);
+            }
+            block.append(text);
+            items.last().unwrap().append(block);
+        }
+        let list = match paragraph.previous_sibling() {
+            Some(previous) if matches!(previous.data.borrow().value, NodeValue::DescriptionList) => previous,
+            _ => {
+                let list = new_node(arena, NodeValue::DescriptionList);
+                paragraph.insert_before(list);
+                list
+            }
+        };
+        for item in items {
+            list.append(item);
+        }
+        paragraph.detach();
+        if let Some(next) = list.next_sibling().filter(|next| matches!(next.data.borrow().value, NodeValue::DescriptionList)) {
+            for item in next.children().collect::<Vec<_>>() {
+                list.append(item);
+            }
+            next.detach();
+        }
+    }
+}
+
+/// The text of a definition line's first inline after the `: ` the line starts with.
+fn definition_text(inline: &AstNode) -> Option<String> {
+    match &inline.data.borrow().value {
+        NodeValue::Text(text) => text
+            .strip_prefix(':')
+            .filter(|rest| rest.starts_with([' ', '\t']))
+            .map(|rest| rest.trim_start().to_string()),
+        _ => None,
+    }
+}
+
+fn new_node<'a>(arena: &'a Arena<AstNode<'a>>, value: NodeValue) -> &'a AstNode<'a> {
+    arena.alloc(AstNode::new(RefCell::new(Ast::new(value, LineColumn { line: 0, column: 0 }))))
+}
+
+/// Replaces the `^id` markers Obsidian ends blocks with by an anchor that
+/// `[[Note#^id]]` links to: at the start of the paragraph or list item the marker
+/// ends, or before the block a marker on a line of its own follows.
+fn block_anchors<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let paragraphs: Vec<_> =
+        root.descendants().filter(|node| matches!(node.data.borrow().value, NodeValue::Paragraph)).collect();
+    for paragraph in paragraphs {
+        let Some(last) = paragraph.last_child() else {
+            continue;
+        };
+        let id = {
+            let mut value = last.data.borrow_mut();
+            let NodeValue::Text(text) = &mut value.value else {
+                continue;
+            };
+            let Some(marker) = BLOCK_MARKER.captures(text) else {
+                continue;
+            };
+            let id = marker[1].to_string();
+            let start = marker.get(0).unwrap().start();
+            text.truncate(start);
+            id
+        };
+        // A marker on the line after the text: drop the line break before it
+        if last.data.borrow().value.text().is_some_and(|text| text.is_empty()) {
+            if let Some(previous) = last.previous_sibling()
+                && matches!(previous.data.borrow().value, NodeValue::SoftBreak | NodeValue::LineBreak)
+            {
+                previous.detach();
+            }
+            last.detach();
+        }
+        let anchor = format!("<a class=\"block-anchor\" id=\"^{id}\"></a>");
+        if paragraph.first_child().is_some() {
+            paragraph.prepend(new_node(arena, NodeValue::HtmlInline(anchor)));
+            continue;
+        }
+        let block = html_block(arena, format!("{anchor}\n"));
+        match paragraph.previous_sibling() {
+            Some(previous) => previous.insert_before(block),
+            None => paragraph.insert_before(block),
+        }
+        paragraph.detach();
+    }
+}
+
+/// Renders text between single tildes (`H~2~O`) as subscript. comrak reads both
+/// `~x~` and `~~x~~` as strikethrough, so the source tells them apart; double
+/// tildes stay strikethrough, or are kept as written when `strikethrough` is off.
+fn render_subscripts<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>, markdown: &str, strikethrough: bool) {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let struck: Vec<_> =
+        root.descendants().filter(|node| matches!(node.data.borrow().value, NodeValue::Strikethrough)).collect();
+    for node in struck {
+        let position = node.data.borrow().sourcepos;
+        let opener = lines.get(position.start.line.wrapping_sub(1)).and_then(|line| line.get(position.start.column - 1..));
+        let (open, close) = match opener {
+            Some(text) if text.starts_with('~') && !text.starts_with("~~") => {
+                (NodeValue::HtmlInline("<sub>".to_string()), NodeValue::HtmlInline("</sub>".to_string()))
+            }
+            _ if strikethrough => continue,
+            _ => (NodeValue::Text("~~".to_string()), NodeValue::Text("~~".to_string())),
+        };
+        node.insert_before(new_node(arena, open));
+        for child in node.children().collect::<Vec<_>>() {
+            node.insert_before(child);
+        }
+        node.insert_before(new_node(arena, close));
+        node.detach();
+    }
+}
+
+/// Reports markdown images (`![](image.png)`) written without alt text.
+fn check_image_alt_text<'a>(root: &'a AstNode<'a>, source: &Path) {
+    for node in root.descendants() {
+        if let NodeValue::Image(image) = &node.data.borrow().value
+            && node.children().next().is_none()
+        {
+            warning!(file: source, near: &image.url; "image {} has no alt text in {}", image.url, source.display());
+        }
+    }
+}
+
+/// Rewrites the Admonition plugin's code fences (```` ```ad-warning ````), with their
+/// optional `title:` and `collapse: open|closed` lines, as `> [!warning]` callouts,
+/// so older vaults render the same callouts. Nested admonitions, opened with a
+/// longer fence, become nested callouts. Other fenced code is left alone.
+pub fn admonitions_to_callouts(markdown: &str) -> String {
+    let lines: Vec<&str> = markdown.lines().collect();
+    admonition_lines(&lines).join("\n")
+}
+
+fn admonition_lines(lines: &[&str]) -> Vec<String> {
+    let mut out = Vec::with_capacity(lines.len());
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        let Some((fence, info)) = code_fence(line) else {
+            out.push(line.to_string());
+            continue;
+        };
+        let end = lines[i..]
+            .iter()
+            .position(|l| code_fence(l).is_some_and(|(close, info)| info.is_empty() && close.starts_with(fence)))
+            .map_or(lines.len(), |n| i + n);
+        let body = &lines[i..end];
+        let Some(kind) = info.strip_prefix("ad-").filter(|kind| !kind.is_empty()) else {
+            out.push(line.to_string());
+            out.extend(body.iter().map(|l| l.to_string()));
+            if end < lines.len() {
+                out.push(lines[end].to_string());
+            }
+            i = end + 1;
+            continue;
+        };
+        i = end + 1;
+
+        let mut title = String::new();
+        let mut fold = "";
+        let mut start = 0;
+        for option in body {
+            match option.split_once(':').map(|(key, value)| (key.trim(), value.trim())) {
+                Some(("title", value)) => title = value.to_string(),
+                Some(("collapse", "closed")) => fold = "-",
+                Some(("collapse", "open")) => fold = "+",
+                Some(("collapse" | "icon" | "color", _)) => {}
+                _ => break,
+            }
+            start += 1;
+        }
+        let indent = &line[..line.len() - line.trim_start().len()];
+        out.push(format!("{indent}> [!{kind}]{fold} {title}").trim_end().to_string());
+        for inner in admonition_lines(&body[start..]) {
+            out.push(format!("{indent}> {}", inner.strip_prefix(indent).unwrap_or(&inner)).trim_end().to_string());
+        }
+        out.push(String::new());
+    }
+    out
+}
+
+/// The fence (```` ``` ```` or `~~~`, at least three long) and info string of a line opening or closing fenced code.
+fn code_fence(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim_start();
+    let marker = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.len() - trimmed.trim_start_matches(marker).len();
+    (len >= 3).then(|| (&trimmed[..len], trimmed[len..].trim()))
+}
+
+/// Turns blockquotes starting with `[!type] Title` into callouts. This covers
+/// Obsidian callouts, including foldable `[!type]-`/`[!type]+` ones, and
+/// GitHub alerts such as `> [!NOTE]`, which use the same marker. Spoilers,
+/// `[!spoiler]` callouts and blockquotes written `>! text`, start folded, so
+/// readers click to reveal them.
+fn render_callouts<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>, markdown: &str) {
+    let lines: Vec<&str> = markdown.lines().collect();
+    let quotes: Vec<_> = root
+        .descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::BlockQuote))
+        .collect();
+    for quote in quotes {
+        let Some(paragraph) = quote.first_child().filter(|p| matches!(p.data.borrow().value, NodeValue::Paragraph))
+        else {
+            continue;
+        };
+        let first_line: Vec<_> = paragraph
+            .children()
+            .take_while(|n| !matches!(n.data.borrow().value, NodeValue::SoftBreak | NodeValue::LineBreak))
+            .collect();
+        let line: String = first_line.iter().map(|n| plain_text(n)).collect();
+        let (name, fold, title) = if let Some(caps) = CALLOUT_MARKER.captures(line.trim()) {
+            // Drop the marker line; the rest of the paragraph stays as the callout's first block
+            if let Some(last) = first_line.last()
+                && let Some(line_break) = last.next_sibling()
+            {
+                line_break.detach();
+            }
+            for node in first_line {
+                node.detach();
+            }
+            if paragraph.first_child().is_none() {
+                paragraph.detach();
+            }
+            (caps[1].to_lowercase(), caps[2].to_string(), caps[3].trim().to_string())
+        } else if is_spoiler_quote(quote, &lines) {
+            strip_spoiler_markers(quote);
+            ("spoiler".to_string(), String::new(), String::new())
+        } else {
+            continue;
+        };
+
+        let kind = CALLOUT_ALIASES.iter().find(|(alias, _)| *alias == name).map_or(name.as_str(), |(_, kind)| kind);
+        let title = if title.is_empty() {
+            let mut chars = name.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
+        } else {
+            title
+        };
+        // A spoiler is folded unless its marker says otherwise
+        let fold = match fold.as_str() {
+            "" if kind == "spoiler" => "-",
+            fold => fold,
+        };
+        let attrs = format!("class=\"callout callout-{}\" data-callout=\"{}\"", escape_html(kind), escape_html(&name));
+        let (open, close) = match fold {
+            "" => (
+                format!("<div {attrs}>\n<div class=\"callout-title\">{}</div>\n<div class=\"callout-content\">\n", escape_html(&title)),
+                "</div>\n</div>\n",
+            ),
+            fold => (
+                format!(
+                    "<details {attrs}{}>\n<summary class=\"callout-title\">{}</summary>\n<div class=\"callout-content\">\n",
+                    if fold == "+" { " open" } else { "" },
+                    escape_html(&title)
+                ),
+                "</div>\n</details>\n",
+            ),
+        };
+
+        quote.insert_before(html_block(arena, open));
+        let children: Vec<_> = quote.children().collect();
+        for child in children {
+            quote.insert_before(child);
+        }
+        quote.insert_before(html_block(arena, close.to_string()));
+        quote.detach();
+    }
+}
+
+/// Whether the quote is written `>! text`, with the `!` right after the `>`; a quote
+/// such as `> !important` only starts with an exclamation mark.
+fn is_spoiler_quote(quote: &AstNode, lines: &[&str]) -> bool {
+    let position = quote.data.borrow().sourcepos;
+    lines
+        .get(position.start.line.wrapping_sub(1))
+        .and_then(|line| line.get(position.start.column.saturating_sub(1)..))
+        .is_some_and(|text| text.starts_with(">!"))
+}
+
+/// Removes the `!` starting each line of a `>! text` spoiler, and the
+/// paragraphs left empty by lines that held only the marker.
+fn strip_spoiler_markers<'a>(quote: &'a AstNode<'a>) {
+    let paragraphs: Vec<_> =
+        quote.children().filter(|node| matches!(node.data.borrow().value, NodeValue::Paragraph)).collect();
+    for paragraph in paragraphs {
+        let mut line_start = true;
+        for node in paragraph.children() {
+            let mut data = node.data.borrow_mut();
+            match &mut data.value {
+                NodeValue::Text(text) if line_start => {
+                    if let Some(rest) = text.strip_prefix('!') {
+                        *text = rest.trim_start().to_string();
+                    }
+                    line_start = false;
+                }
+                NodeValue::SoftBreak | NodeValue::LineBreak => line_start = true,
+                _ => line_start = false,
+            }
+        }
+        let text_only = paragraph.children().all(|node| {
+            matches!(node.data.borrow().value, NodeValue::Text(_) | NodeValue::SoftBreak | NodeValue::LineBreak)
+        });
+        if text_only && plain_text(paragraph).trim().is_empty() {
+            paragraph.detach();
+        }
+    }
+}
+
+/// Replaces external links with their text and a numbered reference marker, and
+/// appends a list of the referenced URLs to the document. Links to the same URL
+/// share a number; bare URLs are left alone since they already show the address.
+fn cite_external_links<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let links: Vec<_> = root
+        .descendants()
+        .filter(|node| match &node.data.borrow().value {
+            NodeValue::Link(link) => {
+                (link.url.starts_with("http://") || link.url.starts_with("https://"))
+                    && node.children().map(plain_text).collect::<String>() != link.url
+            }
+            _ => false,
+        })
+        .collect();
+
+    let mut references: Vec<(String, String)> = Vec::new();
+    for link in links {
+        let (url, title) = match &link.data.borrow().value {
+            NodeValue::Link(link) => (link.url.clone(), link.title.clone()),
+            _ => continue,
+        };
+        // The list links back to the first citation of each URL
+        let (number, id) = match references.iter().position(|(u, _)| *u == url) {
+            Some(i) => (i + 1, String::new()),
+            None => {
+                references.push((url, title));
+                (references.len(), format!(" id=\"cite-ref-{}\"", references.len()))
+            }
+        };
+        let children: Vec<_> = link.children().collect();
+        for child in children {
+            link.insert_before(child);
+        }
+        let marker = format!(
+            "<sup class=\"citation-ref\"><a href=\"#cite-{number}\"{id}>[{number}]</a></sup>"
+        );
+        link.insert_before(arena.alloc(AstNode::new(RefCell::new(Ast::new(
+            NodeValue::HtmlInline(marker),
+            LineColumn { line: 0, column: 0 },
+        )))));
+        link.detach();
+    }
+    if references.is_empty() {
+        return;
+    }
+
+    let mut list = String::from("<section class=\"references\">\n<h2>References</h2>\n<ol>\n");
+    for (i, (url, title)) in references.iter().enumerate() {
+        let number = i + 1;
+        let text = if title.is_empty() { url } else { title };
+        list.push_str(&format!(
+            "<li id=\"cite-{number}\"><a href=\"{}\">{}</a> <a class=\"citation-back\" href=\"#cite-ref-{number}\" aria-label=\"Back to text\">↩</a></li>\n",
+            escape_html(url),
+            escape_html(text)
+        ));
+    }
+    list.push_str("</ol>\n</section>\n");
+    root.append(html_block(arena, list));
+}
+
+/// Replaces `![title](https://youtu.be/...)` images, and YouTube or Vimeo URLs
+/// written alone in a paragraph, with click-to-load video facades.
+fn render_video_facades<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>) {
+    let videos: Vec<_> = root
+        .descendants()
+        .filter_map(|node| {
+            let (url, title) = match &node.data.borrow().value {
+                NodeValue::Image(image) => (image.url.clone(), plain_text(node)),
+                // A bare URL, autolinked, rather than a link with its own text
+                NodeValue::Link(link) if plain_text(node) == link.url => (link.url.clone(), String::new()),
+                _ => return None,
+            };
+            let video = HostedVideo::parse(&url)?;
+            Some((node, url, title, video))
+        })
+        .collect();
+    for (node, url, title, video) in videos {
+        let title = if !title.trim().is_empty() {
+            title
+        } else if video.host == "youtube" {
+            "YouTube video".to_string()
+        } else {
+            "Vimeo video".to_string()
+        };
+        let html = video.facade_html(&url, &title);
+        let parent = node.parent().filter(|p| matches!(p.data.borrow().value, NodeValue::Paragraph));
+        let alone = parent.is_some_and(|p| {
+            p.children().all(|child| {
+                child.same_node(node) || matches!(&child.data.borrow().value, NodeValue::Text(t) if t.trim().is_empty())
+            })
+        });
+        match parent {
+            Some(paragraph) if alone => {
+                paragraph.insert_before(html_block(arena, format!("{html}\n")));
+                paragraph.detach();
+            }
+            // Links within text stay links; images get the facade in their place
+            _ if matches!(node.data.borrow().value, NodeValue::Link(_)) => {}
+            _ => {
+                node.insert_before(arena.alloc(AstNode::new(RefCell::new(Ast::new(
+                    NodeValue::HtmlInline(html),
+                    LineColumn { line: 0, column: 0 },
+                )))));
+                node.detach();
+            }
+        }
+    }
+}
+
+/// Replaces paragraphs holding nothing but the URL of a tweet or Mastodon post
+/// with a copy of the post. Posts that can't be fetched stay links.
+fn embed_posts<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>, http: &HttpClient, source: &Path) {
+    let posts: Vec<_> = root
+        .descendants()
+        .filter_map(|node| {
+            let url = match &node.data.borrow().value {
+                NodeValue::Link(link) if plain_text(node) == link.url => link.url.clone(),
+                _ => return None,
+            };
+            let paragraph = node.parent().filter(|p| matches!(p.data.borrow().value, NodeValue::Paragraph))?;
+            let alone = paragraph.children().all(|child| {
+                child.same_node(node) || matches!(&child.data.borrow().value, NodeValue::Text(t) if t.trim().is_empty())
+            });
+            Some((paragraph, SocialPost::parse(&url).filter(|_| alone)?, url))
+        })
+        .collect();
+    for (paragraph, post, url) in posts {
+        match post.embed_html(&url, http) {
+            Ok(html) => {
+                paragraph.insert_before(html_block(arena, format!("{html}\n")));
+                paragraph.detach();
+            }
+            Err(e) => warning!(file: source, near: &url; "could not embed {url} in {}: {e}", source.display()),
+        }
+    }
+}
+
+/// Replaces external links that are alone in their paragraph, or list item,
+/// with bookmark cards. Links whose page can't be fetched stay links.
+fn render_link_cards<'a>(arena: &'a Arena<AstNode<'a>>, root: &'a AstNode<'a>, http: &HttpClient, source: &Path) {
+    let links: Vec<_> = root
+        .descendants()
+        .filter_map(|node| {
+            let url = match &node.data.borrow().value {
+                NodeValue::Link(link) if link.url.starts_with("http://") || link.url.starts_with("https://") => {
+                    link.url.clone()
+                }
+                _ => return None,
+            };
+            let paragraph = node.parent().filter(|p| matches!(p.data.borrow().value, NodeValue::Paragraph))?;
+            let alone = paragraph.children().all(|child| {
+                child.same_node(node) || matches!(&child.data.borrow().value, NodeValue::Text(t) if t.trim().is_empty())
+            });
+            alone.then(|| (paragraph, url, plain_text(node)))
+        })
+        .collect();
+    for (paragraph, url, text) in links {
+        match LinkCard::fetch(&url, http) {
+            Ok(card) => {
+                paragraph.insert_before(html_block(arena, format!("{}\n", card.html(&url, &text))));
+                paragraph.detach();
+            }
+            Err(e) => warning!(file: source, near: &url; "could not fetch a link card for {url} in {}: {e}", source.display()),
+        }
+    }
+}
+
+fn html_block<'a>(arena: &'a Arena<AstNode<'a>>, literal: String) -> &'a AstNode<'a> {
+    let value = NodeValue::HtmlBlock(NodeHtmlBlock { block_type: 6, literal });
+    arena.alloc(AstNode::new(RefCell::new(Ast::new(value, LineColumn { line: 0, column: 0 }))))
+}
+
+/// The text of the first paragraph of `markdown` that has any, without markup,
+/// cut at a word boundary to at most `max_chars` characters.
+pub fn summary(markdown: &str, options: &ComrakOptions, max_chars: usize) -> String {
+    let arena = Arena::new();
+    let root = parse_document(&arena, markdown, options);
+    block_anchors(&arena, root);
+    let text = root
+        .children()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::Paragraph))
+        .map(|node| plain_text(node).split_whitespace().collect::<Vec<_>>().join(" "))
+        .find(|text| !text.is_empty())
+        .unwrap_or_default();
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let cut = text.char_indices().nth(max_chars).map_or(text.len(), |(i, _)| i);
+    let end = text[..cut].rfind(' ').unwrap_or(cut);
+    format!("{}…", text[..end].trim_end_matches([',', ';', ':', '.']))
+}
+
+/// The text of `markdown` without markup, its paragraphs, headings, and table
+/// cells joined by spaces.
+pub fn block_text(markdown: &str, options: &ComrakOptions) -> String {
+    let arena = Arena::new();
+    let root = parse_document(&arena, markdown, options);
+    let texts: Vec<String> = root
+        .descendants()
+        .filter(|node| node.data.borrow().value.contains_inlines())
+        .map(plain_text)
+        .collect();
+    texts.join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The text of a whole Markdown document without markup: a paragraph per block,
+/// list items on lines of their own marked with `-`, table rows with their cells
+/// separated by tabs, and code blocks as written. Raw HTML is left out.
+pub fn document_text(markdown: &str, options: &ComrakOptions) -> String {
+    let arena = Arena::new();
+    let root = parse_document(&arena, markdown, options);
+    let mut text = String::new();
+    // Whether the last block was a list item or table row, which follow each other line by line
+    let mut last_line = false;
+    for node in root.descendants() {
+        let (block, line) = match &node.data.borrow().value {
+            NodeValue::CodeBlock(code) => (code.literal.trim_end().to_string(), false),
+            NodeValue::TableRow(_) => (node.children().map(plain_text).collect::<Vec<_>>().join("\t"), true),
+            NodeValue::Paragraph | NodeValue::Heading(_) => {
+                let in_item = node.parent().is_some_and(|parent| {
+                    matches!(parent.data.borrow().value, NodeValue::Item(_) | NodeValue::TaskItem(_))
+                });
+                if in_item && node.previous_sibling().is_none() {
+                    let depth = node.ancestors().filter(|node| matches!(node.data.borrow().value, NodeValue::List(_))).count();
+                    (format!("{}- {}", "  ".repeat(depth.saturating_sub(1)), plain_text(node)), true)
+                } else {
+                    (plain_text(node), false)
+                }
+            }
+            _ => continue,
+        };
+        if block.trim().is_empty() {
+            continue;
+        }
+        if !text.is_empty() {
+            text.push_str(if line && last_line { "\n" } else { "\n\n" });
+        }
+        text.push_str(&block);
+        last_line = line;
+    }
+    text + "\n"
+}
+
+/// The text content of an inline node and its children, without markup.
+fn plain_text<'a>(node: &'a AstNode<'a>) -> String {
+    match &node.data.borrow().value {
+        NodeValue::Text(text) => text.clone(),
+        NodeValue::Code(code) => code.literal.clone(),
+        NodeValue::SoftBreak | NodeValue::LineBreak => " ".to_string(),
+        _ => node.children().map(plain_text).collect(),
+    }
+}
+
+/// A fence info string such as `rust title="main.rs" {3-5}`: the language
+/// followed by `key=value` attributes, bare flags, and a `{...}` line range spec.
+struct CodeInfo {
+    language: Option<String>,
+    attrs: HashMap<String, String>,
+    flags: Vec<String>,
+    highlight: Vec<(usize, usize)>,
+}
+
+impl CodeInfo {
+    fn line_numbers(&self) -> bool {
+        !self.highlight.is_empty()
+            || self.flags.iter().any(|f| matches!(f.as_str(), "linenos" | "showLineNumbers"))
+    }
+
+    fn is_highlighted(&self, line: usize) -> bool {
+        self.highlight.iter().any(|&(start, end)| (start..=end).contains(&line))
+    }
+}
+
+fn parse_code_info(info: &str) -> CodeInfo {
+    let mut code_info = CodeInfo { language: None, attrs: HashMap::new(), flags: Vec::new(), highlight: Vec::new() };
+    let mut rest = info.trim();
+    while !rest.is_empty() {
+        if let Some(spec) = rest.strip_prefix('{') {
+            let (ranges, remainder) = spec.split_once('}').unwrap_or((spec, ""));
+            code_info.highlight.extend(parse_line_ranges(ranges));
+            rest = remainder.trim_start();
+            continue;
+        }
+        let end = rest.find(|c: char| c.is_whitespace() || c == '=' || c == '{').unwrap_or(rest.len());
+        let key = &rest[..end];
+        rest = &rest[end..];
+        if let Some(value) = rest.strip_prefix('=') {
+            let (value, remainder) = match value.strip_prefix('"') {
+                Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+                None => value.split_once(char::is_whitespace).unwrap_or((value, "")),
+            };
+            code_info.attrs.insert(key.to_string(), value.to_string());
+            rest = remainder;
+        } else if code_info.language.is_none() && code_info.attrs.is_empty() && code_info.flags.is_empty() {
+            code_info.language = Some(key.to_string());
+        } else {
+            code_info.flags.push(key.to_string());
+        }
+        rest = rest.trim_start();
+    }
+    code_info
+}
+
+/// Parses `3-5,8` into inclusive line ranges.
+fn parse_line_ranges(spec: &str) -> Vec<(usize, usize)> {
+    spec.split(',')
+        .filter_map(|part| {
+            let part = part.trim();
+            let (start, end) = part.split_once('-').unwrap_or((part, part));
+            Some((start.trim().parse().ok()?, end.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Renders fenced code blocks with a header bar showing the block's `title` (if
+/// any) and a copy button that copy-code.js wires up. Blocks with a `{3-5}`
+/// range or a `linenos` flag are split into numbered lines, with the listed
+/// lines highlighted.
+fn render_code_blocks<'a>(root: &'a AstNode<'a>, headers: bool) {
+    for node in root.descendants() {
+        let mut data = node.data.borrow_mut();
+        let NodeValue::CodeBlock(code) = &data.value else {
+            continue;
+        };
+        if !code.fenced {
+            continue;
+        }
+        let info = parse_code_info(&code.info);
+        if !headers && !info.line_numbers() {
+            continue;
+        }
+
+        let mut pre_class = String::new();
+        let body = if info.line_numbers() {
+            pre_class.push_str(" class=\"line-numbers\"");
+            code.literal
+                .strip_suffix('\n')
+                .unwrap_or(&code.literal)
+                .split('\n')
+                .enumerate()
+                .map(|(i, line)| {
+                    let class = if info.is_highlighted(i + 1) { "line highlighted" } else { "line" };
+                    format!("<span class=\"{class}\" data-line=\"{}\">{}</span>\n", i + 1, escape_html(line))
+                })
+                .collect::<String>()
+        } else {
+            escape_html(&code.literal)
+        };
+        let class = info
+            .language
+            .as_ref()
+            .map(|lang| format!(" class=\"language-{}\"", escape_html(lang)))
+            .unwrap_or_default();
+        let pre = format!("<pre{pre_class}><code{class}>{body}</code></pre>\n");
+
+        let html = if headers {
+            let title = info
+                .attrs
+                .get("title")
+                .map(|title| format!("<span class=\"code-title\">{}</span>", escape_html(title)))
+                .unwrap_or_default();
+            format!(
+                "<div class=\"code-block\">\n<div class=\"code-header\">{title}\
+                 <button type=\"button\" class=\"copy-code\" aria-label=\"Copy code\">Copy</button></div>\n\
+                 {pre}</div>\n"
+            )
+        } else {
+            pre
+        };
+        data.value = NodeValue::HtmlBlock(NodeHtmlBlock { block_type: 6, literal: html });
+    }
+}
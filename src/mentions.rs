@@ -0,0 +1,115 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::LazyLock;
+use regex::{Regex, RegexBuilder};
+use crate::index::{IndexedNote, SiteIndex};
+use crate::links::find_wikilinks;
+use crate::placeholders::code_ranges;
+
+/// Titles shorter than this are too likely to be ordinary words to count as mentions.
+const MIN_TITLE_CHARS: usize = 3;
+
+/// Markdown links, autolinks, bare URLs, and HTML tags, whose text isn't prose.
+static NOT_PROSE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"!?\[[^\]\n]*\]\([^)\n]*\)|<[^>\n]+>|https?://[^\s)>\]]+").unwrap());
+
+/// A note's title written in another note that doesn't link to it.
+pub struct Mention {
+    /// Index of the note the title is written in
+    pub note: usize,
+    /// Index of the note whose title it is
+    pub target: usize,
+    /// Byte range of the title in the mentioning note's body
+    pub range: Range<usize>,
+}
+
+/// Finds where notes write the titles of other notes, as whole words in any case,
+/// without linking to them anywhere, the way Obsidian's "Unlinked mentions" pane
+/// does. Code, links, URLs, and HTML tags are left out. Mentions are in body order.
+pub fn unlinked_mentions(index: &SiteIndex) -> Vec<Mention> {
+    let mut titles: Vec<(String, usize)> = index
+        .notes
+        .iter()
+        .enumerate()
+        .map(|(i, note)| (note.title.trim().to_lowercase(), i))
+        .filter(|(title, _)| title.chars().count() >= MIN_TITLE_CHARS)
+        .collect();
+    if titles.is_empty() {
+        return Vec::new();
+    }
+    // Longer titles first, so "Rust Macros" is found rather than "Rust" within it
+    titles.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+    let by_title: HashMap<&str, usize> = titles.iter().rev().map(|(title, i)| (title.as_str(), *i)).collect();
+    let pattern = titles.iter().map(|(title, _)| regex::escape(title)).collect::<Vec<_>>().join("|");
+    let Ok(finder) = RegexBuilder::new(&pattern).case_insensitive(true).size_limit(1 << 28).build() else {
+        return Vec::new();
+    };
+    let positions: HashMap<&Path, usize> =
+        index.notes.iter().enumerate().map(|(i, note)| (note.source.as_path(), i)).collect();
+
+    let mut mentions = Vec::new();
+    for (i, note) in index.notes.iter().enumerate() {
+        let linked: HashSet<usize> = index
+            .outgoing_links(note, &note.body)
+            .into_iter()
+            .filter_map(|target| positions.get(target.source.as_path()).copied())
+            .collect();
+        let mut skipped = code_ranges(&note.body);
+        skipped.extend(find_wikilinks(&note.body).map(|(range, _)| range));
+        skipped.extend(NOT_PROSE.find_iter(&note.body).map(|m| m.range()));
+        for found in finder.find_iter(&note.body) {
+            let Some(&target) = by_title.get(found.as_str().to_lowercase().as_str()) else {
+                continue;
+            };
+            if target == i || linked.contains(&target) || !is_word(&note.body, found.range()) {
+                continue;
+            }
+            if skipped.iter().any(|range| range.start < found.end() && found.start() < range.end) {
+                continue;
+            }
+            mentions.push(Mention { note: i, target, range: found.range() });
+        }
+    }
+    mentions
+}
+
+/// Whether `range` of `text` isn't part of a longer word.
+fn is_word(text: &str, range: Range<usize>) -> bool {
+    let before = text[..range.start].chars().next_back();
+    let after = text[range.end..].chars().next();
+    !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+}
+
+/// Turns the first unlinked mention of each note within every note into a
+/// wikilink to it, keeping the text as written. Returns the new bodies of the
+/// notes that changed, by index.
+pub fn link_mentions(notes: &[IndexedNote], mentions: &[Mention]) -> Vec<(usize, String)> {
+    let mut first: HashMap<(usize, usize), &Range<usize>> = HashMap::new();
+    for mention in mentions {
+        first.entry((mention.note, mention.target)).or_insert(&mention.range);
+    }
+    let mut by_note: HashMap<usize, Vec<(&Range<usize>, usize)>> = HashMap::new();
+    for ((note, target), range) in first {
+        by_note.entry(note).or_default().push((range, target));
+    }
+    let mut linked: Vec<(usize, String)> = by_note
+        .into_iter()
+        .map(|(i, mut found)| {
+            found.sort_by_key(|(range, _)| range.start);
+            let body = &notes[i].body;
+            let mut linked = String::with_capacity(body.len());
+            let mut last = 0;
+            for (range, target) in found {
+                let path = notes[target].source.with_extension("").to_string_lossy().replace('\\', "/");
+                linked.push_str(&body[last..range.start]);
+                linked.push_str(&format!("[[{path}|{}]]", &body[range.clone()]));
+                last = range.end;
+            }
+            linked.push_str(&body[last..]);
+            (i, linked)
+        })
+        .collect();
+    linked.sort_by_key(|(i, _)| *i);
+    linked
+}
@@ -0,0 +1,120 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::Path;
+use comrak::ComrakOptions;
+use serde::Serialize;
+use serde_json::Value;
+use crate::config::Config;
+use crate::content::make_comrak_options;
+use crate::diagnostics::info;
+use crate::index::{IndexedNote, SiteIndex};
+use crate::links::replace_wikilinks;
+use crate::markdown::block_text;
+use crate::postprocess::with_base_path;
+use crate::vfs::OutputSink;
+
+#[derive(Serialize)]
+struct NoteMetadata<'a> {
+    title: &'a str,
+    url: String,
+    date: Option<&'a str>,
+    updated: Option<&'a str>,
+    tags: &'a [String],
+    fields: &'a BTreeMap<String, Value>,
+    summary: String,
+    links: Vec<LinkedNote<'a>>,
+    backlinks: Vec<LinkedNote<'a>>,
+    blocks: Vec<BlockMetadata<'a>>,
+    artifacts: Vec<ArtifactMetadata<'a>>,
+}
+
+/// A file generated for the note's page by a `[[page_artifact]]` command.
+#[derive(Serialize)]
+struct ArtifactMetadata<'a> {
+    name: &'a str,
+    url: String,
+    mime_type: Option<&'a str>,
+}
+
+/// A block marked with `^id`, addressable at `url`.
+#[derive(Serialize)]
+struct BlockMetadata<'a> {
+    id: &'a str,
+    url: String,
+    /// The block's text without markup
+    text: String,
+}
+
+#[derive(Serialize)]
+struct LinkedNote<'a> {
+    title: &'a str,
+    url: String,
+}
+
+impl<'a> LinkedNote<'a> {
+    fn new(note: &'a IndexedNote, config: &Config) -> Self {
+        LinkedNote { title: &note.title, url: with_base_path(&note.url, config.base_path.as_deref()) }
+    }
+}
+
+/// Writes a `.json` file next to the page of each of `notes` with its title,
+/// dates, tags, summary, `^id` blocks, generated artifacts, and the notes it links to and is linked from, so
+/// scripts and other tools can read one note without loading the whole site.
+pub fn write_note_metadata(notes: &[IndexedNote], index: &SiteIndex, output: &dyn OutputSink, config: &Config) -> io::Result<()> {
+    let links: HashMap<&Path, Vec<&IndexedNote>> =
+        index.notes.iter().map(|note| (note.source.as_path(), index.outgoing_links(note, &note.body))).collect();
+    let mut backlinks: HashMap<&Path, Vec<&IndexedNote>> = HashMap::new();
+    for note in &index.notes {
+        for target in &links[note.source.as_path()] {
+            backlinks.entry(target.source.as_path()).or_default().push(note);
+        }
+    }
+
+    let comrak_options = make_comrak_options(config);
+    for note in notes {
+        let mut backlinks: Vec<LinkedNote> =
+            backlinks.get(note.source.as_path()).into_iter().flatten().map(|&n| LinkedNote::new(n, config)).collect();
+        backlinks.sort_by(|a, b| a.title.cmp(b.title).then_with(|| a.url.cmp(&b.url)));
+        let metadata = NoteMetadata {
+            title: &note.title,
+            url: with_base_path(&note.url, config.base_path.as_deref()),
+            date: note.frontmatter.date.as_deref(),
+            updated: note.updated.as_deref(),
+            tags: note.frontmatter.tags.as_deref().unwrap_or_default(),
+            fields: &note.fields,
+            summary: note.summary(&comrak_options),
+            links: links[note.source.as_path()].iter().map(|&n| LinkedNote::new(n, config)).collect(),
+            backlinks,
+            blocks: note_blocks(note, &comrak_options, config),
+            artifacts: note
+                .artifacts
+                .iter()
+                .map(|artifact| ArtifactMetadata {
+                    name: &artifact.name,
+                    url: with_base_path(&artifact.url, config.base_path.as_deref()),
+                    mime_type: artifact.mime_type,
+                })
+                .collect(),
+        };
+        let json = serde_json::to_string_pretty(&metadata).map_err(io::Error::other)?;
+        output.write(&note.output.with_extension("json"), json.as_bytes())?;
+    }
+    info!("Wrote metadata of {} notes", notes.len());
+    Ok(())
+}
+
+fn note_blocks<'a>(note: &'a IndexedNote, comrak_options: &ComrakOptions, config: &Config) -> Vec<BlockMetadata<'a>> {
+    note.blocks
+        .iter()
+        .map(|block| {
+            let anchor = format!("^{}", block.id);
+            let url = format!("{}#{anchor}", note.heading_url(&anchor));
+            let text = replace_wikilinks(&block.markdown, |link| if link.embed { String::new() } else { link.label() });
+            BlockMetadata {
+                id: &block.id,
+                url: with_base_path(&url, config.base_path.as_deref()),
+                text: block_text(&text, comrak_options),
+            }
+        })
+        .collect()
+}
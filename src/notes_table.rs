@@ -0,0 +1,70 @@
+use std::io;
+use std::path::Path;
+use tera::{Tera, Value};
+use crate::config::Config;
+use crate::content::{escape_html, page_context};
+use crate::dates::{format_date, parse_date, site_timezone};
+use crate::index::{IndexedNote, SiteIndex};
+use crate::vfs::OutputSink;
+
+/// Writes `notes.html`, a table of every note with its folder, tags, date, and
+/// word count, which notes-table.js sorts by column and filters as the reader
+/// types. A flat alternative to the index's folder tree for large vaults.
+pub fn write_notes_table(
+    notes: &[IndexedNote],
+    index: &SiteIndex,
+    tera: &Tera,
+    output: &dyn OutputSink,
+    config: &Config,
+) -> io::Result<()> {
+    let mut notes: Vec<&IndexedNote> = notes.iter().collect();
+    notes.sort_by_cached_key(|note| note.title.to_lowercase());
+
+    let page = Path::new("notes.html");
+    let mut context = page_context(page, index, config);
+    context.insert("title", "All notes");
+    context.insert("content", &notes_table_html(&notes, config)?);
+    let rendered = tera
+        .render("base.html", &context)
+        .map_err(|e| io::Error::other(format!("Template rendering failed for notes.html: {e}")))?;
+    output.write(page, rendered.as_bytes())
+}
+
+fn notes_table_html(notes: &[&IndexedNote], config: &Config) -> io::Result<String> {
+    let tz = site_timezone(config)?;
+    let mut html = String::from(concat!(
+        "<div class=\"notes-table\">\n",
+        "<label for=\"notes-filter\">Filter notes</label>\n",
+        "<input type=\"search\" id=\"notes-filter\" autocomplete=\"off\">\n",
+        "<table>\n<thead>\n<tr>",
+        "<th scope=\"col\" aria-sort=\"ascending\"><button type=\"button\">Title</button></th>",
+        "<th scope=\"col\"><button type=\"button\">Folder</button></th>",
+        "<th scope=\"col\"><button type=\"button\">Tags</button></th>",
+        "<th scope=\"col\"><button type=\"button\">Date</button></th>",
+        "<th scope=\"col\" data-type=\"number\"><button type=\"button\">Words</button></th>",
+        "</tr>\n</thead>\n<tbody>\n",
+    ));
+    for note in notes {
+        let folder = note.source.parent().map(|folder| folder.display().to_string()).unwrap_or_default();
+        let tags = note.frontmatter.tags.as_deref().unwrap_or_default().join(", ");
+        // Rows sort by the date as written, which orders ISO dates correctly
+        let date = match note.frontmatter.date.as_deref() {
+            Some(date) => {
+                let shown = parse_date(&Value::String(date.to_string()), tz)
+                    .map_or_else(|| date.to_string(), |parsed| format_date(&parsed, config));
+                format!("<td data-sort=\"{}\"><time datetime=\"{0}\">{}</time></td>", escape_html(date), escape_html(&shown))
+            }
+            None => "<td data-sort=\"\"></td>".to_string(),
+        };
+        html.push_str(&format!(
+            "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td>{date}<td>{}</td></tr>\n",
+            escape_html(&note.url),
+            escape_html(&note.title),
+            escape_html(&folder),
+            escape_html(&tags),
+            note.body.split_whitespace().count()
+        ));
+    }
+    html.push_str("</tbody>\n</table>\n</div>\n");
+    Ok(html)
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use serde::Serialize;
+use crate::config::Config;
+use crate::diagnostics::info;
+use crate::index::{IndexedNote, SiteIndex};
+use crate::postprocess::with_base_path;
+use crate::template::theme_file;
+use crate::vfs::OutputSink;
+
+/// A note as the command palette offers it.
+#[derive(Serialize)]
+struct PaletteNote<'a> {
+    title: &'a str,
+    url: String,
+    tags: &'a [String],
+    /// Positions in the list of the notes this one links to
+    links: Vec<usize>,
+}
+
+/// Writes `palette.json`, the title, URL, tags, and outgoing links of every note,
+/// and the `command-palette.js` that reads it: Ctrl+K (⌘K) on any page opens a
+/// palette that finds notes by title, narrows them by `#tag`, and lists the
+/// notes the current page links to and is linked from.
+pub fn write_command_palette(
+    notes: &[IndexedNote],
+    index: &SiteIndex,
+    output: &dyn OutputSink,
+    config: &Config,
+) -> io::Result<()> {
+    let positions: HashMap<&Path, usize> =
+        notes.iter().enumerate().map(|(i, note)| (note.source.as_path(), i)).collect();
+    let notes: Vec<PaletteNote> = notes
+        .iter()
+        .map(|note| PaletteNote {
+            title: &note.title,
+            url: with_base_path(&note.url, config.base_path.as_deref()),
+            tags: note.frontmatter.tags.as_deref().unwrap_or_default(),
+            links: index
+                .outgoing_links(note, &note.body)
+                .into_iter()
+                .filter_map(|target| positions.get(target.source.as_path()).copied())
+                .collect(),
+        })
+        .collect();
+    let json = serde_json::to_string(&notes).map_err(io::Error::other)?;
+    output.write(Path::new("palette.json"), json.as_bytes())?;
+    output.write(Path::new("command-palette.js"), theme_file("command-palette.js")?.as_bytes())?;
+    info!("Wrote the command palette of {} notes", notes.len());
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use crate::diagnostics::warning;
+use crate::links::{find_wikilinks, LinkResolver};
+
+/// Replaces each `![[partial]]` embed in `body` with the body of the partial note
+/// it resolves to, so the partial reads as part of the note. Partials can embed
+/// other partials; an embed that would include a partial within itself is left
+/// as written, with a warning. Other links and embeds are left as they are.
+pub fn include_partials(body: &str, source: &Path, partials: &HashMap<PathBuf, String>, resolver: &LinkResolver) -> String {
+    include(body, source, partials, resolver, &mut vec![source.to_path_buf()])
+}
+
+fn include(
+    body: &str,
+    source: &Path,
+    partials: &HashMap<PathBuf, String>,
+    resolver: &LinkResolver,
+    including: &mut Vec<PathBuf>,
+) -> String {
+    let mut included = String::with_capacity(body.len());
+    let mut last = 0;
+    for (range, link) in find_wikilinks(body) {
+        let Some((path, partial)) = link
+            .embed
+            .then(|| resolver.resolve(&link.target))
+            .flatten()
+            .and_then(|path| partials.get_key_value(path))
+        else {
+            continue;
+        };
+        if including.contains(path) {
+            warning!(
+                file: source, near: &body[range.clone()];
+                "partial {} is embedded within itself in {}",
+                path.display(),
+                source.display()
+            );
+            continue;
+        }
+        included.push_str(&body[last..range.start]);
+        including.push(path.clone());
+        included.push_str(include(partial, path, partials, resolver, including).trim());
+        including.pop();
+        last = range.end;
+    }
+    included.push_str(&body[last..]);
+    included
+}
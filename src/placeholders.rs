@@ -0,0 +1,96 @@
+use std::ops::Range;
+use std::path::Path;
+use std::sync::LazyLock;
+use regex::Regex;
+use serde::Deserialize;
+use crate::diagnostics::warning;
+
+/// Templater commands (`<% tp.date.now() %>`, `<%* ... %>`, possibly over several
+/// lines) and the core Templates plugin's `{{date}}`, `{{time}}`, and `{{title}}`,
+/// with an optional `:format`.
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)<%.*?%>|\{\{\s*(?:date|time|title)(?::[^{}\n]*)?\s*\}\}").unwrap()
+});
+
+/// What happens to template placeholders left unexpanded in a note.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplatePlaceholders {
+    /// Report each note that has any; strict builds fail
+    #[default]
+    Warn,
+    /// Leave them out of the published page
+    Strip,
+    /// Publish them as written
+    Keep,
+}
+
+/// Finds the Templater and core template placeholders in a note body, outside
+/// code, and warns about them or strips them as `mode` says.
+pub fn check_placeholders(body: String, source: &Path, mode: TemplatePlaceholders) -> String {
+    if mode == TemplatePlaceholders::Keep {
+        return body;
+    }
+    let code = code_ranges(&body);
+    let found: Vec<Range<usize>> = PLACEHOLDER
+        .find_iter(&body)
+        .map(|m| m.range())
+        .filter(|m| !code.iter().any(|code| code.contains(&m.start)))
+        .collect();
+    let Some(first) = found.first() else {
+        return body;
+    };
+    if mode == TemplatePlaceholders::Warn {
+        let placeholder = &body[first.clone()];
+        let shown = placeholder.lines().next().unwrap_or(placeholder);
+        warning!(
+            file: source, near: shown;
+            "unexpanded template placeholder {shown} in {} ({} in all); set template_placeholders = \"strip\" to leave them out",
+            source.display(),
+            found.len()
+        );
+        return body;
+    }
+    let mut stripped = String::with_capacity(body.len());
+    let mut last = 0;
+    for range in found {
+        stripped.push_str(&body[last..range.start]);
+        last = range.end;
+    }
+    stripped.push_str(&body[last..]);
+    stripped
+}
+
+/// Byte ranges of fenced code blocks and inline code spans, where placeholders are
+/// shown rather than left over.
+pub(crate) fn code_ranges(body: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut fence: Option<(&str, usize)> = None;
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        match fence {
+            Some((open, start)) => {
+                if trimmed.starts_with(open) {
+                    ranges.push(start..offset + line.len());
+                    fence = None;
+                }
+            }
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => fence = Some((&trimmed[..3], offset)),
+            None => {
+                let mut open = None;
+                for (i, _) in line.match_indices('`') {
+                    match open.take() {
+                        Some(start) => ranges.push(offset + start..offset + i),
+                        None => open = Some(i),
+                    }
+                }
+            }
+        }
+        offset += line.len();
+    }
+    if let Some((_, start)) = fence {
+        ranges.push(start..body.len());
+    }
+    ranges
+}
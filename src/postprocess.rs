@@ -0,0 +1,170 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use lol_html::html_content::{ContentType, Element};
+use lol_html::{element, rewrite_str, ElementContentHandlers, RewriteStrSettings, Selector};
+use serde::Deserialize;
+use crate::diagnostics::config_error;
+use crate::fs::AssetMode;
+use crate::vfs::{OutputSink, VaultSource};
+
+/// A change applied to every element matching a CSS selector in the rendered
+/// pages, configured as an `[[html_rewrite]]` table.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct HtmlRule {
+    pub selector: String,
+    /// Space-separated classes added to the element
+    pub add_class: Option<String>,
+    /// Attributes set on the element, replacing existing values
+    pub set_attributes: BTreeMap<String, String>,
+    pub remove_attributes: Vec<String>,
+    /// Wraps the element in a `<div>` with this class, e.g. to make tables scroll
+    pub wrap_class: Option<String>,
+    /// Removes the element and everything inside it
+    pub remove: bool,
+}
+
+/// The configured HTML rules, with their selectors parsed.
+pub struct HtmlRules<'a> {
+    rules: Vec<(Selector, &'a HtmlRule)>,
+}
+
+impl<'a> HtmlRules<'a> {
+    /// Fails with a config error when a selector isn't valid CSS, or isn't one lol_html can match while streaming.
+    pub fn new(rules: &'a [HtmlRule]) -> io::Result<HtmlRules<'a>> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let selector = rule.selector.parse::<Selector>().map_err(|e| {
+                    config_error(format!("Invalid selector \"{}\" in html_rewrite: {e}", rule.selector))
+                })?;
+                Ok((selector, rule))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(HtmlRules { rules })
+    }
+
+    pub fn rewrite(&self, html: &str) -> io::Result<String> {
+        if self.rules.is_empty() {
+            return Ok(html.to_string());
+        }
+        let handlers = self
+            .rules
+            .iter()
+            .map(|(selector, rule)| {
+                let handler = ElementContentHandlers::default().element(move |el: &mut Element| {
+                    if rule.remove {
+                        el.remove();
+                        return Ok(());
+                    }
+                    if let Some(add) = &rule.add_class {
+                        let class = match el.get_attribute("class") {
+                            Some(class) if !class.trim().is_empty() => format!("{} {add}", class.trim()),
+                            _ => add.clone(),
+                        };
+                        el.set_attribute("class", &class)?;
+                    }
+                    for (name, value) in &rule.set_attributes {
+                        el.set_attribute(name, value)?;
+                    }
+                    for name in &rule.remove_attributes {
+                        el.remove_attribute(name);
+                    }
+                    if let Some(class) = &rule.wrap_class {
+                        el.before(&format!("<div class=\"{}\">", class.replace('"', "&quot;")), ContentType::Html);
+                        el.after("</div>", ContentType::Html);
+                    }
+                    Ok(())
+                });
+                (Cow::Borrowed(selector), handler)
+            })
+            .collect();
+        let settings = RewriteStrSettings { element_content_handlers: handlers, ..RewriteStrSettings::new() };
+        rewrite_str(html, settings).map_err(|e| io::Error::other(format!("HTML rewriting failed: {e}")))
+    }
+}
+
+/// Prefixes a root-relative URL (`/notes/a.html`, not `//host/...`) with `base_path`,
+/// the path a site hosted below the root of its domain is served under.
+pub fn with_base_path(url: &str, base_path: Option<&str>) -> String {
+    let base = base_path.unwrap_or_default().trim_matches('/');
+    if base.is_empty() || !url.starts_with('/') || url.starts_with("//") {
+        return url.to_string();
+    }
+    format!("/{base}{url}")
+}
+
+/// Applies the HTML rules to every page written through it, after templates have
+/// rendered them, and moves root-relative links below `base_path`; other files
+/// pass through untouched.
+pub struct RewriteHtml<'a> {
+    inner: &'a dyn OutputSink,
+    rules: HtmlRules<'a>,
+    base_path: Option<&'a str>,
+}
+
+impl<'a> RewriteHtml<'a> {
+    pub fn new(inner: &'a dyn OutputSink, rules: &'a [HtmlRule], base_path: Option<&'a str>) -> io::Result<RewriteHtml<'a>> {
+        Ok(RewriteHtml { inner, rules: HtmlRules::new(rules)?, base_path })
+    }
+}
+
+/// A rendered page as it is published: the HTML rules applied, then its
+/// root-relative links moved below `base_path`. Pages rendered outside a build,
+/// such as by `obs2web render` and the digest, go through it too.
+pub fn rewrite_page(html: &str, rules: &HtmlRules, base_path: Option<&str>) -> io::Result<String> {
+    let html = rules.rewrite(html)?;
+    let Some(base_path) = base_path.filter(|base| !base.trim_matches('/').is_empty()) else {
+        return Ok(html);
+    };
+    let handlers = vec![element!("[href], [src], [poster], [action]", move |el| {
+        for name in ["href", "src", "poster", "action"] {
+            // Values come as written, where templates may have escaped the slashes
+            if let Some(url) = el.get_attribute(name).map(|url| url.replace("&#x2F;", "/")) {
+                let prefixed = with_base_path(&url, Some(base_path));
+                if prefixed != url {
+                    el.set_attribute(name, &prefixed)?;
+                }
+            }
+        }
+        Ok(())
+    })];
+    let settings = RewriteStrSettings { element_content_handlers: handlers, ..RewriteStrSettings::new() };
+    rewrite_str(&html, settings).map_err(|e| io::Error::other(format!("HTML rewriting failed: {e}")))
+}
+
+impl OutputSink for RewriteHtml<'_> {
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let is_page = path.extension().is_some_and(|ext| ext == "html");
+        match std::str::from_utf8(contents) {
+            Ok(html) if is_page => {
+                let html = rewrite_page(html, &self.rules, self.base_path)
+                    .map_err(|e| io::Error::other(format!("{} in {}", e, path.display())))?;
+                self.inner.write(path, html.as_bytes())
+            }
+            _ => self.inner.write(path, contents),
+        }
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.inner.read(path)
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        self.inner.size(path)
+    }
+
+    fn files(&self) -> io::Result<Vec<PathBuf>> {
+        self.inner.files()
+    }
+
+    fn copy_asset(&self, vault: &dyn VaultSource, source: &Path, dest: &Path, mode: AssetMode) -> io::Result<()> {
+        self.inner.copy_asset(vault, source, dest, mode)
+    }
+
+    fn local_dir(&self) -> Option<&Path> {
+        self.inner.local_dir()
+    }
+}
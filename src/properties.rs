@@ -0,0 +1,70 @@
+use chrono_tz::Tz;
+use serde_json::Value;
+use crate::config::Config;
+use crate::content::escape_html;
+use crate::dates::{format_date, parse_date};
+use crate::index::{frontmatter_link, url_for, IndexedNote, LinkTarget, SiteIndex};
+
+/// The note's frontmatter as a panel of properties, as Obsidian 1.4+ shows them
+/// above a note: each field named, with lists as separate values, checkboxes as
+/// ticks, dates in the site's format, and `[[links]]` and URLs as links. Only
+/// fields in `properties_show`, when it lists any, and none in `properties_hide`
+/// are shown; none when no field is left.
+pub fn properties_html(note: &IndexedNote, index: &SiteIndex, tz: Tz, config: &Config) -> Option<String> {
+    let rows: Vec<String> = note
+        .properties
+        .iter()
+        .filter(|(name, _)| config.properties_show.is_empty() || config.properties_show.contains(name))
+        .filter(|(name, _)| !config.properties_hide.contains(name))
+        .filter(|(_, value)| !value.is_null() && value.as_array().is_none_or(|values| !values.is_empty()))
+        .map(|(name, value)| {
+            let (kind, shown) = match value {
+                Value::Array(values) => {
+                    let items: String = values
+                        .iter()
+                        .map(|value| format!("<li>{}</li>", property_value(value, index, tz, config)))
+                        .collect();
+                    ("list", format!("<ul>{items}</ul>"))
+                }
+                Value::Bool(_) => ("checkbox", property_value(value, index, tz, config)),
+                Value::Number(_) => ("number", property_value(value, index, tz, config)),
+                _ => ("text", property_value(value, index, tz, config)),
+            };
+            format!("<div class=\"property property-{kind}\"><dt>{}</dt><dd>{shown}</dd></div>\n", escape_html(name))
+        })
+        .collect();
+    if rows.is_empty() {
+        return None;
+    }
+    Some(format!("<dl class=\"properties\">\n{}</dl>\n", rows.concat()))
+}
+
+/// A single value of a property as HTML.
+fn property_value(value: &Value, index: &SiteIndex, tz: Tz, config: &Config) -> String {
+    let text = match value {
+        Value::Bool(true) => return "<span class=\"property-checkbox\" aria-label=\"Yes\">☑</span>".to_string(),
+        Value::Bool(false) => return "<span class=\"property-checkbox\" aria-label=\"No\">☐</span>".to_string(),
+        Value::String(text) => text.trim(),
+        Value::Number(number) => return number.to_string(),
+        other => return escape_html(&other.to_string()),
+    };
+    if let Some(link) = text.strip_prefix("[[").and_then(|link| link.strip_suffix("]]")) {
+        let target = frontmatter_link(text);
+        let label = link.split_once('|').map_or(target, |(_, alias)| alias.trim());
+        let url = match index.resolve(target) {
+            Some(LinkTarget::Note(note)) => note.url.clone(),
+            Some(LinkTarget::Asset(path)) => url_for(path),
+            _ => return escape_html(label),
+        };
+        return format!("<a href=\"{}\">{}</a>", escape_html(&url), escape_html(label));
+    }
+    if text.starts_with("https://") || text.starts_with("http://") {
+        return format!("<a href=\"{0}\">{0}</a>", escape_html(text));
+    }
+    match parse_date(&Value::String(text.to_string()), tz) {
+        Some(date) => {
+            format!("<time datetime=\"{}\">{}</time>", escape_html(text), escape_html(&format_date(&date, config)))
+        }
+        None => escape_html(text),
+    }
+}
@@ -0,0 +1,38 @@
+use std::io;
+use std::path::Path;
+use sha2::{Digest, Sha256};
+use tera::{Context, Tera};
+use crate::diagnostics::info;
+use crate::index::url_for;
+use crate::template::theme_file;
+use crate::vfs::OutputSink;
+
+/// Files larger than this are left to runtime caching instead of being downloaded on install.
+const PRECACHE_MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Writes `sw.js`, a service worker that pre-caches every page and asset of the
+/// built site. Its cache name is a hash over the content of all output files.
+pub fn write_service_worker(output: &dyn OutputSink) -> io::Result<()> {
+    let mut precache = Vec::new();
+    let mut build_hash = Sha256::new();
+    for relative in output.files()? {
+        let contents = output.read(&relative)?;
+        build_hash.update(relative.to_string_lossy().as_bytes());
+        build_hash.update(Sha256::digest(&contents));
+        if contents.len() as u64 <= PRECACHE_MAX_FILE_SIZE {
+            // Relative URLs keep the worker usable when the site is hosted under a subpath
+            precache.push(format!(".{}", url_for(&relative)));
+        }
+    }
+    let hash: String = build_hash.finalize().iter().take(8).map(|b| format!("{b:02x}")).collect();
+
+    let template = theme_file("sw.js")?;
+    let mut context = Context::new();
+    context.insert("cache_name", &format!("obs2web-{hash}"));
+    context.insert("precache", &precache);
+    let service_worker = Tera::one_off(&template, &context, false)
+        .map_err(|e| io::Error::other(format!("Template rendering failed for sw.js: {e}")))?;
+    output.write(Path::new("sw.js"), service_worker.as_bytes())?;
+    info!("Wrote service worker pre-caching {} files", precache.len());
+    Ok(())
+}
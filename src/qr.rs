@@ -0,0 +1,15 @@
+use qrcode::render::svg;
+use qrcode::QrCode;
+
+/// Renders `url` as an inline SVG QR code, or `None` if it is too long to encode.
+pub fn qr_code_svg(url: &str) -> Option<String> {
+    let code = QrCode::new(url.as_bytes()).ok()?;
+    let svg = code
+        .render::<svg::Color>()
+        .min_dimensions(160, 160)
+        .dark_color(svg::Color("#000000"))
+        .light_color(svg::Color("#ffffff"))
+        .build();
+    // Drop the XML declaration so the SVG can be embedded in HTML
+    Some(svg[svg.find("<svg")?..].to_string())
+}
@@ -0,0 +1,28 @@
+use std::io;
+use std::path::Path;
+use tera::{Context, Tera};
+use crate::config::Config;
+use crate::index::IndexedNote;
+use crate::postprocess::with_base_path;
+use crate::vfs::OutputSink;
+
+/// Writes `random.html`, which sends the reader on to a note picked at random in
+/// the browser, as Obsidian's "Open random note" does. The page carries the URLs
+/// of every note, so it needs no other file.
+pub fn write_random_page(
+    notes: &[IndexedNote],
+    tera: &Tera,
+    output: &dyn OutputSink,
+    config: &Config,
+) -> io::Result<()> {
+    let urls: Vec<String> =
+        notes.iter().map(|note| with_base_path(&note.url, config.base_path.as_deref())).collect();
+    // Inside a script element, so a URL can't be allowed to close it
+    let urls = serde_json::to_string(&urls).map_err(io::Error::other)?.replace("</", "<\\/");
+    let mut context = Context::new();
+    context.insert("urls", &urls);
+    let html = tera
+        .render("random.html", &context)
+        .map_err(|e| io::Error::other(format!("Template rendering failed for random.html: {e}")))?;
+    output.write(Path::new("random.html"), html.as_bytes())
+}
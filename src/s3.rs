@@ -0,0 +1,212 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use chrono::DateTime;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use crate::config::Config;
+use crate::content::media_type;
+use crate::diagnostics::config_error;
+use crate::http::percent_encode;
+use crate::vfs::OutputSink;
+
+/// How an output directory names a bucket to publish into: `s3://bucket/prefix`.
+pub const S3_SCHEME: &str = "s3://";
+
+/// A site written straight into an S3 bucket below a key prefix, without a local
+/// copy. Requests are signed with `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`, and
+/// `AWS_SESSION_TOKEN`; the bucket is in `AWS_REGION`, and `AWS_ENDPOINT_URL` points
+/// at another store speaking the S3 API, such as MinIO or R2. Objects an earlier
+/// build wrote and this one doesn't are left in the bucket.
+pub struct S3Output {
+    bucket: String,
+    prefix: String,
+    region: String,
+    /// Scheme and host of a store other than AWS, addressed path-style
+    endpoint: Option<(String, String)>,
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+    written: Mutex<BTreeSet<PathBuf>>,
+    agent: ureq::Agent,
+}
+
+impl S3Output {
+    pub fn new(url: &str, config: &Config) -> io::Result<S3Output> {
+        let location = url.strip_prefix(S3_SCHEME).unwrap_or(url);
+        let (bucket, prefix) = location.split_once('/').unwrap_or((location, ""));
+        if bucket.is_empty() {
+            return Err(config_error(format!("{url} names no bucket; write it as s3://bucket/prefix")));
+        }
+        let (Some(access_key), Some(secret_key)) = (env_var("AWS_ACCESS_KEY_ID"), env_var("AWS_SECRET_ACCESS_KEY")) else {
+            return Err(config_error(format!("Set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY to publish to {url}")));
+        };
+        let endpoint = env_var("AWS_ENDPOINT_URL").map(|endpoint| {
+            let (scheme, host) = endpoint.split_once("://").unwrap_or(("https", endpoint.as_str()));
+            (scheme.to_string(), host.trim_end_matches('/').to_string())
+        });
+        Ok(S3Output {
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_matches('/').to_string(),
+            region: env_var("AWS_REGION").or_else(|| env_var("AWS_DEFAULT_REGION")).unwrap_or_else(|| "us-east-1".to_string()),
+            endpoint,
+            access_key,
+            secret_key,
+            session_token: env_var("AWS_SESSION_TOKEN"),
+            written: Mutex::new(BTreeSet::new()),
+            // Uploads of large attachments take as long as they take; only a stalled connection is given up on
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(Duration::from_secs(config.http_timeout))
+                .timeout_read(Duration::from_secs(config.http_timeout))
+                .build(),
+        })
+    }
+
+    /// The object key of an output path, or an error when the path would lead out
+    /// of the prefix.
+    fn key(&self, path: &Path) -> io::Result<String> {
+        let mut key = self.prefix.clone();
+        for component in path.components() {
+            let name = match component {
+                Component::Normal(name) => name.to_str(),
+                Component::CurDir => continue,
+                _ => None,
+            };
+            let Some(name) = name else {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a key in the bucket", path.display())));
+            };
+            if !key.is_empty() {
+                key.push('/');
+            }
+            key.push_str(name);
+        }
+        Ok(key)
+    }
+
+    /// Sends a request for the object at `path`, signed with AWS Signature Version 4,
+    /// and returns the response body.
+    fn request(&self, method: &str, path: &Path, body: &[u8]) -> io::Result<Vec<u8>> {
+        let key = self.key(path)?.split('/').map(percent_encode).collect::<Vec<_>>().join("/");
+        let (scheme, host, uri) = match &self.endpoint {
+            Some((scheme, host)) => (scheme.as_str(), host.clone(), format!("/{}/{key}", self.bucket)),
+            None => ("https", format!("{}.s3.{}.amazonaws.com", self.bucket, self.region), format!("/{key}")),
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(io::Error::other)?;
+        let amz_date = DateTime::from_timestamp(now.as_secs() as i64, 0).unwrap_or_default().format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = hex(&Sha256::digest(body));
+
+        // Sorted by name, as the canonical request lists them
+        let mut signed = vec![("host", host.clone()), ("x-amz-content-sha256", payload_hash.clone()), ("x-amz-date", amz_date.clone())];
+        if let Some(token) = &self.session_token {
+            signed.push(("x-amz-security-token", token.clone()));
+        }
+        let signed_names = signed.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+        let canonical_headers: String = signed.iter().map(|(name, value)| format!("{name}:{}\n", value.trim())).collect();
+        let canonical_request = format!("{method}\n{uri}\n\n{canonical_headers}\n{signed_names}\n{payload_hash}");
+        let date = &amz_date[..8];
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let string_to_sign =
+            format!("AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}", hex(&Sha256::digest(canonical_request.as_bytes())));
+        let mut signing_key = keyed(format!("AWS4{}", self.secret_key).as_bytes(), date.as_bytes());
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            signing_key = keyed(&signing_key, part.as_bytes());
+        }
+        let signature = hex(&keyed(&signing_key, string_to_sign.as_bytes()));
+
+        // The client sends the host itself
+        let mut headers: Vec<(&str, String)> = signed.into_iter().filter(|(name, _)| *name != "host").collect();
+        headers.push((
+            "authorization",
+            format!("AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_names}, Signature={signature}", self.access_key),
+        ));
+        if method == "PUT"
+            && let Some(content_type) = content_type(path)
+        {
+            headers.push(("content-type", content_type.to_string()));
+        }
+        self.send(method, &format!("{scheme}://{host}{uri}"), &headers, body)
+    }
+
+    fn send(&self, method: &str, url: &str, headers: &[(&str, String)], body: &[u8]) -> io::Result<Vec<u8>> {
+        use std::io::Read;
+        let mut request = self.agent.request(method, url);
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        let result = if method == "PUT" { request.send_bytes(body) } else { request.call() };
+        let response = match result {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => {
+                return Err(io::Error::new(io::ErrorKind::NotFound, format!("{url} is not in the bucket")));
+            }
+            Err(ureq::Error::Status(status, response)) => {
+                let detail = response.into_string().unwrap_or_default();
+                return Err(io::Error::other(format!("{method} {url} failed with {status}: {detail}")));
+            }
+            Err(e) => return Err(io::Error::other(format!("{method} {url} failed: {e}"))),
+        };
+        let mut contents = Vec::new();
+        response.into_reader().read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+}
+
+impl OutputSink for S3Output {
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.request("PUT", path, contents)?;
+        self.written.lock().unwrap().insert(path.to_path_buf());
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.request("GET", path, &[])
+    }
+
+    /// The files this build wrote; the bucket isn't listed.
+    fn files(&self) -> io::Result<Vec<PathBuf>> {
+        Ok(self.written.lock().unwrap().iter().cloned().collect())
+    }
+}
+
+/// The `Content-Type` a browser needs to be served the file with, or `None` to
+/// leave it to the store.
+fn content_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension().and_then(|s| s.to_str()).unwrap_or_default().to_lowercase();
+    Some(match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "webmanifest" => "application/manifest+json",
+        "xml" => "application/xml",
+        "txt" | "md" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "woff2" => "font/woff2",
+        "wasm" => "application/wasm",
+        _ => return media_type(path),
+    })
+}
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn keyed(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes a key of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
@@ -0,0 +1,203 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::config::{CONFIG_FILE, STARTER_CONFIG};
+use crate::diagnostics::info;
+use crate::template::{DEFAULT_THEME, THEME_DIR};
+
+/// Workflow that builds the vault on every push to main and publishes it to GitHub Pages.
+const GITHUB_WORKFLOW: &str = r#"name: Publish
+
+on:
+  push:
+    branches: [main]
+  workflow_dispatch:
+
+permissions:
+  contents: read
+  pages: write
+  id-token: write
+
+concurrency:
+  group: pages
+  cancel-in-progress: true
+
+jobs:
+  publish:
+    runs-on: ubuntu-latest
+    environment:
+      name: github-pages
+      url: ${{ steps.deployment.outputs.page_url }}
+    steps:
+      - uses: actions/checkout@v4
+        with:
+          # Full history, for --git-history and updated dates
+          fetch-depth: 0
+      - uses: dtolnay/rust-toolchain@stable
+      - name: Install obs2web
+        run: cargo install --locked --git https://github.com/nickngn/obs2web
+      - name: Build site
+        # Warnings show up on the notes they concern; exit code 2 (warnings only) doesn't fail the build
+        run: obs2web build --quiet --annotations github --vault-path . --output-dir public || [ $? -eq 2 ]
+      - uses: actions/upload-pages-artifact@v3
+        with:
+          path: public
+      - id: deployment
+        uses: actions/deploy-pages@v4
+"#;
+
+/// Reference for a new theme's author: the files of a theme and the variables
+/// each template is rendered with.
+const THEME_README: &str = r#"# Theme
+
+A copy of obs2web's default theme, to change as you like. obs2web loads the
+theme from `templates/` in the folder it runs in, so copy or rename this folder
+to `templates/` (next to where you run `obs2web build`) to use it.
+
+Templates are [Tera](https://keats.github.io/tera/docs/) templates. Partials
+that are missing (`audio.html`, `video.html`, `digest.html`, `author.html`,
+`random.html`, `recent.html`, `recipe.html`, `encrypted.html`) fall back to the default theme's, and
+`style.css` and the scripts are copied to the site as they are.
+
+## Files
+
+- `base.html`: every note page, and the generated pages (changes, all notes, authors, books)
+- `index.html`: the home page, a tree of the vault's folders
+- `tag.html`: one page per tag
+- `history.html`: a note's revisions, with `--git-history`
+- `search.html`: the search box, included by `index.html`
+- `macros.html`: macros the other templates import
+- `audio.html`, `video.html`: embedded media, rendered within a note's content
+- `digest.html`: the email digest of `obs2web digest`
+- `author.html`: the list of an author's notes, with `author_pages`
+- `random.html`: the page that opens a random note, with `random_note`
+- `recent.html`: the recently updated notes, included by `index.html`
+- `recipe.html`: a recipe card above the note, for a `[[note_type]]` of recipes; it extends base.html's `content` block
+- `encrypted.html`: the page that asks for the password of an `[[encrypted_folder]]` and decrypts the page in its place
+- `style.css`: the stylesheet
+- `*.js`, `sw.js`: scripts base.html loads behind the flags below
+- `social-card.svg`: the image rendered for notes without a cover, with `social_cards`
+
+## base.html
+
+Set on every page:
+
+- `title`: page title
+- `content`: the page's HTML
+- `relative_path`: path from the page to the site root, for linking `style.css` and scripts
+- `page_class`: class of the element holding the page, which instant navigation swaps
+- `build_time`: when the site was built
+- `recent_notes`: the recently updated notes, each with `title`, `url`, `date` (RFC 3339), and `updated` (whether `date` is an update)
+- `print_button`, `site_icons`, `web_manifest`, `pwa`, `instant_navigation`, `copy_code`, `video_facades`, `notes_table`, `random_note`, `command_palette`, `flashcards`, `map`, `outgoing_links_panel`: whether the config turns on the feature of the same name
+- `opensearch`: with `opensearch`, the name the site is offered to browsers as a search engine under
+
+Set on note pages, when the note has them:
+
+- `date`: the note's `date`, as written
+- `updated`: when the note was last changed
+- `tags`: the note's tags
+- `fields`: Dataview inline fields (`Key:: Value`), by key
+- `authors`: each with `id`, `name`, `avatar`, `bio`, `url`, and `links` (`title`, `url`)
+- `cover`: URL of the cover image
+- `history_url`: URL of the note's history page
+- `canonical_url`: the page's absolute URL, with `site_url`
+- `og_image`, `og_type`, `json_ld`: Open Graph and structured data
+- `qr_code`: SVG of a QR code for `canonical_url`
+- `archived`: whether the note is archived; `successor_url` and `successor_title` name the note that replaces it
+- `part_nav`: for notes split into pages, `note_title`, `parts`, `previous`, and `next`, each part with `title`, `url`, and `current`
+- `outgoing_links`: notes the page links to
+- `unlinked_mentions`: notes that write this note's title without linking it
+- `artifacts`: files `[[page_artifact]]` commands generated for the note, each with `name`, `url`, and `mime_type`
+- `properties`: every field of the note's frontmatter as written, by name
+- `properties_panel`: with `properties_panel`, the HTML of the panel showing them
+
+Notes in lists have `title`, `path`, `url`, `date`, `tags`, `cover`, and `archived`.
+
+## index.html
+
+- `nodes`: the folder tree; each node has `title`, `notes`, and child `nodes`
+- `recent_notes`, `site_icons`, `web_manifest`, `pwa`, `notes_table`, `random_note`, `command_palette`, `flashcards`, `map`, `opensearch`: as in base.html
+- `search`, `changes_page`, `timeline`: whether the config turns them on
+
+## tag.html
+
+- `tag`: the tag
+- `notes`: the notes tagged with it
+
+## Others
+
+- `history.html`: `title`, `url` of the note, and `revisions`
+- `audio.html`, `video.html`: `url`, `title`, `mime_type`, and the URLs of the `captions` (`.vtt`) and `transcript` (`.txt`) beside the file
+- `author.html`: `author` and their `notes`
+- `random.html`: `urls` of every note
+- `recipe.html`: as base.html; it reads `servings`, `prep_time`, `cook_time`, `total_time`, and the lists `ingredients` and `steps` from `properties`
+- `encrypted.html`: `relative_path` as in base.html, and `page`, the JSON of the encrypted page (`salt`, `iterations`, `iv`, and `data`, in base64)
+- `digest.html`: `title`, `site_url`, `since`, `until`, and `notes`
+"#;
+
+/// Sets a vault up for publishing: a starter obs2web.toml, a copy of the default
+/// theme in `templates/` (which builds run from the vault pick up), and, if asked
+/// for, a GitHub Actions workflow. Files that already exist are left alone.
+pub fn init(vault_path: &Path, github_workflow: bool) -> io::Result<()> {
+    fs::create_dir_all(vault_path)?;
+    write_new(&vault_path.join(CONFIG_FILE), STARTER_CONFIG)?;
+
+    let theme_dir = vault_path.join(THEME_DIR);
+    fs::create_dir_all(&theme_dir)?;
+    for (name, contents) in DEFAULT_THEME {
+        write_new(&theme_dir.join(name), contents)?;
+    }
+    ignore_scaffold(vault_path)?;
+
+    if github_workflow {
+        let workflow_dir = vault_path.join(".github/workflows");
+        fs::create_dir_all(&workflow_dir)?;
+        write_new(&workflow_dir.join("publish.yml"), GITHUB_WORKFLOW)?;
+    }
+    info!("Vault ready; build it from {} with `obs2web build -v . -o public`", vault_path.display());
+    Ok(())
+}
+
+/// Starts a custom theme in `dir`: a copy of every file of the default theme,
+/// and a README listing the variables each template is rendered with. Files that
+/// already exist are left alone.
+pub fn new_theme(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    for (name, contents) in DEFAULT_THEME {
+        write_new(&dir.join(name), contents)?;
+    }
+    write_new(&dir.join("README.md"), THEME_README)?;
+    info!("Theme ready; obs2web uses it from {THEME_DIR}/ in the folder it runs in");
+    Ok(())
+}
+
+fn write_new(path: &Path, contents: &str) -> io::Result<()> {
+    if path.exists() {
+        info!("Keeping existing {}", path.display());
+        return Ok(());
+    }
+    fs::write(path, contents)?;
+    info!("Wrote {}", path.display());
+    Ok(())
+}
+
+/// Keeps the theme and the suggested output folder out of the published site,
+/// which would otherwise copy them as attachments.
+fn ignore_scaffold(vault_path: &Path) -> io::Result<()> {
+    let ignore_path = vault_path.join(".obs2webignore");
+    let mut ignore = fs::read_to_string(&ignore_path).unwrap_or_default();
+    let original_len = ignore.len();
+    for folder in ["templates", "public"] {
+        if ignore.lines().any(|line| line.trim().trim_matches('/') == folder) {
+            continue;
+        }
+        if !ignore.is_empty() && !ignore.ends_with('\n') {
+            ignore.push('\n');
+        }
+        ignore.push_str(&format!("/{folder}/\n"));
+    }
+    if ignore.len() == original_len {
+        return Ok(());
+    }
+    fs::write(&ignore_path, ignore)
+}
@@ -0,0 +1,103 @@
+use std::collections::BTreeSet;
+use std::io;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+use regex::Regex;
+use serde::Deserialize;
+use crate::config::Config;
+use crate::diagnostics::{config_error, info, warning};
+use crate::index::SiteIndex;
+use crate::vfs::VaultSource;
+
+/// What happens to a note in which `secret_scan` finds something; the `secret_scan_policy` setting.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanPolicy {
+    /// Publish it and report what was found
+    Warn,
+    /// Leave the note out of the site and report what was found
+    Exclude,
+    /// Report everything found, then stop the build
+    #[default]
+    Fail,
+}
+
+/// What is looked for in every note, by the name hits are reported under.
+static BUILTIN_PATTERNS: LazyLock<Vec<(&str, Regex)>> = LazyLock::new(|| {
+    [
+        ("email address", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}"),
+        ("private key", r"-----BEGIN (?:[A-Z]+ )?PRIVATE KEY-----"),
+        ("AWS access key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+        ("GitHub token", r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})"),
+        ("Slack token", r"\bxox[abprs]-[A-Za-z0-9-]{10,}"),
+        ("Stripe key", r"\b[rs]k_live_[A-Za-z0-9]{16,}"),
+        ("Google API key", r"\bAIza[0-9A-Za-z_-]{35}"),
+        ("API key", r"\bsk-[A-Za-z0-9_-]{20,}"),
+        ("JSON web token", r"\beyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}"),
+    ]
+    .into_iter()
+    .map(|(name, pattern)| (name, Regex::new(pattern).unwrap()))
+    .collect()
+});
+
+/// Looks through the source of every note of the site, frontmatter included, for
+/// email addresses, shapes of API keys and tokens, private keys, and matches of
+/// `secret_patterns`, except for matches of `secret_allow`. Every hit is reported
+/// with its file and line. With `secret_scan_policy = "fail"` a hit stops the
+/// build; with `exclude` the notes with hits are returned, to be left out.
+pub fn scan_notes(vault: &dyn VaultSource, index: &SiteIndex, config: &Config) -> io::Result<BTreeSet<PathBuf>> {
+    let compile = |patterns: &[String], setting: &str| -> io::Result<Vec<Regex>> {
+        let compile = |pattern: &String| {
+            Regex::new(pattern).map_err(|e| config_error(format!("Invalid {setting} pattern {pattern}: {e}")))
+        };
+        patterns.iter().map(compile).collect()
+    };
+    let custom = compile(&config.secret_patterns, "secret_patterns")?;
+    let allowed = compile(&config.secret_allow, "secret_allow")?;
+    let patterns: Vec<(&str, &Regex)> = BUILTIN_PATTERNS
+        .iter()
+        .map(|(name, pattern)| (*name, pattern))
+        .chain(custom.iter().map(|pattern| ("match of secret_patterns", pattern)))
+        .collect();
+
+    let mut flagged = BTreeSet::new();
+    let mut hits = 0;
+    for note in &index.notes {
+        let text = vault.read_to_string(&note.source)?;
+        for (number, line) in text.lines().enumerate() {
+            for (name, pattern) in &patterns {
+                for found in pattern.find_iter(line) {
+                    let found = found.as_str();
+                    if allowed.iter().any(|allow| allow.find(found).is_some_and(|m| m.len() == found.len())) {
+                        continue;
+                    }
+                    warning!(file: &note.source, near: found; "{}:{}: {name} {}", note.source.display(), number + 1, masked(found));
+                    flagged.insert(note.source.clone());
+                    hits += 1;
+                }
+            }
+        }
+    }
+    if hits == 0 {
+        info!("Found no secrets in {} notes", index.notes.len());
+        return Ok(flagged);
+    }
+    let message = format!("Found {hits} possible secrets in {} notes", flagged.len());
+    match config.secret_scan_policy {
+        ScanPolicy::Fail => Err(io::Error::other(format!("{message}; remove them, or allow them with secret_allow"))),
+        ScanPolicy::Exclude => {
+            warning!("{message}; leaving those notes out of the site");
+            Ok(flagged)
+        }
+        ScanPolicy::Warn => {
+            warning!("{message}");
+            Ok(BTreeSet::new())
+        }
+    }
+}
+
+/// The start of a hit, enough to find it, without printing the secret into build logs.
+fn masked(found: &str) -> String {
+    let shown: String = found.chars().take(4).collect();
+    format!("{shown}{}", "*".repeat(found.chars().count().saturating_sub(4).min(12)))
+}
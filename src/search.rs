@@ -0,0 +1,182 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+use tera::{Context, Tera};
+use crate::config::Config;
+use crate::content::{absolute_url, escape_html};
+use crate::diagnostics::{info, warning};
+use crate::index::IndexedNote;
+use crate::postprocess::with_base_path;
+use crate::template::theme_file;
+use crate::vfs::OutputSink;
+
+/// What powers the search page.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchBackend {
+    /// obs2web's own JSON index, sharded for large vaults
+    #[default]
+    Json,
+    /// An index built by Pagefind from the rendered pages, loaded in small fragments
+    Pagefind,
+}
+
+/// A search result as the search page shows it.
+#[derive(Serialize)]
+struct SearchDoc<'a> {
+    url: String,
+    title: &'a str,
+    tags: &'a [String],
+}
+
+/// `search-index.json`: the documents plus either every term, or (when sharded)
+/// the keys of the shard files holding the terms.
+#[derive(Serialize)]
+struct SearchIndex<'a> {
+    docs: Vec<SearchDoc<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    terms: Option<&'a BTreeMap<String, BTreeSet<usize>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shards: Option<Vec<String>>,
+}
+
+/// Builds an inverted index (term -> notes containing it) of every note and
+/// writes it with the search page. Indexes larger than `search_shard_threshold`
+/// bytes are split into `search/<key>.json` shards by the first character of
+/// each term, which the search page loads only for the words being searched.
+pub fn write_search_index(notes: &[IndexedNote], output: &dyn OutputSink, tera: &Tera, config: &Config) -> io::Result<()> {
+    if config.search_backend == SearchBackend::Pagefind {
+        return run_pagefind(output, tera, config);
+    }
+    let mut terms: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+    for (id, note) in notes.iter().enumerate() {
+        let tags = note.frontmatter.tags.as_deref().unwrap_or_default().join(" ");
+        for text in [note.title.as_str(), tags.as_str(), note.body.as_str()] {
+            for term in tokenize(text) {
+                terms.entry(term).or_default().insert(id);
+            }
+        }
+    }
+    let docs = || {
+        notes
+            .iter()
+            .map(|note| SearchDoc {
+                url: with_base_path(&note.url, config.base_path.as_deref()),
+                title: &note.title,
+                tags: note.frontmatter.tags.as_deref().unwrap_or_default(),
+            })
+            .collect()
+    };
+
+    let single = serde_json::to_string(&SearchIndex { docs: docs(), terms: Some(&terms), shards: None })
+        .map_err(io::Error::other)?;
+    if single.len() <= config.search_shard_threshold {
+        output.write(Path::new("search-index.json"), single.as_bytes())?;
+        info!("Wrote search index of {} terms", terms.len());
+    } else {
+        let mut shards: BTreeMap<String, BTreeMap<&str, &BTreeSet<usize>>> = BTreeMap::new();
+        for (term, ids) in &terms {
+            shards.entry(shard_key(term)).or_default().insert(term, ids);
+        }
+        for (key, shard) in &shards {
+            let shard_path = Path::new("search").join(format!("{key}.json"));
+            output.write(&shard_path, serde_json::to_string(shard).map_err(io::Error::other)?.as_bytes())?;
+        }
+        let index = SearchIndex { docs: docs(), terms: None, shards: Some(shards.keys().cloned().collect()) };
+        output.write(Path::new("search-index.json"), serde_json::to_string(&index).map_err(io::Error::other)?.as_bytes())?;
+        info!("Wrote search index of {} terms in {} shards", terms.len(), shards.len());
+    }
+
+    output.write(Path::new("search.js"), theme_file("search.js")?.as_bytes())?;
+    render_search_page(output, tera, false)
+}
+
+/// Indexes the rendered site with Pagefind, which writes its index and search UI
+/// to `pagefind/`. Pages mark their searchable content with `data-pagefind-*` attributes.
+fn run_pagefind(output: &dyn OutputSink, tera: &Tera, config: &Config) -> io::Result<()> {
+    let output_dir = output
+        .local_dir()
+        .ok_or_else(|| io::Error::other("Pagefind indexes a site on disk; build into a directory to use it"))?;
+    render_search_page(output, tera, true)?;
+    // The command may carry arguments, e.g. "npx pagefind"
+    let mut words = config.pagefind_command.split_whitespace();
+    let program = words.next().unwrap_or("pagefind");
+    info!("Running {} on {}", config.pagefind_command, output_dir.display());
+    let status = Command::new(program)
+        .args(words)
+        .arg("--site")
+        .arg(output_dir)
+        .status()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::other(format!(
+                "{program} not found: install Pagefind (https://pagefind.app) or set pagefind_command"
+            )),
+            _ => e,
+        })?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{} failed with {status}", config.pagefind_command)));
+    }
+    Ok(())
+}
+
+fn render_search_page(output: &dyn OutputSink, tera: &Tera, pagefind: bool) -> io::Result<()> {
+    let mut context = Context::new();
+    context.insert("pagefind", &pagefind);
+    let search_html = tera.render("search.html", &context).map_err(|e| {
+        io::Error::other(format!("Template rendering failed for search.html: {e}"))
+    })?;
+    output.write(Path::new("search.html"), search_html.as_bytes())
+}
+
+/// Writes `opensearch.xml`, an OpenSearch description that lets browsers add the
+/// site as a search engine, searching with the search page. Needs `site_url`.
+pub fn write_opensearch(output: &dyn OutputSink, config: &Config) -> io::Result<()> {
+    let Some(name) = opensearch_name(config) else {
+        warning!("opensearch needs search and site_url to be set; no OpenSearch description will be written");
+        return Ok(());
+    };
+    let url = |path: &str| escape_html(&absolute_url(path, config));
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<OpenSearchDescription xmlns=\"http://a9.com/-/spec/opensearch/1.1/\" xmlns:moz=\"http://www.mozilla.org/2006/browser/search/\">\n");
+    // Browsers show at most 16 characters of the short name
+    xml.push_str(&format!("  <ShortName>{}</ShortName>\n", escape_html(&name.chars().take(16).collect::<String>())));
+    xml.push_str(&format!("  <Description>Search {}</Description>\n", escape_html(&name)));
+    xml.push_str("  <InputEncoding>UTF-8</InputEncoding>\n");
+    if config.icon.is_some() {
+        xml.push_str(&format!("  <Image width=\"16\" height=\"16\" type=\"image/x-icon\">{}</Image>\n", url("/favicon.ico")));
+    }
+    xml.push_str(&format!("  <Url type=\"text/html\" method=\"get\" template=\"{}?q={{searchTerms}}\"/>\n", url("/search.html")));
+    xml.push_str(&format!(
+        "  <Url type=\"application/opensearchdescription+xml\" rel=\"self\" template=\"{}\"/>\n",
+        url("/opensearch.xml")
+    ));
+    xml.push_str(&format!("  <moz:SearchForm>{}</moz:SearchForm>\n", url("/search.html")));
+    xml.push_str("</OpenSearchDescription>\n");
+    output.write(Path::new("opensearch.xml"), xml.as_bytes())
+}
+
+/// The name the site is added to browsers as a search engine under, its
+/// `site_name` or else the host of `site_url`; none when there is nothing to add.
+pub(crate) fn opensearch_name(config: &Config) -> Option<String> {
+    let site_url = config.site_url.as_deref().filter(|_| config.search && config.opensearch)?;
+    let host = site_url.split("://").nth(1).unwrap_or(site_url).split('/').next().unwrap_or(site_url);
+    Some(config.site_name.clone().unwrap_or_else(|| host.to_string()))
+}
+
+/// Lowercased words of at least two characters; search.js splits queries the same way.
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 2)
+        .map(str::to_lowercase)
+}
+
+/// The shard a term belongs to: its first character, or `_` for anything that
+/// isn't ASCII, so shard file names stay portable. Must match search.js.
+fn shard_key(term: &str) -> String {
+    match term.chars().next() {
+        Some(c) if c.is_ascii_alphanumeric() => c.to_string(),
+        _ => "_".to_string(),
+    }
+}
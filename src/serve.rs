@@ -0,0 +1,215 @@
+use std::fs::{self, File};
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::reject::{Reject, Rejection};
+use warp::{Filter, Reply};
+use zip::ZipArchive;
+use crate::cli::ServeArgs;
+use crate::diagnostics::{info, warning};
+
+/// Serves an existing build over HTTP without rebuilding it. `path` may be a
+/// site directory or a `.zip`/`.tar.gz` archive produced with `--output-format`.
+/// A site built with a `base_path` is served below it, as it will be when hosted.
+/// With `--auth` every request must carry the credentials, and with `--tls-cert`
+/// the site is served over HTTPS.
+pub fn preview(path: &Path, server: &ServeArgs, base_path: Option<&str>) -> io::Result<()> {
+    // Checked first, so a mistyped flag fails before an archive is unpacked
+    let auth = basic_auth(server.auth.as_deref())?;
+    let tls = match (&server.tls_cert, &server.tls_key) {
+        (Some(cert), Some(key)) => Some((read_pem(cert)?, read_pem(key)?)),
+        _ => None,
+    };
+    let addr = SocketAddr::new(server.host, server.port);
+    if server.auth.is_some() && tls.is_none() && !server.host.is_loopback() {
+        warning!("--auth without --tls-cert sends the credentials unencrypted to {addr}");
+    }
+
+    // Archives are unpacked into a temporary directory that lives as long as the server
+    let mut staging = None;
+    let root: PathBuf = if path.is_dir() {
+        path.to_path_buf()
+    } else {
+        let dir = tempfile::tempdir()?;
+        extract_archive(path, dir.path())?;
+        let root = dir.path().to_path_buf();
+        staging = Some(dir);
+        root
+    };
+
+    // A site built with a base path is served below it
+    let segments: Vec<String> =
+        base_path.unwrap_or_default().split('/').filter(|s| !s.is_empty()).map(str::to_string).collect();
+    let mut prefix = warp::any().boxed();
+    for segment in &segments {
+        prefix = prefix.and(warp::path(segment.clone())).boxed();
+    }
+    // Sites built with clean URLs link to `/Note` for `Note.html`, as a rewriting server would serve it
+    let pages_root = root.clone();
+    let pages = warp::path::tail().and_then(move |tail: warp::path::Tail| {
+        let page = clean_url_page(&pages_root, tail.as_str());
+        async move {
+            let html = match page {
+                Some(page) => tokio::fs::read(page).await.ok(),
+                None => None,
+            };
+            html.map(warp::reply::html).ok_or_else(warp::reject::not_found)
+        }
+    });
+    let files = warp::fs::dir(root).map(warp::Reply::into_response);
+    let routes = auth.and(prefix).and(files.or(pages.map(warp::Reply::into_response)).unify()).recover(ask_credentials);
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let base: String = segments.iter().map(|s| format!("{s}/")).collect();
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    info!("Previewing {} at {scheme}://{addr}/{base}", path.display());
+    match tls {
+        Some((cert, key)) => runtime.block_on(warp::serve(routes).tls().cert(cert).key(key).run(addr)),
+        None => runtime.block_on(warp::serve(routes).run(addr)),
+    }
+    drop(staging);
+    Ok(())
+}
+
+/// A request without the `--auth` credentials.
+#[derive(Debug)]
+struct Unauthorized;
+
+impl Reject for Unauthorized {}
+
+/// Passes the requests that carry `credentials` (`user:pass`) as HTTP basic
+/// authentication, or every request when there are none.
+fn basic_auth(credentials: Option<&str>) -> io::Result<BoxedFilter<()>> {
+    let Some(credentials) = credentials else {
+        return Ok(warp::any().boxed());
+    };
+    if credentials.split_once(':').is_none_or(|(user, _)| user.is_empty()) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "--auth must be given as user:pass"));
+    }
+    // Digests are compared rather than the headers, so how long a comparison takes gives nothing away
+    let expected = Sha256::digest(format!("Basic {}", BASE64.encode(credentials)));
+    let check = move |header: Option<String>| async move {
+        match header {
+            Some(header) if Sha256::digest(header.trim()) == expected => Ok(()),
+            _ => Err(warp::reject::custom(Unauthorized)),
+        }
+    };
+    let filter = warp::header::optional::<String>("authorization").and_then(check);
+    Ok(filter.untuple_one().boxed())
+}
+
+/// Answers requests without the credentials with a 401 that makes browsers ask for them.
+async fn ask_credentials(rejection: Rejection) -> Result<warp::reply::Response, Rejection> {
+    if rejection.find::<Unauthorized>().is_none() {
+        return Err(rejection);
+    }
+    let challenge = r#"Basic realm="obs2web", charset="UTF-8""#;
+    Ok(warp::reply::with_header(StatusCode::UNAUTHORIZED, "WWW-Authenticate", challenge).into_response())
+}
+
+fn read_pem(path: &Path) -> io::Result<Vec<u8>> {
+    fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("Cannot read {}: {e}", path.display())))
+}
+
+fn extract_archive(archive: &Path, dest: &Path) -> io::Result<()> {
+    let name = archive.to_string_lossy();
+    info!("Extracting archive: {}", archive.display());
+    if name.ends_with(".zip") {
+        let mut zip = ZipArchive::new(File::open(archive)?).map_err(io::Error::other)?;
+        zip.extract(dest).map_err(io::Error::other)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        tar::Archive::new(GzDecoder::new(File::open(archive)?)).unpack(dest)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Not a site directory or a .zip/.tar.gz archive: {}", archive.display()),
+        ))
+    }
+}
+
+/// The page a clean URL path (`Guides/Linking` for `Guides/Linking.html`) names below
+/// `root`, or none when, decoded, it isn't made of plain names only: `..`, `%2F`
+/// leading to an absolute path, or a drive would reach files outside the site.
+fn clean_url_page(root: &Path, url_path: &str) -> Option<PathBuf> {
+    let page = PathBuf::from(format!("{}.html", percent_decode(url_path)));
+    page.components().all(|c| matches!(c, Component::Normal(_))).then(|| root.join(page))
+}
+
+/// Decodes the `%XX` escapes of a URL path.
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_urls_stay_inside_the_site() {
+        let root = Path::new("/srv/site");
+        assert_eq!(clean_url_page(root, "Guides/Linking"), Some(root.join("Guides/Linking.html")));
+        assert_eq!(clean_url_page(root, "My%20Note"), Some(root.join("My Note.html")));
+        for url_path in ["../secret", "%2Fetc%2Fsecret", "Guides/%2E%2E/%2E%2E/secret", "%2F%2Fhost%2Fshare%2Fsecret"] {
+            assert_eq!(clean_url_page(root, url_path), None, "{url_path} leaves the site");
+        }
+    }
+
+    #[tokio::test]
+    async fn basic_auth_asks_for_the_credentials() {
+        let filter = basic_auth(Some("reader:hunter2")).unwrap().map(|| "page").recover(ask_credentials);
+        let answer = |header: Option<String>| {
+            let request = warp::test::request().path("/Note.html");
+            match header {
+                Some(header) => request.header("authorization", header),
+                None => request,
+            }
+        };
+
+        let response = answer(None).reply(&filter).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(response.headers()["www-authenticate"].to_str().unwrap().starts_with("Basic "));
+        let wrong = format!("Basic {}", BASE64.encode("reader:hunter3"));
+        assert_eq!(answer(Some(wrong)).reply(&filter).await.status(), StatusCode::UNAUTHORIZED);
+
+        let right = format!("Basic {}", BASE64.encode("reader:hunter2"));
+        let response = answer(Some(right)).reply(&filter).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().as_ref(), b"page");
+    }
+
+    #[tokio::test]
+    async fn no_credentials_pass_every_request() {
+        let filter = basic_auth(None).unwrap().map(|| "page");
+        assert_eq!(warp::test::request().reply(&filter).await.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn credentials_need_a_user_and_a_colon() {
+        for credentials in ["reader", ":hunter2"] {
+            assert!(basic_auth(Some(credentials)).is_err(), "{credentials} was accepted");
+        }
+        assert!(basic_auth(Some("reader:")).is_ok());
+    }
+}
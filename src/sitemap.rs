@@ -0,0 +1,65 @@
+use std::io;
+use std::path::Path;
+use chrono::SecondsFormat;
+use serde_json::Value;
+use crate::config::Config;
+use crate::content::{absolute_url, escape_html};
+use crate::dates::{parse_date, site_timezone};
+use crate::diagnostics::{info, warning};
+use crate::index::IndexedNote;
+use crate::vfs::OutputSink;
+
+/// Root-relative URL of the sitemap.
+pub const SITEMAP_URL: &str = "/sitemap.xml";
+
+/// Writes `sitemap.xml`, listing the absolute URL of the home page and of every
+/// page of the site's notes, with the date each note was last updated, for search
+/// engines and for the cache warming `obs2web deploy` can do. Needs `site_url`.
+pub fn write_sitemap(notes: &[IndexedNote], output: &dyn OutputSink, config: &Config) -> io::Result<()> {
+    if config.site_url.is_none() {
+        warning!("sitemap needs site_url to be set; no sitemap will be written");
+        return Ok(());
+    }
+    let tz = site_timezone(config)?;
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n");
+    xml.push_str(&url_entry(&absolute_url("/", config), None));
+    for note in notes {
+        let date = note.updated.as_deref().or(note.frontmatter.date.as_deref());
+        let lastmod = date
+            .and_then(|date| parse_date(&Value::from(date), tz))
+            .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true));
+        if note.parts.is_empty() {
+            xml.push_str(&url_entry(&absolute_url(&note.url, config), lastmod.as_deref()));
+        }
+        for part in &note.parts {
+            xml.push_str(&url_entry(&absolute_url(&part.url, config), lastmod.as_deref()));
+        }
+    }
+    xml.push_str("</urlset>\n");
+    output.write(Path::new(SITEMAP_URL.trim_start_matches('/')), xml.as_bytes())?;
+    info!("Wrote sitemap of {} notes", notes.len());
+    Ok(())
+}
+
+fn url_entry(url: &str, lastmod: Option<&str>) -> String {
+    match lastmod {
+        Some(lastmod) => format!("  <url><loc>{}</loc><lastmod>{lastmod}</lastmod></url>\n", escape_html(url)),
+        None => format!("  <url><loc>{}</loc></url>\n", escape_html(url)),
+    }
+}
+
+/// The URLs a sitemap lists, in order.
+pub fn sitemap_urls(xml: &str) -> Vec<String> {
+    xml.split("<loc>")
+        .skip(1)
+        .filter_map(|rest| rest.split_once("</loc>"))
+        .map(|(url, _)| {
+            url.trim()
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&amp;", "&")
+        })
+        .collect()
+}
@@ -0,0 +1,81 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use resvg::{tiny_skia, usvg};
+use tera::{Context, Tera};
+use crate::config::Config;
+use crate::diagnostics::info;
+use crate::index::IndexedNote;
+use crate::template::theme_file;
+use crate::vfs::OutputSink;
+
+/// Characters per title line on the card, and the number of lines before the title is cut off.
+const TITLE_LINE_LENGTH: usize = 28;
+const TITLE_MAX_LINES: usize = 3;
+
+/// Output-relative path of a note's social card, e.g. `Folder/Note.html` -> `cards/Folder/Note.png`.
+pub fn card_path(output: &Path) -> PathBuf {
+    Path::new("cards").join(output).with_extension("png")
+}
+
+/// Renders a PNG preview card for every note: the SVG card template is filled
+/// in with the note's title and the site name through Tera, then rasterized.
+pub fn write_social_cards(notes: &[IndexedNote], output: &dyn OutputSink, config: &Config) -> io::Result<()> {
+    // The theme's card is used when the config doesn't name a template
+    let template = match &config.social_card_template {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|e| io::Error::other(format!("Cannot read social card template {}: {e}", path.display())))?,
+        None => theme_file("social-card.svg")?,
+    };
+
+    // Loading system fonts is slow, so it happens once for all cards
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+
+    for note in notes {
+        let mut context = Context::new();
+        context.insert("title", &note.title);
+        let title_lines = wrap_title(&note.title);
+        // Baseline of the first line, so the block of lines is centered vertically
+        context.insert("title_y", &(340 - 40 * title_lines.len().saturating_sub(1)));
+        context.insert("title_lines", &title_lines);
+        context.insert("site_name", &config.site_name);
+        context.insert("date", &note.frontmatter.date);
+        context.insert("tags", &note.frontmatter.tags);
+        let svg = Tera::one_off(&template, &context, true)
+            .map_err(|e| io::Error::other(format!("Social card rendering failed for {}: {e}", note.source.display())))?;
+
+        let tree = usvg::Tree::from_str(&svg, &options)
+            .map_err(|e| io::Error::other(format!("Invalid social card SVG for {}: {e}", note.source.display())))?;
+        let size = tree.size().to_int_size();
+        let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+            .ok_or_else(|| io::Error::other("Social card template has an empty size"))?;
+        resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+
+        output.write(&card_path(&note.output), &pixmap.encode_png().map_err(io::Error::other)?)?;
+    }
+    info!("Wrote {} social cards", notes.len());
+    Ok(())
+}
+
+/// Breaks a title into lines at word boundaries, since SVG text doesn't wrap;
+/// titles that don't fit end with an ellipsis.
+fn wrap_title(title: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in title.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= TITLE_LINE_LENGTH => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    if lines.len() > TITLE_MAX_LINES {
+        lines.truncate(TITLE_MAX_LINES);
+        if let Some(last) = lines.last_mut() {
+            last.push('…');
+        }
+    }
+    lines
+}
@@ -0,0 +1,69 @@
+use std::io;
+use crate::blocks::strip_block_markers;
+use crate::config::Config;
+use crate::content::{absolute_url, frontmatter_alt, image_size, is_image, link_anchor, make_comrak_options};
+use crate::diagnostics::info;
+use crate::index::{url_for, IndexedNote, LinkTarget, SiteIndex};
+use crate::links::replace_wikilinks;
+use crate::markdown::document_text;
+use crate::postprocess::with_base_path;
+use crate::vfs::OutputSink;
+
+/// Writes the note's Markdown (`.md`, with `markdown_files`) and its text
+/// without markup (`.txt`, with `text_files`) next to the page of each of
+/// `notes`, for readers who want the source and for tools that index or process
+/// the site's content. Frontmatter is left out, wikilinks become Markdown links
+/// to the site's pages and files, and `^id` markers are dropped.
+pub fn write_note_sources(notes: &[IndexedNote], index: &SiteIndex, output: &dyn OutputSink, config: &Config) -> io::Result<()> {
+    let comrak_options = make_comrak_options(config);
+    for note in notes {
+        let markdown = note_markdown(note, index, config);
+        if config.markdown_files {
+            output.write(&note.output.with_extension("md"), markdown.as_bytes())?;
+        }
+        if config.text_files {
+            let text = document_text(&markdown, &comrak_options);
+            output.write(&note.output.with_extension("txt"), text.as_bytes())?;
+        }
+    }
+    info!("Wrote the sources of {} notes", notes.len());
+    Ok(())
+}
+
+/// The note's body as plain Markdown, its wikilinks pointing at the published
+/// site. Links to notes that aren't published become their text.
+pub(crate) fn note_markdown(note: &IndexedNote, index: &SiteIndex, config: &Config) -> String {
+    let site_url = |url: &str| public_url(url, config);
+    let body = replace_wikilinks(&note.body, |link| {
+        let label = link.label();
+        if link.target.is_empty() {
+            let anchor = link_anchor(link, note).map(|anchor| format!("#{anchor}")).unwrap_or_default();
+            return format!("[{label}]({anchor})");
+        }
+        match index.resolve(&link.target) {
+            Some(LinkTarget::Note(target)) => {
+                let anchor = link_anchor(link, target);
+                let url = anchor.as_deref().map_or(target.url.as_str(), |anchor| target.heading_url(anchor));
+                let anchor = anchor.map(|anchor| format!("#{anchor}")).unwrap_or_default();
+                format!("[{label}](<{}{anchor}>)", site_url(url))
+            }
+            Some(LinkTarget::Asset(asset)) if link.embed && is_image(asset) => {
+                let alt = link.alias.clone().filter(|alias| image_size(alias).is_none());
+                let alt = alt.or_else(|| frontmatter_alt(link, asset, note)).unwrap_or_default();
+                format!("![{alt}](<{}>)", site_url(&url_for(asset)))
+            }
+            Some(LinkTarget::Asset(asset)) => format!("[{label}](<{}>)", site_url(&url_for(asset))),
+            Some(LinkTarget::Unpublished(_)) | None => label,
+        }
+    });
+    strip_block_markers(&body)
+}
+
+/// A root-relative URL of the site as it is published: absolute with `site_url`,
+/// so copies read elsewhere still link back to the site, and under `base_path`.
+pub(crate) fn public_url(url: &str, config: &Config) -> String {
+    match &config.site_url {
+        Some(_) => absolute_url(url, config),
+        None => with_base_path(url, config.base_path.as_deref()),
+    }
+}
@@ -0,0 +1,170 @@
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::index::{IndexedNote, UrlStyle};
+use crate::links::slugify;
+
+/// One page of a note split at its headings.
+#[derive(Debug, Clone)]
+pub struct NotePart {
+    /// The note's title for the first part, the heading it starts at for the others
+    pub title: String,
+    /// Output-relative path of the part's page
+    pub output: PathBuf,
+    /// Root-relative URL of the part's page
+    pub url: String,
+    /// Byte range of the part in the note's body, without the heading it starts at
+    pub body: Range<usize>,
+    /// Anchors of the headings on the part's page, so `[[Note#Heading]]` finds it
+    pub anchors: Vec<String>,
+}
+
+/// A part in the navigation between the pages of a split note.
+#[derive(Serialize, Debug, Clone)]
+pub struct PartLink {
+    pub title: String,
+    pub url: String,
+    pub current: bool,
+}
+
+/// What the template gets to link the parts of a split note.
+#[derive(Serialize, Debug)]
+pub struct PartNav {
+    pub note_title: String,
+    pub parts: Vec<PartLink>,
+    pub previous: Option<PartLink>,
+    pub next: Option<PartLink>,
+}
+
+/// Splits the note's body at every heading of `max_level` or higher, e.g. at H1s
+/// and H2s for 2. The first part keeps the note's own page and holds the text
+/// before the first such heading; the others are published below the note's
+/// page, at `Note/<heading>.html` (named as `style` names pages). Returns nothing
+/// when there is nothing to split.
+pub fn split_note(note: &IndexedNote, max_level: usize, style: UrlStyle) -> Vec<NotePart> {
+    let headings = headings(&note.body);
+    // The anchors the headings have in the whole note, which their parts keep
+    let anchor = |heading: &Heading| {
+        note.headings.iter().find(|h| h.start == heading.line.start).map(|h| h.anchor.clone()).unwrap_or_default()
+    };
+    let mut boundaries: Vec<&Heading> = headings.iter().filter(|h| h.level <= max_level).collect();
+    // A note that opens with a heading has no intro, so its first part starts there
+    let mut first_title = note.title.clone();
+    let mut first_start = 0;
+    let mut first_anchors = Vec::new();
+    if let Some(first) = boundaries.first()
+        && note.body[..first.line.start].trim().is_empty()
+    {
+        first_title = first.text.clone();
+        first_start = first.line.end;
+        first_anchors.push(anchor(first));
+        boundaries.remove(0);
+    }
+    if boundaries.is_empty() {
+        return Vec::new();
+    }
+
+    let dir = part_dir(&note.output);
+    let mut parts = vec![NotePart {
+        title: first_title,
+        output: note.output.clone(),
+        url: note.url.clone(),
+        body: first_start..boundaries[0].line.start,
+        anchors: first_anchors,
+    }];
+    let mut used = Vec::new();
+    for (i, heading) in boundaries.iter().enumerate() {
+        let end = boundaries.get(i + 1).map_or(note.body.len(), |next| next.line.start);
+        let mut slug = slugify(&heading.text);
+        if slug.is_empty() {
+            slug = format!("part-{}", i + 2);
+        }
+        // Repeated headings get numbered pages, as comrak numbers repeated anchors
+        let base = slug.clone();
+        let mut n = 1;
+        while used.contains(&slug) {
+            slug = format!("{base}-{n}");
+            n += 1;
+        }
+        used.push(slug.clone());
+        let output = style.output(&dir.join(format!("{slug}.html")));
+        parts.push(NotePart {
+            title: heading.text.clone(),
+            url: style.url(&output),
+            output,
+            body: heading.line.end..end,
+            anchors: vec![anchor(heading)],
+        });
+    }
+    for part in &mut parts {
+        let range = part.body.clone();
+        part.anchors.extend(
+            headings
+                .iter()
+                .filter(|h| range.contains(&h.line.start))
+                .map(anchor),
+        );
+    }
+    parts
+}
+
+/// The navigation for `parts[current]`.
+pub fn part_nav(note: &IndexedNote, current: usize) -> PartNav {
+    let link = |i: usize| {
+        let part = &note.parts[i];
+        PartLink { title: part.title.clone(), url: part.url.clone(), current: i == current }
+    };
+    PartNav {
+        note_title: note.title.clone(),
+        parts: (0..note.parts.len()).map(link).collect(),
+        previous: current.checked_sub(1).map(link),
+        next: (current + 1 < note.parts.len()).then(|| link(current + 1)),
+    }
+}
+
+/// Folder the parts of a note are published in: `Note.html` -> `Note/`, `n/id/index.html` -> `n/id/`.
+fn part_dir(output: &Path) -> PathBuf {
+    if output.file_name().is_some_and(|name| name == "index.html") {
+        output.parent().unwrap_or(Path::new("")).to_path_buf()
+    } else {
+        output.with_extension("")
+    }
+}
+
+pub(crate) struct Heading {
+    pub level: usize,
+    pub text: String,
+    /// Byte range of the heading's line, including its line break
+    pub line: Range<usize>,
+}
+
+/// The ATX headings of a Markdown body, ignoring fenced code.
+pub(crate) fn headings(body: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut start = 0;
+    for line in body.split_inclusive('\n') {
+        let range = start..start + line.len();
+        start = range.end;
+        let trimmed = line.trim_start();
+        if let Some(open) = fence {
+            if trimmed.starts_with(open) {
+                fence = None;
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            continue;
+        }
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        if !(1..=6).contains(&level) || !trimmed[level..].starts_with([' ', '\t']) {
+            continue;
+        }
+        let text = trimmed[level..].trim().trim_end_matches('#').trim();
+        if !text.is_empty() {
+            headings.push(Heading { level, text: text.to_string(), line: range });
+        }
+    }
+    headings
+}
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use chrono::SecondsFormat;
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::authors::Author;
+use crate::config::Config;
+use crate::content::absolute_url;
+use crate::dates::{parse_date, site_timezone};
+use crate::index::IndexedNote;
+
+/// How notes of a frontmatter `type` are published, configured as a `[[note_type]]`
+/// table: e.g. `recipe` notes rendered with recipe.html and described as schema.org Recipes.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct NoteType {
+    /// The frontmatter `type` it applies to, compared without regard to case
+    #[serde(rename = "type")]
+    pub name: String,
+    /// Template the notes are rendered with, unless their frontmatter names one
+    pub template: Option<String>,
+    /// schema.org type of the notes' JSON-LD, e.g. `Recipe`
+    pub schema: Option<String>,
+    /// schema.org properties of the JSON-LD, each filled from the frontmatter field it names
+    pub properties: BTreeMap<String, String>,
+}
+
+/// The `[[note_type]]` for the note's frontmatter `type`, when one is configured.
+pub fn note_type<'a>(note: &IndexedNote, config: &'a Config) -> Option<&'a NoteType> {
+    let name = note.frontmatter.page_type.as_deref()?.trim();
+    config.note_types.iter().find(|note_type| note_type.name.eq_ignore_ascii_case(name))
+}
+
+/// The schema.org type of a note: `BlogPosting` or `TechArticle` when its
+/// frontmatter `type` says so, `Article` otherwise.
+fn schema_type(page_type: Option<&str>) -> &'static str {
+    match page_type.map(|t| t.trim().to_lowercase().replace([' ', '-', '_'], "")).as_deref() {
+        Some("blogposting" | "blogpost" | "blog" | "post") => "BlogPosting",
+        Some("techarticle" | "technical" | "tech" | "documentation" | "docs") => "TechArticle",
+        _ => "Article",
+    }
+}
+
+/// A note's page described as a schema.org article in JSON-LD, ready to go in a
+/// `<script type="application/ld+json">`: its headline, dates, authors, tags, and
+/// image and URL when they are known. Dates that can't be read are left out.
+/// A note whose `[[note_type]]` has a `schema` is described as that type instead,
+/// with the properties the note type fills from its frontmatter.
+pub fn json_ld(
+    note: &IndexedNote,
+    headline: &str,
+    description: &str,
+    url: Option<&str>,
+    image: Option<&str>,
+    authors: &[Author],
+    config: &Config,
+) -> String {
+    let tz = site_timezone(config).unwrap_or(Tz::UTC);
+    let date = |text: &Option<String>| {
+        text.as_deref()
+            .and_then(|d| parse_date(&Value::from(d), tz))
+            .map(|date| date.to_rfc3339_opts(SecondsFormat::Secs, true))
+    };
+    let authors: Vec<Value> = authors
+        .iter()
+        .map(|author| {
+            let mut person = json!({ "@type": "Person", "name": author.name });
+            if let Some(url) = &author.url {
+                person["url"] = json!(absolute_url(url, config));
+            }
+            if let Some(avatar) = &author.avatar {
+                person["image"] = json!(absolute_url(avatar, config));
+            }
+            person
+        })
+        .collect();
+
+    let custom = note_type(note, config).filter(|note_type| note_type.schema.is_some());
+    let schema = custom
+        .and_then(|note_type| note_type.schema.as_deref())
+        .unwrap_or_else(|| schema_type(note.frontmatter.page_type.as_deref()));
+    let mut data = json!({
+        "@context": "https://schema.org",
+        "@type": schema,
+        "headline": headline,
+    });
+    if let Some(note_type) = custom {
+        // Types other than articles, such as Recipe, are named rather than headlined
+        data["name"] = json!(headline);
+        for (property, field) in &note_type.properties {
+            if let Some(value) = note.properties.get(field) {
+                data[property] = value.clone();
+            }
+        }
+    }
+    if !description.is_empty() {
+        data["description"] = json!(description);
+    }
+    if let Some(url) = url {
+        data["url"] = json!(url);
+        data["mainEntityOfPage"] = json!({ "@type": "WebPage", "@id": url });
+    }
+    if let Some(image) = image {
+        data["image"] = json!(image);
+    }
+    if let Some(published) = date(&note.frontmatter.date) {
+        data["datePublished"] = json!(published);
+    }
+    if let Some(modified) = date(&note.updated) {
+        data["dateModified"] = json!(modified);
+    }
+    if !authors.is_empty() {
+        data["author"] = json!(authors);
+    }
+    if let Some(tags) = note.frontmatter.tags.as_deref().filter(|tags| !tags.is_empty()) {
+        data["keywords"] = json!(tags);
+    }
+    if let Some(site_name) = &config.site_name {
+        data["publisher"] = json!({ "@type": "Organization", "name": site_name });
+    }
+    // A `</script>` in a value would end the script element early
+    serde_json::to_string(&data).unwrap_or_default().replace("</", "<\\/")
+}
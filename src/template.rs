@@ -1,75 +1,184 @@
 use std::collections::HashMap;
 use std::path::Path;
 use tera::{Context, Tera};
-use crate::domain::{Note, Node};
+use crate::config::Config;
+use crate::dates::date_filter;
+use crate::domain::{Note, Node, SortOrder};
+use crate::index::SiteIndex;
+use crate::search::opensearch_name;
+use crate::vfs::OutputSink;
 use std::collections::VecDeque;
 use std::fs;
 
-pub fn init_tera() -> std::io::Result<Tera> {
-    Tera::new("templates/**/*.html").map_err(|e| {
-        std::io::Error::new(
-            std::io::ErrorKind::Other,
+/// Folder, relative to the working directory, holding a customized theme.
+pub const THEME_DIR: &str = "templates";
+
+/// Partials that themes may leave out, as they were added after themes could be
+/// customized; the default theme's versions are used instead.
+const OPTIONAL_PARTIALS: [&str; 8] = [
+    "audio.html",
+    "video.html",
+    "digest.html",
+    "author.html",
+    "random.html",
+    "recent.html",
+    "recipe.html",
+    "encrypted.html",
+];
+
+/// The default theme, compiled in so builds work without a `templates/` folder
+/// and `init` can hand out a copy to customize.
+pub const DEFAULT_THEME: [(&str, &str); 25] = [
+    ("base.html", include_str!("../templates/base.html")),
+    ("index.html", include_str!("../templates/index.html")),
+    ("tag.html", include_str!("../templates/tag.html")),
+    ("history.html", include_str!("../templates/history.html")),
+    ("search.html", include_str!("../templates/search.html")),
+    ("macros.html", include_str!("../templates/macros.html")),
+    ("audio.html", include_str!("../templates/audio.html")),
+    ("video.html", include_str!("../templates/video.html")),
+    ("digest.html", include_str!("../templates/digest.html")),
+    ("author.html", include_str!("../templates/author.html")),
+    ("random.html", include_str!("../templates/random.html")),
+    ("recent.html", include_str!("../templates/recent.html")),
+    ("recipe.html", include_str!("../templates/recipe.html")),
+    ("encrypted.html", include_str!("../templates/encrypted.html")),
+    ("style.css", include_str!("../templates/style.css")),
+    ("copy-code.js", include_str!("../templates/copy-code.js")),
+    ("navigation.js", include_str!("../templates/navigation.js")),
+    ("video-facade.js", include_str!("../templates/video-facade.js")),
+    ("search.js", include_str!("../templates/search.js")),
+    ("notes-table.js", include_str!("../templates/notes-table.js")),
+    ("command-palette.js", include_str!("../templates/command-palette.js")),
+    ("flashcards.js", include_str!("../templates/flashcards.js")),
+    ("map.js", include_str!("../templates/map.js")),
+    ("sw.js", include_str!("../templates/sw.js")),
+    ("social-card.svg", include_str!("../templates/social-card.svg")),
+];
+
+/// The theme's templates, with obs2web's filters registered.
+pub fn init_tera(config: &Config) -> std::io::Result<Tera> {
+    let mut tera = load_theme()?;
+    tera.register_filter("date", date_filter(config)?);
+    Ok(tera)
+}
+
+fn load_theme() -> std::io::Result<Tera> {
+    if !Path::new(THEME_DIR).is_dir() {
+        let mut tera = Tera::default();
+        let pages = DEFAULT_THEME.into_iter().filter(|(name, _)| name.ends_with(".html"));
+        tera.add_raw_templates(pages).map_err(|e| {
+            std::io::Error::other(format!("Failed to initialize the default theme: {e}"))
+        })?;
+        return Ok(tera);
+    }
+    let mut tera = Tera::new(&format!("{THEME_DIR}/**/*.html")).map_err(|e| {
+        std::io::Error::other(
             format!("Failed to initialize templates: {e}"),
         )
-    })
+    })?;
+    let missing: Vec<(&str, &str)> = DEFAULT_THEME
+        .into_iter()
+        .filter(|(name, _)| OPTIONAL_PARTIALS.contains(name) && !tera.get_template_names().any(|t| t == *name))
+        .collect();
+    tera.add_raw_templates(missing)
+        .map_err(|e| std::io::Error::other(format!("Failed to initialize templates: {e}")))?;
+    Ok(tera)
+}
+
+/// A file of the theme in `templates/`, or of the default theme when there is no such folder.
+pub fn theme_file(name: &str) -> std::io::Result<String> {
+    if Path::new(THEME_DIR).is_dir() {
+        return fs::read_to_string(Path::new(THEME_DIR).join(name));
+    }
+    DEFAULT_THEME
+        .iter()
+        .find(|(file, _)| *file == name)
+        .map(|(_, contents)| contents.to_string())
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("{name} is not part of the theme")))
 }
 
-pub fn render_index(tera: &Tera, output_dir: &Path, notes: &[Note]) -> std::io::Result<()> {
+pub fn render_index(
+    tera: &Tera,
+    output: &dyn OutputSink,
+    notes: &[Note],
+    index: &SiteIndex,
+    config: &Config,
+) -> std::io::Result<()> {
     let mut context = Context::new();
+    context.insert("site_icons", &config.icon.is_some());
+    context.insert("search", &config.search);
+    context.insert("changes_page", &config.changes_page);
+    context.insert("notes_table", &config.notes_table);
+    context.insert("flashcards", &config.flashcards);
+    context.insert("timeline", &config.timeline);
+    context.insert("map", &config.map);
+    context.insert("random_note", &config.random_note);
+    context.insert("command_palette", &config.command_palette);
+    context.insert("recent_notes", &index.recent);
+    context.insert("web_manifest", &(config.icon.is_some() || config.pwa));
+    context.insert("pwa", &config.pwa);
+    context.insert("opensearch", &opensearch_name(config));
 
-    let notes_tree = initiate_nodes_tree(notes.to_vec(), output_dir);
+    let notes_tree = initiate_nodes_tree(notes.to_vec(), index);
 
     context.insert("nodes", &notes_tree);
     let index_html = tera.render("index.html", &context).map_err(|e| {
-        std::io::Error::new(
-            std::io::ErrorKind::Other,
+        std::io::Error::other(
             format!("Template rendering failed for index.html: {e:?}"),
         )
     })?;
-    let index_path = output_dir.join("index.html");
-    fs::write(index_path, index_html)?;
-    Ok(())
+    output.write(Path::new("index.html"), index_html.as_bytes())
+}
+
+/// Writes navigation.js, the instant navigation script, set up to swap the same
+/// page container base.html renders.
+pub fn write_navigation_script(output: &dyn OutputSink, config: &Config) -> std::io::Result<()> {
+    let template = theme_file("navigation.js")?;
+    let mut context = Context::new();
+    context.insert("container_class", &config.page_container_class);
+    let script = Tera::one_off(&template, &context, false).map_err(|e| {
+        std::io::Error::other(format!("Template rendering failed for navigation.js: {e}"))
+    })?;
+    output.write(Path::new("navigation.js"), script.as_bytes())
 }
 
 pub fn render_tag_pages(
     tera: &Tera,
-    output_dir: &Path,
+    output: &dyn OutputSink,
     tags: HashMap<String, Vec<Note>>,
 ) -> std::io::Result<()> {
-    let tags_dir = output_dir.join("tags");
-    fs::create_dir_all(&tags_dir)?;
     for (tag, notes) in tags {
         let mut context = Context::new();
         context.insert("tag", &tag);
         context.insert("notes", &notes);
         let tag_html = tera.render("tag.html", &context).map_err(|e| {
-            std::io::Error::new(
-                std::io::ErrorKind::Other,
+            std::io::Error::other(
                 format!("Template rendering failed for tag.html (tag=\"{}\"): {e}", tag),
             )
         })?;
-        let tag_path = tags_dir.join(format!("{}.html", tag));
-        fs::write(tag_path, tag_html)?;
+        let tag_path = Path::new("tags").join(format!("{}.html", tag));
+        output.write(&tag_path, tag_html.as_bytes())?;
     }
     Ok(())
 }
 
-fn initiate_nodes_tree(mut notes: Vec<Note>, output_dir: &Path) -> Node {
+fn initiate_nodes_tree(mut notes: Vec<Note>, index: &SiteIndex) -> Node {
     let mut root_node = Node {
         nodes: Vec::new(),
-        title: output_dir.to_str().unwrap().to_string(),
+        title: String::new(),
         notes: Vec::new(),
     };
     notes.sort_by(|a, b| a.path.cmp(&b.path));
     notes.iter().for_each(|n| {
-        let mut parts = n.path.to_str().unwrap().split("/").collect::<VecDeque<&str>>();
-        parts.pop_back(); // Remove file name
-        parts.pop_front(); // Remove output dir
+        // The tree mirrors the vault's folders, wherever the page is published
+        let parts = n.path.parent()
+            .map(|p| p.iter().filter_map(|c| c.to_str()).collect::<VecDeque<&str>>())
+            .unwrap_or_default();
         let node_ref = find_or_create_node(parts, &mut root_node);
-        let mut note = n.clone();
-        note.path = note.path.strip_prefix(output_dir).unwrap().to_path_buf();
-        node_ref.notes.push(note);
-        node_ref.notes.sort_by(|a, b| b.path.cmp(&a.path));
+        node_ref.notes.push(n.clone());
+        let folder = n.path.parent().unwrap_or(Path::new(""));
+        sort_notes(&mut node_ref.notes, index.folder_sort(folder));
     });
     root_node
 }
@@ -95,3 +204,15 @@ fn find_or_create_node<'a>(mut path_parts: VecDeque<&str>, node: &'a mut Node) -
     let child = &mut node.nodes[idx];
     find_or_create_node(path_parts, child)
 }
+
+
+fn sort_notes(notes: &mut [Note], order: SortOrder) {
+    match order {
+        SortOrder::Title => notes.sort_by(|a, b| a.title.cmp(&b.title)),
+        SortOrder::TitleDesc => notes.sort_by(|a, b| b.title.cmp(&a.title)),
+        SortOrder::Date => notes.sort_by(|a, b| a.date.cmp(&b.date)),
+        SortOrder::DateDesc => notes.sort_by(|a, b| b.date.cmp(&a.date)),
+        SortOrder::Path => notes.sort_by(|a, b| a.path.cmp(&b.path)),
+        SortOrder::PathDesc => notes.sort_by(|a, b| b.path.cmp(&a.path)),
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io;
+use serde::Serialize;
+use serde_json::Value;
+use tera::{Context, Tera};
+use crate::config::Config;
+use crate::dates::date_filter;
+use crate::diagnostics::file_error;
+use crate::domain::Note;
+use crate::index::IndexedNote;
+
+/// What a templated note sees as `site`.
+#[derive(Serialize)]
+struct SiteContext<'a> {
+    title: Option<&'a str>,
+    url: Option<&'a str>,
+    /// Every note of the site, newest first
+    notes: Vec<Note>,
+    /// The notes with each tag, newest first
+    tags: BTreeMap<&'a str, Vec<Note>>,
+}
+
+/// What a templated note sees as `page`: the note itself.
+#[derive(Serialize)]
+struct PageContext<'a> {
+    title: &'a str,
+    url: &'a str,
+    date: Option<&'a str>,
+    updated: Option<&'a str>,
+    tags: &'a [String],
+    fields: &'a BTreeMap<String, Value>,
+}
+
+/// Whether a note asks for its body to be rendered as a template.
+pub fn is_templated(note: &IndexedNote) -> bool {
+    note.frontmatter.templating == Some(true)
+}
+
+/// Renders the bodies of the notes with `templating: true` as Tera templates, so
+/// hub notes can list other notes (`{% for note in site.tags.recipes %}`) or show
+/// their own frontmatter (`{{ page.date | date }}`) in Markdown. The result is
+/// converted like any other note body. Returns each templated note's index with
+/// its new body; a template error fails the build.
+pub fn expand_templates(notes: &[IndexedNote], config: &Config) -> io::Result<Vec<(usize, String)>> {
+    if !notes.iter().any(is_templated) {
+        return Ok(Vec::new());
+    }
+    let mut tera = Tera::default();
+    tera.register_filter("date", date_filter(config)?);
+
+    let mut sorted: Vec<&IndexedNote> = notes.iter().collect();
+    sorted.sort_by(|a, b| b.frontmatter.date.cmp(&a.frontmatter.date).then_with(|| a.title.cmp(&b.title)));
+    let mut tags: BTreeMap<&str, Vec<Note>> = BTreeMap::new();
+    for note in &sorted {
+        for tag in note.frontmatter.tags.iter().flatten() {
+            tags.entry(tag).or_default().push(note.to_note());
+        }
+    }
+    let site = SiteContext {
+        title: config.site_name.as_deref(),
+        url: config.site_url.as_deref(),
+        notes: sorted.iter().map(|note| note.to_note()).collect(),
+        tags,
+    };
+
+    let mut expanded = Vec::new();
+    for (i, note) in notes.iter().enumerate().filter(|(_, note)| is_templated(note)) {
+        let page = PageContext {
+            title: &note.title,
+            url: &note.url,
+            date: note.frontmatter.date.as_deref(),
+            updated: note.updated.as_deref(),
+            tags: note.frontmatter.tags.as_deref().unwrap_or_default(),
+            fields: &note.fields,
+        };
+        let mut context = Context::new();
+        context.insert("site", &site);
+        context.insert("page", &page);
+        let body = tera.render_str(&note.body, &context).map_err(|e| {
+            // Tera's own message only names its one-off template; the cause is further down
+            let mut message = e.to_string();
+            let mut source = e.source();
+            while let Some(cause) = source {
+                message = format!("{message}: {cause}");
+                source = cause.source();
+            }
+            file_error(&note.source, format!("Template error in {}: {message}", note.source.display()))
+        })?;
+        expanded.push((i, body));
+    }
+    Ok(expanded)
+}
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::LazyLock;
+use chrono::Datelike;
+use chrono_tz::Tz;
+use regex::Regex;
+use tera::{Tera, Value};
+use crate::config::Config;
+use crate::content::{escape_html, page_context};
+use crate::dates::{format_date, parse_date, site_timezone};
+use crate::index::{IndexedNote, SiteIndex};
+use crate::vfs::OutputSink;
+
+/// A year written at the start of a date that isn't a full one, such as `1066` or `-44-03-15`.
+static LEADING_YEAR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(-?\d{1,6})\b").unwrap());
+
+/// A note placed on the timeline.
+struct TimelineEntry<'a> {
+    note: &'a IndexedNote,
+    /// The date as written, which orders entries within a year
+    date: &'a str,
+    /// The date as shown
+    shown: String,
+    /// Whether the date is the note's `event_date` rather than its `date`
+    event: bool,
+}
+
+/// Writes `timeline.html`, every note with an `event_date`, or else a `date`, in
+/// chronological order and grouped by year, for journals and history vaults.
+/// Dates that aren't full dates, such as `1066`, are placed by their year.
+pub fn write_timeline(
+    notes: &[IndexedNote],
+    index: &SiteIndex,
+    tera: &Tera,
+    output: &dyn OutputSink,
+    config: &Config,
+) -> io::Result<()> {
+    let tz = site_timezone(config)?;
+    let mut years: BTreeMap<i64, Vec<TimelineEntry>> = BTreeMap::new();
+    for note in notes {
+        let (date, event) = match (&note.frontmatter.event_date, &note.frontmatter.date) {
+            (Some(date), _) => (date.as_str(), true),
+            (None, Some(date)) => (date.as_str(), false),
+            (None, None) => continue,
+        };
+        let Some((year, shown)) = timeline_date(date, tz, config) else {
+            continue;
+        };
+        years.entry(year).or_default().push(TimelineEntry { note, date, shown, event });
+    }
+    for entries in years.values_mut() {
+        entries.sort_by(|a, b| a.date.cmp(b.date).then_with(|| a.note.title.cmp(&b.note.title)));
+    }
+
+    let page = Path::new("timeline.html");
+    let mut context = page_context(page, index, config);
+    context.insert("title", "Timeline");
+    context.insert("content", &timeline_html(&years));
+    let rendered = tera
+        .render("base.html", &context)
+        .map_err(|e| io::Error::other(format!("Template rendering failed for timeline.html: {e}")))?;
+    output.write(page, rendered.as_bytes())
+}
+
+/// The year of a date and the date as shown, or none when it has no year to place it by.
+fn timeline_date(date: &str, tz: Tz, config: &Config) -> Option<(i64, String)> {
+    match parse_date(&Value::String(date.to_string()), tz) {
+        Some(parsed) => Some((parsed.year().into(), format_date(&parsed, config))),
+        None => {
+            let year = LEADING_YEAR.captures(date.trim())?[1].parse().ok()?;
+            Some((year, date.trim().to_string()))
+        }
+    }
+}
+
+fn timeline_html(years: &BTreeMap<i64, Vec<TimelineEntry>>) -> String {
+    let mut html = String::from("<div class=\"timeline\">\n");
+    for (year, entries) in years {
+        html.push_str(&format!("<section class=\"timeline-year\">\n<h2 id=\"year-{year}\">{year}</h2>\n<ol>\n"));
+        for entry in entries {
+            html.push_str(&format!(
+                "<li class=\"{}\"><time datetime=\"{}\">{}</time> <a href=\"{}\">{}</a></li>\n",
+                if entry.event { "timeline-event" } else { "timeline-note" },
+                escape_html(entry.date),
+                escape_html(&entry.shown),
+                escape_html(&entry.note.url),
+                escape_html(&entry.note.title)
+            ));
+        }
+        html.push_str("</ol>\n</section>\n");
+    }
+    html.push_str("</div>\n");
+    html
+}
@@ -0,0 +1,296 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use walkdir::WalkDir;
+use zip::ZipArchive;
+use crate::config::{Config, CONFIG_FILE};
+use crate::fs::{prepare_output_dir, process_asset, unlink_output, vault_files, AssetMode};
+
+/// Where a build reads the vault from. Paths are vault-relative. Only the vault
+/// and the site go through these traits; the config, theme overrides, and
+/// `cache_dir` are still read and written on disk.
+pub trait VaultSource: Sync {
+    /// The files to publish: notes, attachments, and folder configs.
+    fn files(&self, config: &Config) -> io::Result<Vec<PathBuf>>;
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Size of a file in bytes; by default it is read to find out.
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        self.read(path).map(|contents| contents.len() as u64)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("{} is not valid UTF-8", path.display())))
+    }
+
+    /// The vault's folder on disk, for what needs a real checkout (git history, linked assets).
+    fn local_dir(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Where a build writes the site to. Paths are output-relative.
+pub trait OutputSink: Sync {
+    /// Writes a file, creating the folders above it.
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    /// Size of a written file in bytes; by default it is read to find out.
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        self.read(path).map(|contents| contents.len() as u64)
+    }
+
+    /// Every file written so far, sorted.
+    fn files(&self) -> io::Result<Vec<PathBuf>>;
+
+    /// Places a vault attachment at `dest`; by default its contents are copied.
+    fn copy_asset(&self, vault: &dyn VaultSource, source: &Path, dest: &Path, _mode: AssetMode) -> io::Result<()> {
+        self.write(dest, &vault.read(source)?)
+    }
+
+    /// The site's folder on disk, for tools that index the finished site (Pagefind).
+    fn local_dir(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// A vault folder on disk.
+pub struct LocalVault {
+    root: PathBuf,
+}
+
+impl LocalVault {
+    pub fn new(root: &Path) -> LocalVault {
+        LocalVault { root: root.to_path_buf() }
+    }
+}
+
+impl VaultSource for LocalVault {
+    fn files(&self, config: &Config) -> io::Result<Vec<PathBuf>> {
+        Ok(vault_files(&self.root, config)
+            .into_iter()
+            .filter_map(|p| p.strip_prefix(&self.root).ok().map(Path::to_path_buf))
+            .collect())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(path))
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        fs::metadata(self.root.join(path)).map(|meta| meta.len())
+    }
+
+    fn local_dir(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
+
+/// A vault held in memory, e.g. unpacked from a zip archive or put together by a test.
+/// Hidden entries and the site config are left out of the published files, as on disk;
+/// ignore files aren't read.
+#[derive(Default)]
+pub struct MemoryVault {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl MemoryVault {
+    pub fn new() -> MemoryVault {
+        MemoryVault::default()
+    }
+
+    pub fn insert(&mut self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) {
+        self.files.insert(path.into(), contents.into());
+    }
+
+    /// Reads every file of a zip archive of the vault.
+    pub fn from_zip(reader: impl Read + Seek) -> io::Result<MemoryVault> {
+        let mut archive = ZipArchive::new(reader).map_err(io::Error::other)?;
+        let mut vault = MemoryVault::new();
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).map_err(io::Error::other)?;
+            // Entries escaping the archive root are skipped, like any unsafe path
+            let Some(path) = entry.enclosed_name() else {
+                continue;
+            };
+            if entry.is_dir() {
+                continue;
+            }
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents)?;
+            vault.insert(path, contents);
+        }
+        Ok(vault)
+    }
+}
+
+impl VaultSource for MemoryVault {
+    fn files(&self, _config: &Config) -> io::Result<Vec<PathBuf>> {
+        let hidden = |path: &Path| {
+            path.components().any(|c| matches!(c, Component::Normal(name) if name.to_string_lossy().starts_with('.')))
+        };
+        Ok(self
+            .files
+            .keys()
+            .filter(|path| !hidden(path) && path.as_path() != Path::new(CONFIG_FILE))
+            .cloned()
+            .collect())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} is not in the vault", path.display())))
+    }
+}
+
+/// A site folder on disk, emptied when the build starts.
+pub struct LocalDir {
+    root: PathBuf,
+}
+
+impl LocalDir {
+    pub fn create(root: &Path) -> io::Result<LocalDir> {
+        prepare_output_dir(root)?;
+        Ok(LocalDir { root: root.to_path_buf() })
+    }
+}
+
+impl OutputSink for LocalDir {
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let path = within(&self.root, path)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        unlink_output(&path)?;
+        fs::write(path, contents)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(self.root.join(path))
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        fs::metadata(self.root.join(path)).map(|meta| meta.len())
+    }
+
+    fn files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in WalkDir::new(&self.root).sort_by_file_name() {
+            let entry = entry.map_err(io::Error::other)?;
+            if entry.file_type().is_file() {
+                files.push(entry.path().strip_prefix(&self.root).map_err(io::Error::other)?.to_path_buf());
+            }
+        }
+        Ok(files)
+    }
+
+    /// Assets of a vault on disk are linked or copied as `mode` says, without reading them.
+    fn copy_asset(&self, vault: &dyn VaultSource, source: &Path, dest: &Path, mode: AssetMode) -> io::Result<()> {
+        match vault.local_dir() {
+            Some(vault_dir) => process_asset(&vault_dir.join(source), &within(&self.root, dest)?, mode),
+            None => self.write(dest, &vault.read(source)?),
+        }
+    }
+
+    fn local_dir(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
+
+/// `path` below `root`, or an error when it would lead out of it: a site's paths are
+/// plain names, without `..`, a root, or a drive.
+fn within(root: &Path, path: &Path) -> io::Result<PathBuf> {
+    if path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        Ok(root.join(path))
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is outside the site folder", path.display())))
+    }
+}
+
+/// A folder within another site output, for one of several sites built together.
+pub struct OutputSubdir<'a> {
+    inner: &'a dyn OutputSink,
+    prefix: PathBuf,
+    local: Option<PathBuf>,
+}
+
+impl<'a> OutputSubdir<'a> {
+    pub fn new(inner: &'a dyn OutputSink, prefix: &Path) -> OutputSubdir<'a> {
+        let local = inner.local_dir().map(|dir| dir.join(prefix));
+        OutputSubdir { inner, prefix: prefix.to_path_buf(), local }
+    }
+}
+
+impl OutputSink for OutputSubdir<'_> {
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.inner.write(&self.prefix.join(path), contents)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.inner.read(&self.prefix.join(path))
+    }
+
+    fn size(&self, path: &Path) -> io::Result<u64> {
+        self.inner.size(&self.prefix.join(path))
+    }
+
+    fn files(&self) -> io::Result<Vec<PathBuf>> {
+        Ok(self
+            .inner
+            .files()?
+            .into_iter()
+            .filter_map(|path| path.strip_prefix(&self.prefix).ok().map(Path::to_path_buf))
+            .collect())
+    }
+
+    fn copy_asset(&self, vault: &dyn VaultSource, source: &Path, dest: &Path, mode: AssetMode) -> io::Result<()> {
+        self.inner.copy_asset(vault, source, &self.prefix.join(dest), mode)
+    }
+
+    fn local_dir(&self) -> Option<&Path> {
+        self.local.as_deref()
+    }
+}
+
+/// A site kept in memory, for tests and hosts without a filesystem.
+#[derive(Default)]
+pub struct MemoryOutput {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryOutput {
+    pub fn new() -> MemoryOutput {
+        MemoryOutput::default()
+    }
+
+    pub fn into_files(self) -> BTreeMap<PathBuf, Vec<u8>> {
+        self.files.into_inner().unwrap()
+    }
+}
+
+impl OutputSink for MemoryOutput {
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        self.files.lock().unwrap().insert(path.to_path_buf(), contents.to_vec());
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.files
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} was not written", path.display())))
+    }
+
+    fn files(&self) -> io::Result<Vec<PathBuf>> {
+        Ok(self.files.lock().unwrap().keys().cloned().collect())
+    }
+}
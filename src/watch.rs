@@ -0,0 +1,40 @@
+use std::io;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+use notify::{Event, RecursiveMode, Watcher};
+use crate::diagnostics::{info, warning};
+
+/// How long the vault has to be quiet before a rebuild starts, so a burst of
+/// saves (or a sync client touching many files) triggers a single rebuild.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Calls `rebuild` whenever a file in the vault changes. Changes inside
+/// `output_dir` are ignored, so an output directory inside the vault doesn't
+/// retrigger itself. Failed rebuilds are reported and watching continues.
+pub fn watch(vault_path: &Path, output_dir: &Path, mut rebuild: impl FnMut() -> io::Result<()>) -> io::Result<()> {
+    let output_dir = output_dir.canonicalize().unwrap_or_else(|_| output_dir.to_path_buf());
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(io::Error::other)?;
+    watcher.watch(vault_path, RecursiveMode::Recursive).map_err(io::Error::other)?;
+    info!("Watching {} for changes...", vault_path.display());
+
+    let relevant = |event: notify::Result<Event>| match event {
+        Ok(event) => event.paths.iter().any(|path| !path.starts_with(&output_dir)),
+        Err(e) => {
+            warning!("watch error: {e}");
+            false
+        }
+    };
+    loop {
+        let event = rx.recv().map_err(io::Error::other)?;
+        if !relevant(event) {
+            continue;
+        }
+        while rx.recv_timeout(DEBOUNCE).is_ok() {}
+        info!("Change detected, rebuilding...");
+        if let Err(e) = rebuild() {
+            warning!("rebuild failed: {e}");
+        }
+    }
+}
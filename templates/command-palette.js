@@ -0,0 +1,160 @@
+// The command palette: Ctrl+K (⌘K on macOS) opens a box that finds notes by
+// title as you type, narrows them to tags written as #tag, and, before anything
+// is typed, lists the notes the current page links to and is linked from.
+// Arrow keys pick a note and Enter opens it. The dialog lives outside the page
+// container, so it keeps working after instant navigation swaps the page.
+(function () {
+    const MAX_RESULTS = 50;
+    const script = document.currentScript;
+    const indexUrl = new URL(script.dataset.index, location.href).href;
+    let notes = null;
+    let dialog = null;
+    let selected = 0;
+
+    function load() {
+        if (!notes) {
+            notes = fetch(indexUrl).then((response) => response.json()).then((list) => {
+                list.forEach((note, i) => {
+                    note.backlinks = [];
+                    note.id = i;
+                });
+                list.forEach((note) => {
+                    note.links.forEach((target) => list[target].backlinks.push(note.id));
+                });
+                return list;
+            });
+        }
+        return notes;
+    }
+
+    function currentNote(list) {
+        const path = decodeURI(location.pathname);
+        return list.find((note) => decodeURI(note.url) === path);
+    }
+
+    function matches(query) {
+        const words = query.toLowerCase().split(/\s+/).filter(Boolean);
+        const tags = words.filter((word) => word.startsWith('#') && word.length > 1).map((word) => word.slice(1));
+        const terms = words.filter((word) => !word.startsWith('#'));
+        return (note) => terms.every((term) => note.title.toLowerCase().includes(term))
+            && tags.every((tag) => note.tags.some((t) => t.toLowerCase().startsWith(tag)));
+    }
+
+    function section(title, list) {
+        if (!list.length) {
+            return [];
+        }
+        const heading = document.createElement('li');
+        heading.className = 'palette-heading';
+        heading.setAttribute('role', 'presentation');
+        heading.textContent = title;
+        return [heading, ...list.map(item)];
+    }
+
+    function item(note) {
+        const li = document.createElement('li');
+        li.setAttribute('role', 'option');
+        const link = document.createElement('a');
+        link.href = note.url;
+        link.textContent = note.title;
+        li.append(link);
+        if (note.tags.length) {
+            const tags = document.createElement('span');
+            tags.className = 'palette-tags';
+            tags.textContent = note.tags.map((tag) => '#' + tag).join(' ');
+            li.append(tags);
+        }
+        return li;
+    }
+
+    async function render() {
+        const list = await load();
+        const query = dialog.querySelector('input').value.trim();
+        const results = dialog.querySelector('ul');
+        let items;
+        const current = currentNote(list);
+        if (!query && current) {
+            items = [
+                ...section('Links', current.links.map((i) => list[i])),
+                ...section('Backlinks', current.backlinks.map((i) => list[i])),
+            ];
+        } else {
+            const found = list.filter(matches(query)).sort((a, b) => a.title.localeCompare(b.title));
+            items = found.slice(0, MAX_RESULTS).map(item);
+        }
+        results.replaceChildren(...items);
+        selected = 0;
+        highlight();
+    }
+
+    function options() {
+        return Array.from(dialog.querySelectorAll('[role="option"]'));
+    }
+
+    function highlight() {
+        options().forEach((option, i) => {
+            option.setAttribute('aria-selected', i === selected ? 'true' : 'false');
+            if (i === selected) {
+                option.scrollIntoView({ block: 'nearest' });
+            }
+        });
+    }
+
+    function create() {
+        dialog = document.createElement('dialog');
+        dialog.className = 'command-palette';
+        dialog.setAttribute('aria-label', 'Go to note');
+        const input = document.createElement('input');
+        input.type = 'search';
+        input.placeholder = 'Go to note… (#tag to filter)';
+        input.setAttribute('aria-label', 'Note title or #tag');
+        const results = document.createElement('ul');
+        results.setAttribute('role', 'listbox');
+        dialog.append(input, results);
+        document.body.append(dialog);
+        input.addEventListener('input', render);
+        input.addEventListener('keydown', (e) => {
+            const count = options().length;
+            if (e.key === 'ArrowDown' && count) {
+                selected = (selected + 1) % count;
+                highlight();
+                e.preventDefault();
+            } else if (e.key === 'ArrowUp' && count) {
+                selected = (selected - 1 + count) % count;
+                highlight();
+                e.preventDefault();
+            } else if (e.key === 'Enter' && count) {
+                options()[selected].querySelector('a').click();
+                e.preventDefault();
+            }
+        });
+        // Following a result, or clicking outside the box, closes it
+        dialog.addEventListener('click', (e) => {
+            if (e.target === dialog || e.target.closest('a')) {
+                dialog.close();
+            }
+        });
+    }
+
+    function open() {
+        if (!dialog) {
+            create();
+        }
+        const input = dialog.querySelector('input');
+        input.value = '';
+        dialog.showModal();
+        input.focus();
+        render();
+    }
+
+    document.addEventListener('keydown', (e) => {
+        if ((e.ctrlKey || e.metaKey) && e.key.toLowerCase() === 'k') {
+            e.preventDefault();
+            if (dialog && dialog.open) {
+                dialog.close();
+            } else {
+                open();
+            }
+        }
+    });
+})();
@@ -0,0 +1,36 @@
+// Steps through the flashcards page one card at a time: Previous and Next move
+// between cards, Shuffle deals them in a random order, and a card's question
+// opens to reveal its answer. Without this script the page lists every card.
+// Handlers are delegated so they keep working after instant navigation swaps the page.
+document.addEventListener('click', function (e) {
+    const button = e.target.closest('.flashcard-controls button');
+    if (!button) {
+        return;
+    }
+    const deck = button.closest('.flashcards');
+    const list = deck.querySelector('ol');
+    const cards = Array.from(list.children);
+    if (!cards.length) {
+        return;
+    }
+    let current = cards.findIndex((card) => card.classList.contains('current'));
+    if (button.dataset.action === 'shuffle') {
+        for (let i = cards.length - 1; i > 0; i--) {
+            const j = Math.floor(Math.random() * (i + 1));
+            [cards[i], cards[j]] = [cards[j], cards[i]];
+        }
+        list.append(...cards);
+        current = 0;
+    } else if (current < 0) {
+        current = 0;
+    } else if (button.dataset.action === 'next') {
+        current = (current + 1) % cards.length;
+    } else {
+        current = (current - 1 + cards.length) % cards.length;
+    }
+    deck.classList.add('reviewing');
+    cards.forEach((card, i) => {
+        card.classList.toggle('current', i === current);
+        card.querySelector('details').open = false;
+    });
+});
@@ -0,0 +1,50 @@
+// Draws the map page: loads Leaflet, then puts a marker linking to its note on
+// every place listed in the map element's data-markers. The map is drawn when
+// the page loads and whenever instant navigation swaps a map page in.
+(function () {
+    const LEAFLET = 'https://unpkg.com/leaflet@1.9.4/dist/leaflet';
+    let leaflet = null;
+
+    function loadLeaflet() {
+        if (!leaflet) {
+            const css = document.createElement('link');
+            css.rel = 'stylesheet';
+            css.href = LEAFLET + '.css';
+            document.head.append(css);
+            leaflet = new Promise((resolve, reject) => {
+                const script = document.createElement('script');
+                script.src = LEAFLET + '.js';
+                script.onload = () => resolve(window.L);
+                script.onerror = reject;
+                document.head.append(script);
+            });
+        }
+        return leaflet;
+    }
+
+    async function draw(element) {
+        element.dataset.ready = 'true';
+        const L = await loadLeaflet();
+        const markers = JSON.parse(element.dataset.markers);
+        const map = L.map(element);
+        L.tileLayer(element.dataset.tiles, { attribution: element.dataset.attribution, maxZoom: 19 }).addTo(map);
+        markers.forEach((marker) => {
+            const link = document.createElement('a');
+            link.href = marker.url;
+            link.textContent = marker.title;
+            L.marker([marker.lat, marker.lng]).bindPopup(link).addTo(map);
+        });
+        if (markers.length) {
+            map.fitBounds(markers.map((marker) => [marker.lat, marker.lng]), { padding: [30, 30], maxZoom: 12 });
+        } else {
+            map.setView([20, 0], 2);
+        }
+    }
+
+    function drawAll() {
+        document.querySelectorAll('.note-map:not([data-ready])').forEach(draw);
+    }
+
+    drawAll();
+    new MutationObserver(drawAll).observe(document.body, { childList: true, subtree: true });
+})();
@@ -0,0 +1,87 @@
+// Instant navigation generated by obs2web: internal links are prefetched on
+// hover and followed by swapping the page container instead of reloading.
+(function () {
+    const CONTAINER = '.{{ container_class }}';
+    const pages = new Map();
+
+    function isInternal(link) {
+        if (!link || link.target || link.hasAttribute('download')) {
+            return false;
+        }
+        const url = new URL(link.href, location.href);
+        const samePage = url.pathname === location.pathname && url.search === location.search;
+        return url.origin === location.origin && !(samePage && url.hash);
+    }
+
+    function fetchPage(url) {
+        const key = url.split('#')[0];
+        if (!pages.has(key)) {
+            pages.set(key, fetch(key).then((response) => {
+                if (!response.ok) {
+                    throw new Error(response.statusText);
+                }
+                return response.text();
+            }).catch((err) => {
+                pages.delete(key);
+                throw err;
+            }));
+        }
+        return pages.get(key);
+    }
+
+    async function navigate(url, push) {
+        let doc;
+        try {
+            doc = new DOMParser().parseFromString(await fetchPage(url), 'text/html');
+        } catch (err) {
+            location.href = url;
+            return;
+        }
+        const next = doc.querySelector(CONTAINER);
+        const current = document.querySelector(CONTAINER);
+        if (!next || !current) {
+            location.href = url;
+            return;
+        }
+        const swap = () => {
+            current.replaceWith(next);
+            document.title = doc.title;
+            if (push) {
+                history.pushState({}, '', url);
+            }
+            const target = location.hash && document.getElementById(decodeURIComponent(location.hash.slice(1)));
+            if (target) {
+                target.scrollIntoView();
+            } else {
+                window.scrollTo(0, 0);
+            }
+        };
+        if (document.startViewTransition) {
+            document.startViewTransition(swap);
+        } else {
+            swap();
+        }
+    }
+
+    function prefetch(e) {
+        const link = e.target.closest && e.target.closest('a[href]');
+        if (isInternal(link)) {
+            fetchPage(link.href).catch(() => {});
+        }
+    }
+
+    document.addEventListener('mouseover', prefetch);
+    document.addEventListener('touchstart', prefetch, { passive: true });
+    document.addEventListener('focusin', prefetch);
+
+    document.addEventListener('click', (e) => {
+        const link = e.target.closest('a[href]');
+        if (e.defaultPrevented || e.button !== 0 || e.metaKey || e.ctrlKey || e.shiftKey || e.altKey || !isInternal(link)) {
+            return;
+        }
+        e.preventDefault();
+        navigate(link.href, true);
+    });
+
+    window.addEventListener('popstate', () => navigate(location.href, false));
+})();
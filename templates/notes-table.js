@@ -0,0 +1,45 @@
+// Sorts the table of all notes when a column header is clicked, and filters its
+// rows by what is typed above it. Handlers are delegated so they keep working
+// after instant navigation swaps the page.
+document.addEventListener('click', function (e) {
+    const button = e.target.closest('.notes-table th button');
+    if (!button) {
+        return;
+    }
+    const header = button.closest('th');
+    const table = header.closest('table');
+    const column = Array.from(header.parentElement.children).indexOf(header);
+    const ascending = header.getAttribute('aria-sort') !== 'ascending';
+    const numeric = header.dataset.type === 'number';
+    const key = function (row) {
+        const cell = row.children[column];
+        const value = cell.dataset.sort !== undefined ? cell.dataset.sort : cell.textContent.trim();
+        return numeric ? Number(value) : value.toLowerCase();
+    };
+    const body = table.tBodies[0];
+    const rows = Array.from(body.rows).sort(function (a, b) {
+        const x = key(a);
+        const y = key(b);
+        const order = numeric ? x - y : x.localeCompare(y);
+        return ascending ? order : -order;
+    });
+    body.append(...rows);
+    for (const other of header.parentElement.children) {
+        other.removeAttribute('aria-sort');
+    }
+    header.setAttribute('aria-sort', ascending ? 'ascending' : 'descending');
+});
+
+document.addEventListener('input', function (e) {
+    if (e.target.id !== 'notes-filter') {
+        return;
+    }
+    const words = e.target.value.toLowerCase().split(/\s+/).filter(Boolean);
+    const table = e.target.closest('.notes-table').querySelector('table');
+    for (const row of table.tBodies[0].rows) {
+        const text = row.textContent.toLowerCase();
+        row.hidden = !words.every(function (word) {
+            return text.includes(word);
+        });
+    }
+});
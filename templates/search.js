@@ -0,0 +1,84 @@
+// Search over search-index.json. Large indexes are sharded by the first
+// character of each term; only the shards for the searched words are loaded.
+(function () {
+    const input = document.getElementById('search-input');
+    const results = document.getElementById('search-results');
+    const shards = new Map();
+    let index;
+
+    function loadIndex() {
+        if (!index) {
+            index = fetch('search-index.json').then((response) => response.json());
+        }
+        return index;
+    }
+
+    // Must match shard_key in search.rs
+    function shardKey(word) {
+        return /^[a-z0-9]/.test(word) ? word[0] : '_';
+    }
+
+    async function termsFor(word) {
+        const idx = await loadIndex();
+        if (idx.terms) {
+            return idx.terms;
+        }
+        const key = shardKey(word);
+        if (!idx.shards.includes(key)) {
+            return {};
+        }
+        if (!shards.has(key)) {
+            shards.set(key, fetch('search/' + key + '.json').then((response) => response.json()));
+        }
+        return shards.get(key);
+    }
+
+    // Every word of the query must prefix-match a term of the note
+    async function search(query) {
+        const words = query.toLowerCase().split(/[^\p{L}\p{N}]+/u).filter((word) => word.length > 0);
+        if (words.length === 0) {
+            return [];
+        }
+        const idx = await loadIndex();
+        let matches = null;
+        for (const word of words) {
+            const hits = new Set();
+            for (const [term, ids] of Object.entries(await termsFor(word))) {
+                if (term.startsWith(word)) {
+                    ids.forEach((id) => hits.add(id));
+                }
+            }
+            matches = matches === null ? hits : new Set([...matches].filter((id) => hits.has(id)));
+        }
+        return [...matches].map((id) => idx.docs[id]);
+    }
+
+    function render(docs) {
+        results.replaceChildren(...docs.map((doc) => {
+            const item = document.createElement('li');
+            const link = document.createElement('a');
+            link.href = doc.url;
+            link.textContent = doc.title;
+            item.append(link);
+            if (doc.tags.length > 0) {
+                const tags = document.createElement('span');
+                tags.className = 'note-tags';
+                tags.textContent = ' ' + doc.tags.map((tag) => '#' + tag).join(' ');
+                item.append(tags);
+            }
+            return item;
+        }));
+    }
+
+    let timer;
+    input.addEventListener('input', () => {
+        clearTimeout(timer);
+        timer = setTimeout(async () => render(await search(input.value)), 150);
+    });
+
+    const query = new URLSearchParams(location.search).get('q');
+    if (query) {
+        input.value = query;
+        search(query).then(render);
+    }
+})();
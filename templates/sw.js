@@ -0,0 +1,40 @@
+// Service worker generated by obs2web. It pre-caches the whole site so it works
+// offline; the cache name is derived from the build's content hashes, so any
+// change to the output replaces the old cache.
+const CACHE = '{{ cache_name }}';
+const PRECACHE = {{ precache | json_encode() | safe }};
+
+self.addEventListener('install', (event) => {
+    event.waitUntil(
+        caches.open(CACHE)
+            .then((cache) => cache.addAll(PRECACHE))
+            .then(() => self.skipWaiting())
+    );
+});
+
+self.addEventListener('activate', (event) => {
+    event.waitUntil(
+        caches.keys()
+            .then((keys) => Promise.all(
+                keys.filter((key) => key.startsWith('obs2web-') && key !== CACHE).map((key) => caches.delete(key))
+            ))
+            .then(() => self.clients.claim())
+    );
+});
+
+// Cache first; anything not pre-cached (such as large media) is cached once fetched
+self.addEventListener('fetch', (event) => {
+    const request = event.request;
+    if (request.method !== 'GET' || new URL(request.url).origin !== self.location.origin) {
+        return;
+    }
+    event.respondWith(
+        caches.match(request, { ignoreSearch: true }).then((cached) => cached || fetch(request).then((response) => {
+            if (response.ok) {
+                const copy = response.clone();
+                caches.open(CACHE).then((cache) => cache.put(request, copy));
+            }
+            return response;
+        }))
+    );
+});
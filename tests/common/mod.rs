@@ -0,0 +1,36 @@
+//! What the integration tests share: building a vault held in memory into a
+//! site held in memory, and reading its pages.
+
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use obs2web::build_site_with;
+use obs2web::config::Config;
+use obs2web::vfs::{MemoryOutput, MemoryVault};
+
+/// A vault of the given files, by vault-relative path.
+pub fn vault(files: &[(&str, &str)]) -> MemoryVault {
+    let mut vault = MemoryVault::new();
+    for (path, contents) in files {
+        vault.insert(*path, *contents);
+    }
+    vault
+}
+
+/// Every file of the site built from `vault`, by output-relative path.
+pub fn build(vault: &MemoryVault, config: &Config) -> BTreeMap<PathBuf, Vec<u8>> {
+    let output = MemoryOutput::new();
+    if let Err(e) = build_site_with(vault, &output, config) {
+        panic!("{e}");
+    }
+    output.into_files()
+}
+
+/// The text of the file written at `path`.
+pub fn page(files: &BTreeMap<PathBuf, Vec<u8>>, path: &str) -> String {
+    match files.get(Path::new(path)) {
+        Some(contents) => String::from_utf8_lossy(contents).into_owned(),
+        None => panic!("{path} was not written"),
+    }
+}
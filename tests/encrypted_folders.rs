@@ -0,0 +1,132 @@
+//! Builds a vault with an `[[encrypted_folder]]` and every site-wide page turned
+//! on, and checks that nothing the folder's note says reaches the output, or the
+//! cache, in the clear.
+
+use std::fs;
+use std::path::Path;
+use obs2web::book::BookConfig;
+use obs2web::build_site_with;
+use obs2web::config::Config;
+use obs2web::encrypt::EncryptedFolder;
+use obs2web::vfs::{MemoryOutput, MemoryVault};
+
+const SECRETS: &[&str] = &["Operation Nightjar", "swordfish-4412", "the harbour at dawn"];
+
+fn vault(plan: &str) -> MemoryVault {
+    let mut vault = MemoryVault::new();
+    vault.insert(
+        "Welcome.md",
+        "---\ndate: 2026-01-01\nauthor: Jane Doe\ntags: [flashcard]\nlocation: [51.5, -0.1]\n---\n\
+         # Welcome\n\nPublic notes live here.\n\nCapital of France::Paris\n",
+    );
+    vault.insert(
+        "Secret/Plans.md",
+        format!(
+            "---\ndate: 2026-01-02\nauthor: Jane Doe\ntags: [flashcard]\nlocation: [48.8, 2.3]\n---\n\
+             # Operation Nightjar\n\nThe launch code is swordfish-4412.\n\nMeeting point::{plan}\n"
+        ),
+    );
+    vault
+}
+
+fn assert_no_secrets(path: &Path, contents: &[u8]) {
+    let text = String::from_utf8_lossy(contents);
+    for secret in SECRETS {
+        assert!(!text.contains(secret), "{} shows {secret:?} in the clear", path.display());
+    }
+}
+
+#[test]
+fn encrypted_notes_reach_no_output_in_the_clear() {
+    let cache = tempfile::tempdir().unwrap();
+    let config = Config {
+        site_url: Some("https://example.com".to_string()),
+        encrypted_folders: vec![EncryptedFolder {
+            folder: "Secret".into(),
+            password: Some("correct horse battery staple".to_string()),
+            ..EncryptedFolder::default()
+        }],
+        books: vec![BookConfig {
+            title: "Everything".to_string(),
+            chapters: vec!["Welcome".to_string(), "Secret/Plans".to_string()],
+            ..BookConfig::default()
+        }],
+        changes_page: true,
+        cache_dir: cache.path().to_path_buf(),
+        notes_table: true,
+        random_note: true,
+        command_palette: true,
+        flashcards: true,
+        flashcard_tag: "flashcard".to_string(),
+        timeline: true,
+        map: true,
+        author_pages: true,
+        note_metadata: true,
+        markdown_files: true,
+        activitypub: true,
+        sitemap: true,
+        search: true,
+        recent_notes: 10,
+        ..Config::default()
+    };
+
+    // The second build has a change to list, so the changes page shows notes rather than only recording them
+    for plan in ["the harbour at dawn", "the harbour at dawn, by boat"] {
+        let output = MemoryOutput::new();
+        build_site_with(&vault(plan), &output, &config).unwrap();
+        let files = output.into_files();
+        assert!(files.contains_key(Path::new("Secret/Plans.html")));
+        assert!(String::from_utf8_lossy(&files[Path::new("Welcome.html")]).contains("Public notes live here"));
+        for (path, contents) in &files {
+            assert_no_secrets(path, contents);
+        }
+    }
+    for entry in fs::read_dir(cache.path()).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_file() {
+            assert_no_secrets(&path, &fs::read(&path).unwrap());
+        }
+    }
+}
+
+#[test]
+fn encrypted_pages_open_with_the_password() {
+    use aes_gcm::aead::{Aead, KeyInit};
+    use aes_gcm::{Aes256Gcm, Key, Nonce};
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD as BASE64;
+
+    let config = Config {
+        encrypted_folders: vec![EncryptedFolder {
+            folder: "Secret".into(),
+            password: Some("correct horse battery staple".to_string()),
+            ..EncryptedFolder::default()
+        }],
+        ..Config::default()
+    };
+    let output = MemoryOutput::new();
+    build_site_with(&vault("the harbour at dawn"), &output, &config).unwrap();
+    let shell = String::from_utf8(output.into_files()[Path::new("Secret/Plans.html")].clone()).unwrap();
+
+    // The page the shell hands its script, as the browser reads it
+    let json = shell
+        .split_once(r#"id="encrypted-page">"#)
+        .and_then(|(_, rest)| rest.split_once("</script>"))
+        .map(|(json, _)| json)
+        .unwrap();
+    let page: serde_json::Value = serde_json::from_str(json).unwrap();
+    let field = |name: &str| BASE64.decode(page[name].as_str().unwrap()).unwrap();
+    let mut key = [0; 32];
+    let iterations = page["iterations"].as_u64().unwrap() as u32;
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(b"correct horse battery staple", &field("salt"), iterations, &mut key);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let html = cipher.decrypt(Nonce::from_slice(&field("iv")), field("data").as_slice()).unwrap();
+    let html = String::from_utf8(html).unwrap();
+    assert!(html.contains("swordfish-4412"));
+    assert!(html.contains("the harbour at dawn"));
+
+    let mut wrong = [0; 32];
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(b"wrong password", &field("salt"), iterations, &mut wrong);
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&wrong));
+    assert!(cipher.decrypt(Nonce::from_slice(&field("iv")), field("data").as_slice()).is_err());
+}
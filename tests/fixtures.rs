@@ -0,0 +1,12 @@
+//! Builds the fixture vaults in `fixtures/` and compares the output with their
+//! snapshots. After an intended rendering change, accept the new output with
+//! `cargo run -- test-fixtures --update` and review the snapshot diff.
+
+use std::path::Path;
+
+#[test]
+fn fixtures_match_snapshots() {
+    if let Err(e) = obs2web::fixtures::run_fixtures(Path::new("fixtures"), false) {
+        panic!("{e}");
+    }
+}
@@ -0,0 +1,31 @@
+//! The Anki deck and review page written from notes tagged `flashcard_tag`.
+
+mod common;
+
+use obs2web::config::Config;
+use common::{build, page, vault};
+
+#[test]
+fn tagged_notes_give_cards_in_every_form() {
+    let deck = "---\ntags: [flashcards/geography]\n---\n\
+                Capital of France::Paris\n\n\
+                Red planet:::Mars\n\n\
+                Largest ocean\n?\nThe Pacific\n";
+    let untagged = "Not a card::Left out\n";
+    let config = Config { flashcards: true, ..Config::default() };
+    let files = build(&vault(&[("Deck.md", deck), ("Other.md", untagged)]), &config);
+
+    let tsv = page(&files, "flashcards.tsv");
+    let cards: Vec<&str> = tsv.lines().filter(|line| !line.starts_with('#')).collect();
+    assert_eq!(cards.len(), 4, "{tsv}");
+    assert!(cards.contains(&"\"Capital of France\"\t\"<p>Paris</p>\"\t\"flashcards::geography\""), "{tsv}");
+    // A `:::` card is asked both ways
+    assert!(cards.iter().any(|card| card.starts_with("\"Red planet\"\t")));
+    assert!(cards.iter().any(|card| card.starts_with("\"Mars\"\t")));
+    assert!(cards.iter().any(|card| card.starts_with("\"Largest ocean\"\t\"<p>The Pacific</p>\"")));
+    assert!(!tsv.contains("Left out"));
+
+    let review = page(&files, "flashcards.html");
+    assert_eq!(review.matches("<li class=\"flashcard\">").count(), 4);
+    assert!(files.contains_key(std::path::Path::new("flashcards.js")));
+}
@@ -0,0 +1,32 @@
+//! Folding H2 and H3 sections, for the whole site or one note.
+
+mod common;
+
+use obs2web::config::Config;
+use common::{build, page, vault};
+
+const NOTE: &str = "# Title\n\nIntro.\n\n## First\n\nOne.\n\n### Nested\n\nTwo.\n\n#### Deep\n\nThree.\n\n## Second\n\nFour.\n";
+const FOLD: &str = "<details class=\"fold\" open>";
+
+#[test]
+fn fold_sections_folds_h2_and_h3_sections() {
+    let files = build(&vault(&[("Note.md", NOTE)]), &Config { fold_sections: true, ..Config::default() });
+    let html = page(&files, "Note.html");
+    assert_eq!(html.matches(FOLD).count(), 3);
+    assert_eq!(html.matches("<div class=\"fold-content\">").count(), 3);
+    // Text before the first H2 isn't folded
+    assert!(html.find("Intro.").unwrap() < html.find(FOLD).unwrap());
+}
+
+#[test]
+fn fold_frontmatter_overrides_the_site_setting() {
+    let folded = format!("---\nfold: true\n---\n{NOTE}");
+    let files = build(&vault(&[("Folded.md", folded.as_str()), ("Plain.md", NOTE)]), &Config::default());
+    assert!(page(&files, "Folded.html").contains(FOLD));
+    assert!(!page(&files, "Plain.html").contains(FOLD));
+
+    let unfolded = format!("---\nfold: false\n---\n{NOTE}");
+    let config = Config { fold_sections: true, ..Config::default() };
+    let files = build(&vault(&[("Unfolded.md", unfolded.as_str())]), &config);
+    assert!(!page(&files, "Unfolded.html").contains(FOLD));
+}
@@ -0,0 +1,36 @@
+//! Gallery pages of the folders holding a `_gallery.md` or listed in `galleries`.
+
+mod common;
+
+use std::path::Path;
+use obs2web::config::Config;
+use common::{build, page, vault};
+
+const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="800" height="600"><rect width="800" height="600" fill="teal"/></svg>"#;
+
+#[test]
+fn gallery_folders_get_a_page_of_their_images() {
+    let vault = vault(&[
+        ("Photos/_gallery.md", "---\ntitle: Summer\n---\nPictures from the coast.\n"),
+        ("Photos/beach.jpg", "not really a jpeg"),
+        ("Photos/sunset.svg", SVG),
+        ("Photos/Nested/deep.jpg", "not really a jpeg"),
+        ("Trips/train.jpg", "not really a jpeg"),
+    ]);
+    let files = build(&vault, &Config { galleries: vec!["Trips".into()], ..Config::default() });
+
+    let html = page(&files, "Photos/gallery.html");
+    assert!(html.contains("Summer"));
+    assert!(html.contains("Pictures from the coast."));
+    assert_eq!(html.matches("<div class=\"lightbox\"").count(), 2);
+    assert!(html.contains("beach.jpg"));
+    assert!(!html.contains("deep.jpg"));
+    // An SVG gets a PNG thumbnail; a JPEG is shown as it is
+    assert!(files.contains_key(Path::new("thumbnails/Photos/sunset.png")));
+    assert!(html.contains("thumbnails/Photos/sunset.png"));
+    assert!(!files.contains_key(Path::new("Photos/_gallery.html")));
+
+    let trips = page(&files, "Trips/gallery.html");
+    assert!(trips.contains("train.jpg"));
+    assert_eq!(trips.matches("<div class=\"lightbox\"").count(), 1);
+}
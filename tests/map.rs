@@ -0,0 +1,27 @@
+//! The map of notes with a `location`.
+
+mod common;
+
+use obs2web::config::Config;
+use common::{build, page, vault};
+
+#[test]
+fn notes_with_a_location_are_placed_on_the_map() {
+    let vault = vault(&[
+        ("Paris.md", "---\nlocation: [48.8566, 2.3522]\n---\n# Paris\n"),
+        ("Lisbon.md", "---\nlocation: \"38.7223, -9.1393\"\n---\n# Lisbon\n"),
+        ("Nowhere.md", "---\nlocation: \"somewhere nice\"\n---\n# Nowhere\n"),
+        ("Home.md", "# Home\n"),
+    ]);
+    let files = build(&vault, &Config { map: true, ..Config::default() });
+    let html = page(&files, "map.html");
+
+    assert!(html.contains("<span class=\"map-coordinates\">48.8566, 2.3522</span>"));
+    assert!(html.contains("<span class=\"map-coordinates\">38.7223, -9.1393</span>"));
+    // Places are listed by title
+    assert!(html.find(">Lisbon</a>").unwrap() < html.find(">Paris</a>").unwrap());
+    assert!(!html.contains(">Nowhere</a>"));
+    assert!(!html.contains(">Home</a>"));
+    assert!(html.contains("data-markers="));
+    assert!(files.contains_key(std::path::Path::new("map.js")));
+}
@@ -0,0 +1,43 @@
+//! Notes of a `[[note_type]]` get its template and schema.org type.
+
+mod common;
+
+use std::collections::BTreeMap;
+use obs2web::config::Config;
+use obs2web::structured_data::NoteType;
+use common::{build, page, vault};
+
+#[test]
+fn recipes_get_their_template_and_schema() {
+    let recipe = NoteType {
+        name: "recipe".to_string(),
+        template: Some("recipe.html".to_string()),
+        schema: Some("Recipe".to_string()),
+        properties: BTreeMap::from([
+            ("recipeIngredient".to_string(), "ingredients".to_string()),
+            ("recipeYield".to_string(), "servings".to_string()),
+        ]),
+    };
+    let config = Config { note_types: vec![recipe], structured_data: true, ..Config::default() };
+    let vault = vault(&[
+        (
+            "Pancakes.md",
+            "---\ntype: Recipe\nservings: 4\ningredients: [Flour, Milk]\nsteps: [Whisk, Fry]\n---\n# Pancakes\n\nServe warm.\n",
+        ),
+        ("Essay.md", "---\ntype: essay\n---\n# Essay\n\nWords.\n"),
+    ]);
+    let files = build(&vault, &config);
+
+    let html = page(&files, "Pancakes.html");
+    assert!(html.contains("<section class=\"recipe-card\">"));
+    assert!(html.contains("<dt>Serves</dt><dd>4</dd>"));
+    assert!(html.contains("<li>Flour</li>"));
+    assert!(html.contains("Serve warm."));
+    assert!(html.contains(r#""@type":"Recipe""#));
+    assert!(html.contains(r#""recipeIngredient":["Flour","Milk"]"#));
+    assert!(html.contains(r#""recipeYield":4"#));
+
+    let essay = page(&files, "Essay.html");
+    assert!(!essay.contains("recipe-card"));
+    assert!(essay.contains(r#""@type":"Article""#));
+}
@@ -0,0 +1,38 @@
+//! Builds a vault with `private_fields` set and checks that no output file holds
+//! a private value, whether it was written as frontmatter or as an inline field.
+
+use obs2web::build_site_with;
+use obs2web::config::Config;
+use obs2web::vfs::{MemoryOutput, MemoryVault};
+
+#[test]
+fn private_fields_reach_no_output() {
+    let mut vault = MemoryVault::new();
+    vault.insert(
+        "Project.md",
+        "---\nclient: Frontmatter Client\nstatus: active\n---\n# Project\n\n\
+         Client:: Line Client\n- client:: Listed Client\n\
+         Billed to [client:: Bracketed Client] and (client:: Parenthesized Client).\n\
+         Status:: shipped\n",
+    );
+    let config = Config {
+        private_fields: vec!["client".to_string()],
+        search: true,
+        note_metadata: true,
+        ..Config::default()
+    };
+    let output = MemoryOutput::new();
+    build_site_with(&vault, &output, &config).unwrap();
+
+    let files = output.into_files();
+    assert!(!files.is_empty());
+    for (path, contents) in &files {
+        let text = String::from_utf8_lossy(contents);
+        for value in ["Frontmatter Client", "Line Client", "Listed Client", "Bracketed Client", "Parenthesized Client"] {
+            assert!(!text.contains(value), "{} shows the private value {value:?}", path.display());
+        }
+    }
+    let page = String::from_utf8_lossy(&files[std::path::Path::new("Project.html")]);
+    assert!(page.contains("Billed to"));
+    assert!(page.contains("shipped"));
+}
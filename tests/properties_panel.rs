@@ -0,0 +1,50 @@
+//! The properties panel shown above notes from their frontmatter.
+
+mod common;
+
+use obs2web::config::Config;
+use common::{build, page, vault};
+
+const NOTE: &str = "---\nstatus: draft\nrating: 4\ndone: true\ntopics: [rust, web]\nsource: https://example.com/post\n\
+                    related: \"[[Other|The other note]]\"\nempty: []\n---\n# Note\n\nText.\n";
+
+fn panel(html: &str) -> &str {
+    let start = html.find("<dl class=\"properties\">").expect("the note has no properties panel");
+    let end = start + html[start..].find("</dl>").unwrap();
+    &html[start..end]
+}
+
+#[test]
+fn properties_panel_shows_each_kind_of_value() {
+    let config = Config { properties_panel: true, ..Config::default() };
+    let files = build(&vault(&[("Note.md", NOTE), ("Other.md", "# Other\n")]), &config);
+    let html = page(&files, "Note.html");
+    let panel = panel(&html);
+
+    assert!(panel.contains("<div class=\"property property-text\"><dt>status</dt><dd>draft</dd></div>"));
+    assert!(panel.contains("<div class=\"property property-number\"><dt>rating</dt><dd>4</dd></div>"));
+    assert!(panel.contains("aria-label=\"Yes\""));
+    assert!(panel.contains("<ul><li>rust</li><li>web</li></ul>"));
+    assert!(panel.contains("<a href=\"https://example.com/post\">https://example.com/post</a>"));
+    assert!(panel.contains(">The other note</a>"));
+    assert!(!panel.contains("<dt>empty</dt>"));
+    assert!(!page(&files, "Other.html").contains("<dl class=\"properties\">"));
+}
+
+#[test]
+fn properties_show_and_hide_pick_the_fields() {
+    let shown = Config {
+        properties_panel: true,
+        properties_show: vec!["status".to_string(), "rating".to_string()],
+        properties_hide: vec!["rating".to_string()],
+        ..Config::default()
+    };
+    let html = page(&build(&vault(&[("Note.md", NOTE)]), &shown), "Note.html");
+    let panel = panel(&html);
+    assert!(panel.contains("<dt>status</dt>"));
+    assert!(!panel.contains("<dt>rating</dt>"));
+    assert!(!panel.contains("<dt>topics</dt>"));
+
+    let html = page(&build(&vault(&[("Note.md", NOTE)]), &Config::default()), "Note.html");
+    assert!(!html.contains("<dl class=\"properties\">"));
+}
@@ -0,0 +1,27 @@
+//! Spoilers written as callouts and as `>!` quotes start folded.
+
+mod common;
+
+use obs2web::config::Config;
+use common::{build, page, vault};
+
+const SPOILER: &str = "<details class=\"callout callout-spoiler\" data-callout=\"spoiler\">";
+
+#[test]
+fn spoiler_quotes_and_callouts_start_folded() {
+    let note = "# Quiz\n\n>! The answer is 42.\n\nBetween.\n\n> [!spoiler] Hint\n> Think of towels.\n\nAfter.\n";
+    let html = page(&build(&vault(&[("Quiz.md", note)]), &Config::default()), "Quiz.html");
+    assert_eq!(html.matches(SPOILER).count(), 2);
+    assert!(html.contains("The answer is 42."));
+    assert!(!html.contains("! The answer"));
+    assert!(html.contains("<summary class=\"callout-title\">Hint</summary>"));
+}
+
+#[test]
+fn quotes_starting_with_an_exclamation_mark_stay_quotes() {
+    let note = "> !important: read this first.\n";
+    let html = page(&build(&vault(&[("Note.md", note)]), &Config::default()), "Note.html");
+    assert!(!html.contains("callout-spoiler"));
+    assert!(html.contains("<blockquote>"));
+    assert!(html.contains("!important: read this first."));
+}
@@ -0,0 +1,25 @@
+//! The timeline of notes by `event_date`, or else `date`, grouped by year.
+
+mod common;
+
+use obs2web::config::Config;
+use common::{build, page, vault};
+
+#[test]
+fn notes_are_placed_by_event_date_or_date() {
+    let vault = vault(&[
+        ("Hastings.md", "---\nevent_date: \"1066\"\ndate: 2024-01-10\n---\n# Battle of Hastings\n"),
+        ("Journal.md", "---\ndate: 2024-03-05\n---\n# Journal\n"),
+        ("Earlier.md", "---\ndate: 2024-02-01\n---\n# Earlier entry\n"),
+        ("Undated.md", "# Undated\n"),
+    ]);
+    let html = page(&build(&vault, &Config { timeline: true, ..Config::default() }), "timeline.html");
+
+    let position = |text: &str| html.find(text).unwrap_or_else(|| panic!("{text} is not on the timeline"));
+    assert!(position("id=\"year-1066\"") < position("id=\"year-2024\""));
+    assert!(position("Battle of Hastings") < position("id=\"year-2024\""));
+    assert!(position("Earlier entry") < position(">Journal<"));
+    assert!(html.contains("<li class=\"timeline-event\"><time datetime=\"1066\">"));
+    assert_eq!(html.matches("<li class=\"timeline-note\">").count(), 2);
+    assert!(!html.contains("Undated"));
+}
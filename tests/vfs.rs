@@ -0,0 +1,56 @@
+//! Builds vaults held in memory, put together file by file and read from a zip,
+//! into a site held in memory.
+
+mod common;
+
+use std::io::{Cursor, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use obs2web::config::Config;
+use obs2web::vfs::{MemoryVault, VaultSource};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+use common::{build, page, vault};
+
+#[test]
+fn memory_vault_builds_into_memory_output() {
+    let mut vault = vault(&[
+        ("Home.md", "# Home\n\nSee [[Guides/Setup]] for the setup.\n"),
+        ("Guides/Setup.md", "# Setup\n\nInstall it.\n"),
+        (".obsidian/app.json", "{}"),
+        ("obs2web.toml", "site_name = \"Ignored\"\n"),
+    ]);
+    vault.insert("logo.png", b"not really a png".to_vec());
+    let files = build(&vault, &Config::default());
+
+    assert!(page(&files, "Home.html").contains("Guides/Setup.html"));
+    assert!(page(&files, "Guides/Setup.html").contains("Install it."));
+    assert_eq!(files[Path::new("logo.png")], b"not really a png");
+    assert!(files.contains_key(Path::new("index.html")));
+    assert!(files.contains_key(Path::new("style.css")));
+    assert!(!files.keys().any(|path| path.starts_with(".obsidian")));
+    assert!(!files.contains_key(Path::new("obs2web.toml")));
+}
+
+#[test]
+fn memory_vault_lists_only_published_files() {
+    let vault = vault(&[("Note.md", "Text"), ("Folder/.hidden.md", "Hidden"), ("obs2web.toml", "")]);
+
+    assert_eq!(vault.files(&Config::default()).unwrap(), vec![PathBuf::from("Note.md")]);
+    assert_eq!(vault.read_to_string(Path::new("Note.md")).unwrap(), "Text");
+    assert_eq!(vault.read(Path::new("Missing.md")).unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn memory_vault_reads_a_zip_without_escaping_entries() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, contents) in [("Notes/Hello.md", "# Hello\n"), ("../Escape.md", "# Escape\n")] {
+        zip.start_file(name, SimpleFileOptions::default()).unwrap();
+        zip.write_all(contents.as_bytes()).unwrap();
+    }
+    zip.add_directory("Empty/", SimpleFileOptions::default()).unwrap();
+    let archive = zip.finish().unwrap();
+
+    let vault = MemoryVault::from_zip(Cursor::new(archive.into_inner())).unwrap();
+    assert_eq!(vault.files(&Config::default()).unwrap(), vec![PathBuf::from("Notes/Hello.md")]);
+    assert!(page(&build(&vault, &Config::default()), "Notes/Hello.html").contains("Hello"));
+}